@@ -3,14 +3,132 @@ pub mod images {
     use tinybmp::Bmp;
 
     use crate::consts;
+
+    /// The bundled images
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ImageId {
+        Papaj,
+        PapajMid,
+        PapajSmol,
+    }
+
+    impl ImageId {
+        pub const ALL: [ImageId; 3] = [ImageId::Papaj, ImageId::PapajMid, ImageId::PapajSmol];
+    }
+
+    /// Decoded image, the placeholder if the file didn't decode
+    pub fn image(id: ImageId) -> &'static Bmp<'static, consts::ColorType> {
+        match id {
+            ImageId::Papaj => &PAPAJ,
+            ImageId::PapajMid => &PAPAJ_MID,
+            ImageId::PapajSmol => &PAPAJ_SMOL,
+        }
+    }
+
+    /// Decode every image now, so broken files are logged at boot rather than the
+    /// first time some scene draws them
+    pub fn preload() {
+        for id in ImageId::ALL {
+            image(id);
+        }
+    }
+
+    // Name and contents of the file behind each image
+    fn file(id: ImageId) -> (&'static str, &'static [u8]) {
+        match id {
+            ImageId::Papaj => ("papaj.bmp", include_bytes!("../assets/images/papaj.bmp")),
+            ImageId::PapajMid => ("papaj_mid.bmp", include_bytes!("../assets/images/papaj_mid.bmp")),
+            ImageId::PapajSmol => ("papaj_smol.bmp", include_bytes!("../assets/images/papaj_smol.bmp")),
+        }
+    }
+
     lazy_static! {
-        pub static ref PAPAJ: Bmp<'static, consts::ColorType> = Bmp::from_slice(
-            include_bytes!("../assets/images/papaj.bmp")
-        ).unwrap();
+        static ref PAPAJ: Bmp<'static, consts::ColorType> = decode(file(ImageId::Papaj));
+        static ref PAPAJ_MID: Bmp<'static, consts::ColorType> = decode(file(ImageId::PapajMid));
+        static ref PAPAJ_SMOL: Bmp<'static, consts::ColorType> = decode(file(ImageId::PapajSmol));
+    }
 
-        pub static ref PAPAJ_SMOL: Bmp<'static, consts::ColorType> = Bmp::from_slice(
-            include_bytes!("../assets/images/papaj_smol.bmp")
-        ).unwrap();
+    fn decode((name, bytes): (&str, &'static [u8])) -> Bmp<'static, consts::ColorType> {
+        Bmp::from_slice(bytes).unwrap_or_else(|e| {
+            log::error!("Image {} doesn't decode ({:?}), drawing a placeholder", name, e);
+            Bmp::from_slice(&PLACEHOLDER).expect("placeholder is a valid BMP")
+        })
     }
-}
 
+    // Magenta and black checkerboard, with `Sprite::new` the magenta squares are transparent
+    const PLACEHOLDER_SIZE: usize = 16;
+    const PLACEHOLDER_CELL: usize = 4;
+    // 24 bit rows are padded to 4 bytes, 16 pixels need none
+    const PLACEHOLDER_ROW_BYTES: usize = PLACEHOLDER_SIZE * 3;
+    const BMP_HEADER_BYTES: usize = 54;
+    const PLACEHOLDER_BYTES: usize = BMP_HEADER_BYTES + PLACEHOLDER_SIZE * PLACEHOLDER_ROW_BYTES;
+    static PLACEHOLDER: [u8; PLACEHOLDER_BYTES] = placeholder_bmp();
+
+    // Same layout as the bundled images: file header, BITMAPINFOHEADER, bottom-up BGR rows
+    const fn placeholder_bmp() -> [u8; PLACEHOLDER_BYTES] {
+        let mut bytes = [0; PLACEHOLDER_BYTES];
+        bytes[0] = b'B';
+        bytes[1] = b'M';
+        put_u32(&mut bytes, 2, PLACEHOLDER_BYTES as u32);
+        put_u32(&mut bytes, 10, BMP_HEADER_BYTES as u32);
+        put_u32(&mut bytes, 14, 40);
+        put_u32(&mut bytes, 18, PLACEHOLDER_SIZE as u32);
+        put_u32(&mut bytes, 22, PLACEHOLDER_SIZE as u32);
+        bytes[26] = 1; // planes
+        bytes[28] = 24; // bits per pixel
+        put_u32(&mut bytes, 34, (PLACEHOLDER_SIZE * PLACEHOLDER_ROW_BYTES) as u32);
+
+        let mut y = 0;
+        while y < PLACEHOLDER_SIZE {
+            let mut x = 0;
+            while x < PLACEHOLDER_SIZE {
+                if (x / PLACEHOLDER_CELL + y / PLACEHOLDER_CELL).is_multiple_of(2) {
+                    let i = BMP_HEADER_BYTES + y * PLACEHOLDER_ROW_BYTES + x * 3;
+                    bytes[i] = 0xff; // blue
+                    bytes[i + 2] = 0xff; // red
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        bytes
+    }
+
+    const fn put_u32(bytes: &mut [u8; PLACEHOLDER_BYTES], at: usize, value: u32) {
+        let le = value.to_le_bytes();
+        bytes[at] = le[0];
+        bytes[at + 1] = le[1];
+        bytes[at + 2] = le[2];
+        bytes[at + 3] = le[3];
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use embedded_graphics::{
+            image::GetPixel as _,
+            prelude::{OriginDimensions as _, Point, RgbColor as _, Size},
+        };
+
+        use super::*;
+
+        #[test]
+        fn every_bundled_image_decodes() {
+            for id in ImageId::ALL {
+                let (name, bytes) = file(id);
+                assert!(Bmp::<consts::ColorType>::from_slice(bytes).is_ok(), "{name} doesn't decode");
+            }
+        }
+
+        #[test]
+        fn broken_files_become_the_placeholder() {
+            let placeholder = decode(("broken.bmp", b"BM not really"));
+            assert_eq!(placeholder.size(), Size::new(PLACEHOLDER_SIZE as u32, PLACEHOLDER_SIZE as u32));
+            // rows are stored bottom-up, the bottom left cell is magenta
+            let bottom = PLACEHOLDER_SIZE as i32 - 1;
+            let cell = PLACEHOLDER_CELL as i32;
+            assert_eq!(placeholder.pixel(Point::new(0, bottom)), Some(consts::ColorType::MAGENTA));
+            assert_eq!(placeholder.pixel(Point::new(cell, bottom)), Some(consts::ColorType::BLACK));
+            assert_eq!(placeholder.pixel(Point::new(cell, bottom - cell)), Some(consts::ColorType::MAGENTA));
+        }
+    }
+}
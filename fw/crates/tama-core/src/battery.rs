@@ -0,0 +1,144 @@
+// Li-ion cells sit on a plateau around 3.7 V for most of their charge, so a linear
+// voltage map reads far too high in the middle. Points are (volts, percent), highest first.
+const DISCHARGE_CURVE: &[(f32, u8)] = &[
+    (4.2, 100),
+    (3.9, 75),
+    (3.7, 50),
+    (3.5, 25),
+    (3.3, 10),
+    (3.0, 0),
+];
+
+/// Estimated charge of a single Li-ion cell, interpolated along `DISCHARGE_CURVE`
+pub fn voltage_to_percent(voltage: f32) -> u8 {
+    let (full_voltage, full_percent) = DISCHARGE_CURVE[0];
+    if voltage >= full_voltage {
+        return full_percent;
+    }
+
+    for pair in DISCHARGE_CURVE.windows(2) {
+        let (high_voltage, high_percent) = pair[0];
+        let (low_voltage, low_percent) = pair[1];
+        if voltage >= low_voltage {
+            let t = (voltage - low_voltage) / (high_voltage - low_voltage);
+            let percent = low_percent as f32 + t * (high_percent - low_percent) as f32;
+            return (percent + 0.5) as u8;
+        }
+    }
+
+    0
+}
+
+/// Below this the pet warns about the battery
+pub const LOW_BATTERY_PERCENT: u8 = 20;
+/// Below this the device shuts down
+pub const CRITICAL_BATTERY_PERCENT: u8 = 5;
+// a level is only left once the charge is this far back above its threshold,
+// so a reading wobbling around a threshold doesn't flap
+const BATTERY_HYSTERESIS_PERCENT: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryLevel {
+    Ok,
+    Low,
+    Critical,
+}
+
+impl BatteryLevel {
+    fn from_percent(percent: u8, margin: u8) -> Self {
+        if percent < CRITICAL_BATTERY_PERCENT + margin {
+            BatteryLevel::Critical
+        } else if percent < LOW_BATTERY_PERCENT + margin {
+            BatteryLevel::Low
+        } else {
+            BatteryLevel::Ok
+        }
+    }
+}
+
+/// Turns charge readings into `BatteryLevel` changes. Levels get worse as soon as a
+/// threshold is crossed, but only get better `BATTERY_HYSTERESIS_PERCENT` above it.
+#[derive(Debug)]
+pub struct BatteryMonitor {
+    level: BatteryLevel,
+}
+
+impl Default for BatteryMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatteryMonitor {
+    pub const fn new() -> Self {
+        Self { level: BatteryLevel::Ok }
+    }
+
+    /// The new level if `percent` changed it
+    pub fn update(&mut self, percent: u8) -> Option<BatteryLevel> {
+        let worse = BatteryLevel::from_percent(percent, 0);
+        let better = BatteryLevel::from_percent(percent, BATTERY_HYSTERESIS_PERCENT);
+
+        let level = if worse > self.level {
+            worse
+        } else if better < self.level {
+            better
+        } else {
+            return None;
+        };
+        self.level = level;
+        Some(level)
+    }
+
+    pub fn level(&self) -> BatteryLevel {
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_follows_the_curve_points() {
+        assert_eq!(voltage_to_percent(4.35), 100);
+        assert_eq!(voltage_to_percent(4.2), 100);
+        assert_eq!(voltage_to_percent(3.7), 50);
+        assert_eq!(voltage_to_percent(3.15), 5);
+        assert_eq!(voltage_to_percent(3.0), 0);
+        assert_eq!(voltage_to_percent(2.5), 0);
+    }
+
+    #[test]
+    fn percent_never_drops_as_the_voltage_rises() {
+        let mut last = 0;
+        for millivolts in 2800..4300 {
+            let percent = voltage_to_percent(millivolts as f32 / 1000.0);
+            assert!(percent >= last, "{} mV reads {}%, less than {}%", millivolts, percent, last);
+            last = percent;
+        }
+    }
+
+    #[test]
+    fn levels_get_worse_right_away_and_better_with_hysteresis() {
+        let mut monitor = BatteryMonitor::new();
+        let levels: alloc::vec::Vec<_> = [50, 20, 19, 10, 5, 4, 3, 5, 7, 8, 20, 22, 23]
+            .into_iter()
+            .map(|percent| monitor.update(percent))
+            .collect();
+        let (low, critical) = (Some(BatteryLevel::Low), Some(BatteryLevel::Critical));
+        assert_eq!(
+            levels,
+            [None, None, low, None, None, critical, None, None, None, low, None, None, Some(BatteryLevel::Ok)]
+        );
+        assert_eq!(monitor.level(), BatteryLevel::Ok);
+    }
+
+    #[test]
+    fn wobbling_around_a_threshold_changes_once() {
+        let mut monitor = BatteryMonitor::new();
+        let changes = [19, 20, 21, 19, 22, 20, 19].into_iter().filter_map(|percent| monitor.update(percent)).count();
+        assert_eq!(changes, 1);
+        assert_eq!(monitor.level(), BatteryLevel::Low);
+    }
+}
@@ -0,0 +1,132 @@
+// Backlight level from the ambient light, in percent like the backlight control
+
+pub const DEFAULT_MIN_BRIGHTNESS: u8 = 10;
+pub const DEFAULT_MAX_BRIGHTNESS: u8 = 100;
+// Smaller changes of the target are ignored so the backlight doesn't flicker
+// when the light level sits right between two steps
+const HYSTERESIS: u8 = 5;
+
+#[derive(Debug, Clone)]
+pub struct AutoBrightness {
+    min: u8,
+    max: u8,
+    /// pinned level, e.g. from a settings menu, overrides the light sensor
+    manual: Option<u8>,
+    current: u8,
+}
+
+impl Default for AutoBrightness {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_BRIGHTNESS, DEFAULT_MAX_BRIGHTNESS)
+    }
+}
+
+impl AutoBrightness {
+    pub fn new(min: u8, max: u8) -> Self {
+        let max = max.min(100);
+        let min = min.min(max);
+        Self {
+            min,
+            max,
+            manual: None,
+            current: max,
+        }
+    }
+
+    /// Backlight for a smoothed light level (0.0 dark - 1.0 bright), without hysteresis
+    pub fn target_brightness(&self, light: f32) -> u8 {
+        let light = light.clamp(0.0, 1.0);
+        let range = (self.max - self.min) as f32;
+        self.min + (light * range + 0.5) as u8
+    }
+
+    /// Feed a new light level, returns the backlight to use
+    pub fn update(&mut self, light: f32) -> u8 {
+        if let Some(level) = self.manual {
+            self.current = level;
+            return level;
+        }
+
+        let target = self.target_brightness(light);
+        // the ends of the range are always reached, otherwise it could stop just short of them
+        if target.abs_diff(self.current) >= HYSTERESIS || target == self.min || target == self.max {
+            self.current = target;
+        }
+        self.current
+    }
+
+    /// `Some` pins the backlight to that level, `None` goes back to following the light
+    pub fn set_manual(&mut self, level: Option<u8>) {
+        self.manual = level.map(|level| level.min(100));
+    }
+
+    pub fn is_manual(&self) -> bool {
+        self.manual.is_some()
+    }
+
+    pub fn current(&self) -> u8 {
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_range_is_clamped_to_percent() {
+        let brightness = AutoBrightness::new(10, 150);
+        assert_eq!(brightness.target_brightness(1.0), 100);
+        assert_eq!(brightness.target_brightness(7.0), 100);
+        assert_eq!(brightness.target_brightness(0.0), 10);
+        assert_eq!(brightness.target_brightness(-1.0), 10);
+        assert_eq!(brightness.current(), 100);
+
+        // a min over the max is pulled down to it
+        let mut brightness = AutoBrightness::new(50, 20);
+        for light in [0.0, 0.5, 1.0] {
+            assert_eq!(brightness.target_brightness(light), 20);
+            assert_eq!(brightness.update(light), 20);
+        }
+    }
+
+    #[test]
+    fn small_changes_are_ignored() {
+        let mut brightness = AutoBrightness::default();
+        assert_eq!(brightness.update(1.0), 100);
+        // a target of 97, too close to change
+        assert_eq!(brightness.update(0.97), 100);
+        assert_eq!(brightness.update(0.94), 95);
+        assert_eq!(brightness.update(0.92), 95);
+        assert_eq!(brightness.update(0.5), 55);
+        assert_eq!(brightness.update(0.54), 55);
+    }
+
+    #[test]
+    fn the_ends_of_the_range_are_always_reached() {
+        let mut brightness = AutoBrightness::default();
+        assert_eq!(brightness.update(0.9), 91);
+        assert_eq!(brightness.update(0.95), 96);
+        assert_eq!(brightness.update(1.0), 100);
+
+        assert_eq!(brightness.update(0.045), 14);
+        assert_eq!(brightness.update(0.0), 10);
+    }
+
+    #[test]
+    fn a_manual_level_overrides_the_light() {
+        let mut brightness = AutoBrightness::default();
+        brightness.set_manual(Some(40));
+        assert!(brightness.is_manual());
+        assert_eq!(brightness.update(1.0), 40);
+        assert_eq!(brightness.update(0.0), 40);
+        brightness.set_manual(Some(200));
+        assert_eq!(brightness.update(0.0), 100);
+
+        // back to following the light, from the manual level on
+        brightness.set_manual(None);
+        assert!(!brightness.is_manual());
+        assert_eq!(brightness.update(0.98), 100);
+        assert_eq!(brightness.update(0.5), 55);
+    }
+}
@@ -1,4 +1,131 @@
 // Platform-agnostic buzzer trait
 pub trait BuzzerTrait: Send {
     fn beep(&self, frequency_hz: u32, duration_ms: u32);
+
+    /// Silence whatever is currently playing
+    fn stop(&self) {}
+
+    /// Loudness of the following beeps, 0 (muted) to `MAX_VOLUME`
+    fn set_volume(&self, _level: u8) {}
+}
+
+pub const MAX_VOLUME: u8 = 100;
+
+/// Buzzer that plays nothing and remembers every beep, so tests can check which tones a
+/// scene played. Clones share the recording: keep one and hand the other to
+/// `Engine::with_buzzer`.
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug, Clone, Default)]
+pub struct RecordingBuzzer {
+    tones: std::sync::Arc<std::sync::Mutex<alloc::vec::Vec<(u32, u32)>>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl RecordingBuzzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `(frequency_hz, duration_ms)` of every beep so far, oldest first
+    pub fn tones(&self) -> alloc::vec::Vec<(u32, u32)> {
+        self.tones.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.tones.lock().unwrap().clear();
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl BuzzerTrait for RecordingBuzzer {
+    fn beep(&self, frequency_hz: u32, duration_ms: u32) {
+        self.tones.lock().unwrap().push((frequency_hz, duration_ms));
+    }
+}
+
+pub const MELODY_MAX_NOTES: usize = 16;
+
+/// Sequence of `(frequency_hz, duration_ms)` notes, a frequency of 0 is a rest
+#[derive(Debug, Clone, Default)]
+pub struct Melody {
+    notes: heapless::Vec<(u32, u32), MELODY_MAX_NOTES>,
+}
+
+impl Melody {
+    /// Notes past `MELODY_MAX_NOTES` are dropped
+    pub fn from_notes(notes: &[(u32, u32)]) -> Self {
+        let mut melody = Self::default();
+        for &note in notes.iter().take(MELODY_MAX_NOTES) {
+            let _ = melody.notes.push(note);
+        }
+        melody
+    }
+}
+
+pub mod melodies {
+    pub const STARTUP: &[(u32, u32)] = &[
+        (293, 96), (329, 96), (349, 96), (329, 96), (293, 96),
+        (261, 96), (261, 96), (261, 96), (261, 96), (261, 96),
+    ];
+
+    pub const GAME_OVER: &[(u32, u32)] = &[(392, 150), (349, 150), (330, 150), (262, 400)];
+}
+
+/// Plays a melody one note at a time without blocking, driven by `tick`
+#[derive(Debug, Default)]
+pub struct MelodyPlayer {
+    melody: Melody,
+    next_note: usize,
+    remaining_ms: u32,
+    playing: bool,
+}
+
+impl MelodyPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces whatever was playing, the first note starts right away
+    pub fn play(&mut self, melody: Melody, buzzer: &dyn BuzzerTrait) {
+        self.melody = melody;
+        self.next_note = 0;
+        self.playing = true;
+        self.start_next_note(buzzer);
+    }
+
+    /// Drops the rest of the melody and silences the note that's playing
+    pub fn stop(&mut self, buzzer: &dyn BuzzerTrait) {
+        if self.playing {
+            self.playing = false;
+            buzzer.stop();
+        }
+    }
+
+    pub fn tick(&mut self, dt_ms: u32, buzzer: &dyn BuzzerTrait) {
+        if !self.playing {
+            return;
+        }
+
+        self.remaining_ms = self.remaining_ms.saturating_sub(dt_ms);
+        if self.remaining_ms == 0 {
+            self.start_next_note(buzzer);
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    fn start_next_note(&mut self, buzzer: &dyn BuzzerTrait) {
+        let Some(&(frequency_hz, duration_ms)) = self.melody.notes.get(self.next_note) else {
+            self.playing = false;
+            return;
+        };
+
+        if frequency_hz > 0 {
+            buzzer.beep(frequency_hz, duration_ms);
+        }
+        self.next_note += 1;
+        self.remaining_ms = duration_ms;
+    }
 }
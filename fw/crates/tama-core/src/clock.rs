@@ -0,0 +1,142 @@
+/// Monotonic time source provided by the platform, for its frame timing and the engine's
+/// `FrameStats` (see `Engine::set_clock`)
+pub trait Clock: Send {
+    /// Microseconds since an arbitrary start, never goes backwards
+    fn now_us(&self) -> u64;
+
+    /// Milliseconds since the same start, wraps after ~49 days so compare with `wrapping_sub`
+    fn now_ms(&self) -> u32 {
+        (self.now_us() / 1000) as u32
+    }
+}
+
+pub const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+const MS_PER_DAY: u32 = SECONDS_PER_DAY * 1000;
+const HOUR_S: u32 = 60 * 60;
+// daylight fades in over the hour after dawn and out over the hour before dusk
+const DAWN_S: u32 = 6 * HOUR_S;
+const DUSK_S: u32 = 20 * HOUR_S;
+const TWILIGHT_S: u32 = HOUR_S;
+
+/// Time of day kept in software, there's no RTC. Runs on the `dt_ms` of the engine's
+/// updates from wherever it was set, and resumes from the time of the last save on boot,
+/// so the time powered off is lost unless the platform has a wall clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayClock {
+    /// since midnight, below `MS_PER_DAY`
+    ms: u32,
+}
+
+impl DayClock {
+    /// Starting at `seconds` past midnight, wrapped to a day
+    pub const fn new(seconds: u32) -> Self {
+        Self { ms: (seconds % SECONDS_PER_DAY) * 1000 }
+    }
+
+    pub fn advance(&mut self, dt_ms: u32) {
+        self.ms = ((self.ms as u64 + dt_ms as u64) % MS_PER_DAY as u64) as u32;
+    }
+
+    /// Set the time to `seconds` past midnight, wrapped to a day
+    pub fn set(&mut self, seconds: u32) {
+        *self = Self::new(seconds);
+    }
+
+    /// Move the time by `delta_s`, wrapping around midnight either way
+    pub fn shift(&mut self, delta_s: i32) {
+        self.set((self.seconds() as i64 + delta_s as i64).rem_euclid(SECONDS_PER_DAY as i64) as u32);
+    }
+
+    /// Since midnight
+    pub fn seconds(&self) -> u32 {
+        self.ms / 1000
+    }
+
+    pub fn hours(&self) -> u32 {
+        self.seconds() / HOUR_S
+    }
+
+    pub fn minutes(&self) -> u32 {
+        self.seconds() / 60 % 60
+    }
+
+    /// Between dusk (20:00) and dawn (06:00)
+    pub fn is_night(&self) -> bool {
+        let seconds = self.seconds();
+        !(DAWN_S..DUSK_S).contains(&seconds)
+    }
+
+    /// 1 in full daylight, 0 at night, in between during the hour after dawn and before dusk
+    pub fn daylight_factor(&self) -> f32 {
+        let seconds = self.seconds();
+        if self.is_night() {
+            0.0
+        } else if seconds < DAWN_S + TWILIGHT_S {
+            (seconds - DAWN_S) as f32 / TWILIGHT_S as f32
+        } else if seconds >= DUSK_S - TWILIGHT_S {
+            (DUSK_S - seconds) as f32 / TWILIGHT_S as f32
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for DayClock {
+    /// Noon, so a fresh device starts out in daylight
+    fn default() -> Self {
+        Self::new(12 * HOUR_S)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hours: u32, minutes: u32) -> DayClock {
+        DayClock::new(hours * HOUR_S + minutes * 60)
+    }
+
+    #[test]
+    fn time_wraps_to_a_day() {
+        assert_eq!(DayClock::new(SECONDS_PER_DAY + 90), DayClock::new(90));
+        assert_eq!(DayClock::default().hours(), 12);
+
+        let mut clock = at(23, 59);
+        clock.advance(2 * 60 * 1000);
+        assert_eq!((clock.hours(), clock.minutes()), (0, 1));
+        clock.advance(u32::MAX);
+        assert_eq!(clock.seconds(), ((60 * 1000 + u32::MAX as u64) % MS_PER_DAY as u64 / 1000) as u32);
+    }
+
+    #[test]
+    fn shifting_wraps_around_midnight_both_ways() {
+        let mut clock = at(0, 30);
+        clock.shift(-(HOUR_S as i32));
+        assert_eq!((clock.hours(), clock.minutes()), (23, 30));
+        clock.shift(HOUR_S as i32);
+        assert_eq!((clock.hours(), clock.minutes()), (0, 30));
+        clock.shift(i32::MIN);
+        assert!(clock.seconds() < SECONDS_PER_DAY);
+    }
+
+    #[test]
+    fn night_is_from_dusk_to_dawn() {
+        assert!(at(5, 59).is_night());
+        assert!(!at(6, 0).is_night());
+        assert!(!at(19, 59).is_night());
+        assert!(at(20, 0).is_night());
+        assert!(at(0, 0).is_night());
+    }
+
+    #[test]
+    fn daylight_fades_in_and_out() {
+        assert_eq!(at(3, 0).daylight_factor(), 0.0);
+        assert_eq!(at(6, 0).daylight_factor(), 0.0);
+        assert_eq!(at(6, 30).daylight_factor(), 0.5);
+        assert_eq!(at(7, 0).daylight_factor(), 1.0);
+        assert_eq!(at(12, 0).daylight_factor(), 1.0);
+        assert_eq!(at(19, 0).daylight_factor(), 1.0);
+        assert_eq!(at(19, 45).daylight_factor(), 0.25);
+        assert_eq!(at(20, 0).daylight_factor(), 0.0);
+    }
+}
@@ -1,10 +1,16 @@
 use alloc::boxed::Box;
+use core::fmt::Write as _;
 use embedded_graphics::{
-    prelude::DrawTarget,
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_10X20}},
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size},
+    primitives::{Circle, PrimitiveStyle, Rectangle},
+    text::{Alignment, Text},
 };
+use heapless::Vec;
 use rand::{SeedableRng, rngs::SmallRng};
 
-use crate::{buzzer::BuzzerTrait, consts, input::Input, output::Output, scenes::{Scene as _, SceneWrapper, UpdateResult, selftest::SelfTestScene}};
+use crate::{assets::images, battery::{BatteryLevel, BatteryMonitor, voltage_to_percent}, buzzer::{BuzzerTrait, MelodyPlayer}, clock::{Clock, DayClock, SECONDS_PER_DAY}, consts, gfx::RenderError, input::{Button, Input, SensorState, SensorType}, log_buffer::LogBuffer, output::Output, pet::Pet, settings::{MemorySettingsStore, Settings, SettingsStore}, toast::ToastQueue, uptime::{self, DURATION_STRING_LEN, Uptime}, scenes::{Scene as _, SceneWrapper, UpdateResult, dvd::DvdScene, selftest::{SelfTestCheck, SelfTestScene}}};
 
 // Default stub buzzer for embedded targets
 pub struct StubBuzzer;
@@ -15,11 +21,127 @@ impl BuzzerTrait for StubBuzzer {
     }
 }
 
+/// Frames the rolling FPS average is taken over
+pub const FRAME_STATS_WINDOW: usize = 30;
+
+/// Timing of the last frames, recorded when the platform sets a clock with `Engine::set_clock`
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    update_us: u32,
+    render_us: u32,
+    last_frame_start_us: Option<u64>,
+    /// time between the starts of consecutive frames, oldest overwritten first
+    frame_times_us: [u32; FRAME_STATS_WINDOW],
+    next_slot: usize,
+    recorded: usize,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameStats {
+    pub const fn new() -> Self {
+        Self {
+            update_us: 0,
+            render_us: 0,
+            last_frame_start_us: None,
+            frame_times_us: [0; FRAME_STATS_WINDOW],
+            next_slot: 0,
+            recorded: 0,
+        }
+    }
+
+    // A frame starts with its update
+    fn record_update(&mut self, start_us: u64, end_us: u64) {
+        if let Some(last_start_us) = self.last_frame_start_us {
+            self.frame_times_us[self.next_slot] = start_us.saturating_sub(last_start_us) as u32;
+            self.next_slot = (self.next_slot + 1) % FRAME_STATS_WINDOW;
+            self.recorded = (self.recorded + 1).min(FRAME_STATS_WINDOW);
+        }
+        self.last_frame_start_us = Some(start_us);
+        self.update_us = end_us.saturating_sub(start_us) as u32;
+    }
+
+    fn record_render(&mut self, start_us: u64, end_us: u64) {
+        self.render_us = end_us.saturating_sub(start_us) as u32;
+    }
+
+    pub fn update_us(&self) -> u32 {
+        self.update_us
+    }
+
+    pub fn render_us(&self) -> u32 {
+        self.render_us
+    }
+
+    /// Average FPS over the last `FRAME_STATS_WINDOW` frames in hundredths, 3000 = 30 FPS
+    pub fn fps_x100(&self) -> u32 {
+        let total_us: u64 = self.frame_times_us[..self.recorded].iter().map(|&us| us as u64).sum();
+        if total_us == 0 {
+            return 0;
+        }
+        (self.recorded as u64 * 100 * 1_000_000 / total_us) as u32
+    }
+}
+
+const SCENE_STACK_DEPTH: usize = 8;
+// the rng starts from this unless `with_seed` is used, so runs on desktop are reproducible
+const DEFAULT_SEED: u64 = 2137;
+// fixed-step scenes catch up at most this many steps per frame, time beyond that is dropped
+const MAX_FIXED_STEPS: u32 = 4;
+// smoothed light level (0..1) below which the pet goes to sleep
+const LIGHTS_OFF_LEVEL: f32 = 0.1;
+// dot in the top right corner while there's an unread notice, see `LogBuffer::has_unacknowledged_notice`
+const NOTICE_DOT_DIAMETER: u32 = 10;
+// time the critical battery warning is shown before powering off
+const CRITICAL_BATTERY_SHUTDOWN_MS: u32 = 10_000;
+// the device usually just loses power, so the pet is saved every now and then
+const AUTOSAVE_INTERVAL_MS: u32 = 5 * 60 * 1000;
+const BATTERY_LOW_TOAST_MS: u32 = 3000;
+
 pub struct Engine {
-    scene: SceneWrapper,
+    /// Active scene is the last one, the ones below are paused. Never empty.
+    scenes: Vec<SceneWrapper, SCENE_STACK_DEPTH>,
     buzzer: Box<dyn BuzzerTrait>,
+    melody_player: MelodyPlayer,
     rng: SmallRng,
+    /// what `rng` was seeded with, `reset` starts it over from here
+    seed: u64,
     input: Input,
+    pet: Pet,
+    date_seed: u32,
+    settings: Settings,
+    settings_store: Box<dyn SettingsStore>,
+    /// changed since the last `save_settings`
+    settings_unsaved: bool,
+    autosave_timer_ms: u32,
+    battery: BatteryMonitor,
+    /// counting down to powering off while the battery is critical
+    shutdown_countdown_ms: Option<u32>,
+    power_off_requested: bool,
+    /// `shutdown` ran, updating and rendering do nothing from then on
+    shut_down: bool,
+    /// time since a button was last held, for the screensaver
+    idle_ms: u32,
+    /// time not yet simulated by a fixed-step scene
+    step_accumulator_ms: u32,
+    /// the next frame is drawn whatever the scenes say, set when the scene stack changes
+    redraw_requested: bool,
+    /// indicators on the last drawn frame, a change redraws too
+    drawn_indicators: Indicators,
+    /// frames aren't timed without it
+    clock: Option<Box<dyn Clock>>,
+    /// unix seconds, see `set_wall_clock`
+    wall_clock: Option<fn() -> u64>,
+    stats: FrameStats,
+    uptime: Uptime,
+    day_clock: DayClock,
+    toasts: ToastQueue,
+    /// filled by the platform, see `logs_mut`
+    logs: LogBuffer,
 }
 
 impl Default for Engine {
@@ -30,74 +152,850 @@ impl Default for Engine {
 
 impl Engine {
     pub fn new() -> Self {
-        Self {
-            scene: SceneWrapper::from(SelfTestScene::new()),
-            buzzer: Box::new(StubBuzzer),
-            rng: SmallRng::seed_from_u64(2137),
-            input: Input::new(),
-        }
+        Self::with_buzzer(Box::new(StubBuzzer))
     }
 
     pub fn with_buzzer(buzzer: Box<dyn BuzzerTrait>) -> Self {
-        Self {
-            scene: SceneWrapper::from(SelfTestScene::new()),
+        Self::with_settings_store(buzzer, Box::new(MemorySettingsStore::default()))
+    }
+
+    /// Settings and the pet are loaded from `settings_store` right away
+    pub fn with_settings_store(buzzer: Box<dyn BuzzerTrait>, mut settings_store: Box<dyn SettingsStore>) -> Self {
+        images::preload();
+        let mut scenes = Vec::new();
+        let _ = scenes.push(SceneWrapper::from(SelfTestScene::new()));
+
+        let mut settings = settings_store.load().unwrap_or_else(|| {
+            log::info!("No saved settings, using defaults");
+            Settings::default()
+        });
+        settings.sessions = settings.sessions.saturating_add(1);
+        buzzer.set_volume(settings.volume);
+        let mut input = Input::new();
+        input.set_light_calibration(settings.light_calibration);
+
+        let mut engine = Self {
+            scenes,
             buzzer,
-            rng: SmallRng::seed_from_u64(2137),
-            input: Input::new(),
+            melody_player: MelodyPlayer::new(),
+            rng: SmallRng::seed_from_u64(DEFAULT_SEED),
+            seed: DEFAULT_SEED,
+            input,
+            pet: Pet::from_stats(settings.pet),
+            date_seed: 0,
+            settings,
+            settings_store,
+            // the session count went up
+            settings_unsaved: true,
+            autosave_timer_ms: 0,
+            battery: BatteryMonitor::new(),
+            shutdown_countdown_ms: None,
+            power_off_requested: false,
+            shut_down: false,
+            idle_ms: 0,
+            step_accumulator_ms: 0,
+            redraw_requested: true,
+            drawn_indicators: Indicators::default(),
+            clock: None,
+            wall_clock: None,
+            stats: FrameStats::new(),
+            uptime: Uptime::new(settings.lifetime_uptime_ms),
+            day_clock: DayClock::new(settings.time_of_day_s),
+            toasts: ToastQueue::new(),
+            logs: LogBuffer::new(),
+        };
+        engine.with_context(|scene, context| scene.on_enter(context));
+        engine
+    }
+
+    /// Add a platform check (an I2C device answering...) to the boot self-test, it runs
+    /// after the default ones. Only has an effect before the self-test finished.
+    pub fn with_self_test_check(mut self, check: SelfTestCheck) -> Self {
+        if let Some(SceneWrapper::SelfTestScene(scene)) = self.scenes.first_mut() {
+            scene.add_check(check);
+        }
+        self
+    }
+
+    /// Seed the rng scenes draw from (pipe layouts...), e.g. from a hardware RNG so
+    /// every boot plays differently
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self.seed = seed;
+        self
+    }
+
+    /// Start over as if just booted, e.g. for a factory reset: the scene stack is back to
+    /// the boot self-test, the rng is reseeded with the boot seed and the pet, input, battery
+    /// warnings, toasts and timers are fresh. The buzzer, settings store, clocks, uptime,
+    /// logs and frame stats are kept, and so are the settings. The saved pet is replaced by
+    /// the new one at the next save. Checks added with `with_self_test_check` don't run again.
+    pub fn reset(&mut self) {
+        log::info!("Engine reset");
+        // after a shutdown the active scene had its on_exit already
+        if !self.shut_down {
+            self.with_context(|scene, context| scene.on_exit(context));
+        }
+        self.shut_down = false;
+        self.scenes.clear();
+        let _ = self.scenes.push(SceneWrapper::from(SelfTestScene::new()));
+        self.melody_player.stop(&*self.buzzer);
+        self.toasts.clear();
+
+        self.rng = SmallRng::seed_from_u64(self.seed);
+        self.input = Input::new();
+        self.input.set_light_calibration(self.settings.light_calibration);
+        self.pet = Pet::new();
+        self.battery = BatteryMonitor::new();
+        self.shutdown_countdown_ms = None;
+        self.power_off_requested = false;
+        self.autosave_timer_ms = 0;
+        self.idle_ms = 0;
+        self.step_accumulator_ms = 0;
+        self.redraw_requested = true;
+
+        self.with_context(|scene, context| scene.on_enter(context));
+    }
+
+    /// Draw the next frame even if nothing changed, for platforms that drew over the last one
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    /// False when the last rendered frame is still up to date, the platform can skip
+    /// `render` and the transfer to the display then
+    pub fn needs_redraw(&self) -> bool {
+        if self.shut_down {
+            return false;
         }
+        self.redraw_requested
+            || self.shutdown_countdown_ms.is_some()
+            || self.indicators() != self.drawn_indicators
+            || self.toasts.needs_redraw()
+            || self.visible_scenes().iter().any(|scene| scene.needs_redraw())
     }
 
-    pub fn render<D>(&self, target: &mut D) -> Result<(), D::Error>
+    /// A frame the target failed to take is drawn again next time, one a scene couldn't
+    /// draw isn't, until something changes
+    pub fn render<D>(&mut self, target: &mut D) -> Result<(), RenderError<D::Error>>
     where
         D: DrawTarget<Color = consts::ColorType>,
     {
-        self.scene.draw(target)
-    }
-
-    pub fn update(&mut self) {
-        // Create Context on the fly with references to buzzer
-        let mut context = Context::new(&*self.buzzer);
-        // Temporarily swap input to avoid borrowing issues
-        core::mem::swap(&mut context.input, &mut self.input);
-        core::mem::swap(&mut context.rng, &mut self.rng);
-        
-        let result = self.scene.update(&mut context);
-        
-        // Swap back
-        core::mem::swap(&mut context.input, &mut self.input);
-        core::mem::swap(&mut context.rng, &mut self.rng);
+        if self.shut_down {
+            return Ok(());
+        }
+        let start_us = self.clock.as_ref().map(|clock| clock.now_us());
+        let result = self.draw_scenes(target);
+        if let (Some(clock), Some(start_us)) = (&self.clock, start_us) {
+            self.stats.record_render(start_us, clock.now_us());
+        }
+        if let Err(RenderError::Scene(reason)) = result {
+            log::error!("Scene can't be drawn: {}", reason);
+        }
+        if !matches!(result, Err(RenderError::Target(_))) {
+            self.redraw_requested = false;
+            self.drawn_indicators = self.indicators();
+            self.toasts.mark_drawn();
+        }
+        result
+    }
+
+    // The topmost opaque scene and the overlays on it, overlays get drawn over what's below them
+    fn visible_scenes(&self) -> &[SceneWrapper] {
+        let first_visible = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        &self.scenes[first_visible..]
+    }
+
+    fn indicators(&self) -> Indicators {
+        Indicators {
+            low_battery: self.battery.level() == BatteryLevel::Low && !self.input.is_charging(),
+            notice: self.logs.has_unacknowledged_notice(),
+        }
+    }
+
+    fn draw_scenes<D>(&self, target: &mut D) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        if let Some(countdown_ms) = self.shutdown_countdown_ms {
+            return draw_critical_battery(countdown_ms, target).map_err(RenderError::Target);
+        }
+
+        let theme = self.settings.theme.theme();
+        for scene in self.visible_scenes() {
+            scene.draw(target, theme)?;
+        }
+
+        let indicators = self.indicators();
+        if indicators.low_battery {
+            draw_low_battery_icon(theme.warning, target)?;
+        }
+        self.toasts.draw(target, theme)?;
+
+        if indicators.notice {
+            let top_left = Point::new((consts::WIDTH - NOTICE_DOT_DIAMETER - 4) as i32, 4);
+            Circle::new(top_left, NOTICE_DOT_DIAMETER)
+                .into_styled(PrimitiveStyle::with_fill(theme.warning))
+                .draw(target)?;
+        }
+
+        Ok(())
+    }
+
+    /// Advance by `dt_ms`, the real time since the last update. Only the topmost
+    /// scene is updated, the ones below it stay paused.
+    pub fn update(&mut self, dt_ms: u32) {
+        if self.shut_down {
+            return;
+        }
+        let start_us = self.clock.as_ref().map(|clock| clock.now_us());
+        self.uptime.advance(dt_ms);
+        self.update_scenes(dt_ms);
+        if let (Some(clock), Some(start_us)) = (&self.clock, start_us) {
+            self.stats.record_update(start_us, clock.now_us());
+        }
+    }
+
+    fn update_scenes(&mut self, dt_ms: u32) {
+        // the pet lives on whatever scene is active, and sleeps when it's dark or night
+        self.day_clock.advance(dt_ms);
+        let lights_off = self.input.get_sensor_state(SensorType::LightSensor) == SensorState::Normal
+            && self.input.get_sensor_value(SensorType::LightSensor) < LIGHTS_OFF_LEVEL;
+        self.pet.update(dt_ms, lights_off || self.day_clock.is_night());
+
+        self.autosave_timer_ms += dt_ms;
+        if self.autosave_timer_ms >= AUTOSAVE_INTERVAL_MS {
+            self.autosave_timer_ms = 0;
+            self.save_settings();
+        }
+
+        // melodies keep playing across scene changes, so the engine advances them, not the scenes
+        self.melody_player.tick(dt_ms, &*self.buzzer);
+        self.toasts.update(dt_ms);
+        self.update_battery(dt_ms);
+        self.update_screensaver(dt_ms);
+
+        // the notices are on screen now
+        if matches!(self.scenes.last(), Some(SceneWrapper::LogScene(_))) {
+            self.logs.acknowledge_notices();
+        }
+
+        let settings_before = self.settings;
+        let result = self.update_top_scene(dt_ms);
+        if self.settings != settings_before {
+            self.apply_settings();
+            self.settings_unsaved = true;
+        }
+
+        if !matches!(result, UpdateResult::None) {
+            // presses that started in the old scene don't carry over as holds
+            self.input.suppress_holds();
+            self.step_accumulator_ms = 0;
+            // the new top scene may not think it changed, but the screen still shows the old one
+            self.redraw_requested = true;
+        }
 
         match result {
             UpdateResult::ChangeScene(scene) => {
                 log::info!("Scene changed");
-                self.scene = scene;
+                self.with_context(|scene, context| scene.on_exit(context));
+                self.scenes.pop();
+                let _ = self.scenes.push(scene);
+                self.with_context(|scene, context| scene.on_enter(context));
+            }
+            UpdateResult::PushScene(scene) => {
+                log::info!("Scene pushed");
+                self.push_scene(scene);
+            }
+            UpdateResult::PopScene => {
+                if self.scenes.len() > 1 {
+                    log::info!("Scene popped");
+                    self.with_context(|scene, context| scene.on_exit(context));
+                    self.scenes.pop();
+                    self.with_context(|scene, context| scene.on_enter(context));
+                } else {
+                    log::error!("Can't pop the last scene");
+                }
             }
             UpdateResult::None => (),
         }
+
+        // e.g. a new high score, worth keeping right away. The settings screen changes them
+        // with every press, they're saved once it's left.
+        let editing_settings = matches!(self.scenes.last(), Some(SceneWrapper::SettingsScene(_)));
+        if self.settings_unsaved && !editing_settings {
+            self.save_settings();
+        }
+    }
+
+    // Settings the engine applies itself, the platform follows the rest (backlight) from `settings`
+    fn apply_settings(&mut self) {
+        self.buzzer.set_volume(self.settings.volume);
+        self.input.set_light_calibration(self.settings.light_calibration);
+    }
+
+    // Warns once the battery gets low. When it's critical the scenes are replaced by a
+    // warning and the platform is asked to power off after a countdown, unless it's charged
+    // back up in the meantime.
+    fn update_battery(&mut self, dt_ms: u32) {
+        if self.input.is_charging() {
+            // the voltage is up because of the charger, and the battery won't run out now
+            self.cancel_shutdown();
+        } else if self.input.get_sensor_state(SensorType::BatteryVoltage) == SensorState::Normal {
+            let percent = voltage_to_percent(self.input.get_sensor_value(SensorType::BatteryVoltage));
+            match self.battery.update(percent) {
+                Some(BatteryLevel::Critical) => {
+                    crate::notice!("Battery critical ({}%), powering off", percent);
+                    self.shutdown_countdown_ms = Some(CRITICAL_BATTERY_SHUTDOWN_MS);
+                }
+                Some(BatteryLevel::Low) => {
+                    crate::notice!("Battery low ({}%)", percent);
+                    self.toasts.push("Battery low", BATTERY_LOW_TOAST_MS);
+                    self.cancel_shutdown();
+                }
+                Some(BatteryLevel::Ok) => self.cancel_shutdown(),
+                None => (),
+            }
+        }
+
+        if let Some(countdown_ms) = self.shutdown_countdown_ms {
+            let countdown_ms = countdown_ms.saturating_sub(dt_ms);
+            self.shutdown_countdown_ms = Some(countdown_ms);
+            if countdown_ms == 0 && !self.power_off_requested {
+                log::info!("Battery critical, requesting power off");
+                self.power_off_requested = true;
+            }
+        }
+    }
+
+    fn cancel_shutdown(&mut self) {
+        // the warning covered the scenes
+        if self.shutdown_countdown_ms.take().is_some() {
+            self.redraw_requested = true;
+        }
+    }
+
+    // Push the screensaver over the current scene after `screensaver_timeout_s` without
+    // any button held. It pops itself on the next press.
+    fn update_screensaver(&mut self, dt_ms: u32) {
+        if Button::ALL.iter().any(|&button| self.input.is_pressed(button)) {
+            self.idle_ms = 0;
+            return;
+        }
+        self.idle_ms = self.idle_ms.saturating_add(dt_ms);
+
+        let timeout_s = self.settings.screensaver_timeout_s;
+        let is_showing = matches!(self.scenes.last(), Some(SceneWrapper::DvdScene(scene)) if scene.is_screensaver());
+        if timeout_s == 0 || is_showing || self.idle_ms < timeout_s as u32 * 1000 {
+            return;
+        }
+
+        log::info!("Idle for {} s, starting screensaver", timeout_s);
+        if !self.push_scene(SceneWrapper::from(DvdScene::screensaver())) {
+            self.idle_ms = 0;
+        }
+    }
+
+    // Pause the active scene and run `scene` over it, false when the stack is full
+    fn push_scene(&mut self, scene: SceneWrapper) -> bool {
+        if self.scenes.is_full() {
+            log::error!("Scene stack full, push ignored");
+            return false;
+        }
+        self.with_context(|scene, context| scene.on_exit(context));
+        let _ = self.scenes.push(scene);
+        self.with_context(|scene, context| scene.on_enter(context));
+        true
+    }
+
+    // Variable-step scenes get one update with the real `dt_ms`. Fixed-step ones get
+    // as many steps as fit in the accumulated time, which may be none this frame.
+    fn update_top_scene(&mut self, dt_ms: u32) -> UpdateResult {
+        let top = self.scenes.last().expect("scene stack can't be empty");
+        let Some(step_ms) = top.fixed_step_ms() else {
+            let result = self.with_context(|scene, context| {
+                context.dt_ms = dt_ms;
+                scene.update(context)
+            });
+            self.input.consume_edges();
+            return result;
+        };
+
+        self.step_accumulator_ms += dt_ms;
+        let steps = (self.step_accumulator_ms / step_ms).min(MAX_FIXED_STEPS);
+        self.step_accumulator_ms = if steps == MAX_FIXED_STEPS {
+            0
+        } else {
+            self.step_accumulator_ms - steps * step_ms
+        };
+
+        for _ in 0..steps {
+            let result = self.with_context(|scene, context| {
+                context.dt_ms = step_ms;
+                scene.update(context)
+            });
+            // presses and shakes of this frame count once, not once per step
+            self.input.consume_edges();
+            if !matches!(result, UpdateResult::None) {
+                return result;
+            }
+        }
+        UpdateResult::None
+    }
+
+    /// Orderly teardown before the platform exits or powers down. The active scene gets
+    /// its `on_exit` (the paused ones had theirs when they were covered), the buzzer is
+    /// silenced and the settings are saved. Afterwards `update`, `render` and `shutdown`
+    /// do nothing, until a `reset`.
+    pub fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        log::info!("Engine shutting down");
+        self.with_context(|scene, context| scene.on_exit(context));
+        self.shut_down = true;
+        self.buzzer.stop();
+        self.save_settings();
+    }
+
+    /// Write the settings and the current pet to the settings store
+    pub fn save_settings(&mut self) {
+        self.settings.pet = self.pet.stats();
+        self.settings.lifetime_uptime_ms = self.uptime.lifetime_ms();
+        self.settings.time_of_day_s = self.day_clock.seconds();
+        if let Some(wall_clock) = self.wall_clock {
+            self.settings.saved_at_s = wall_clock();
+        }
+        self.settings_store.save(&self.settings);
+        self.settings_unsaved = false;
+    }
+
+    // Create Context on the fly with references to the engine state and run `f` on the topmost scene
+    fn with_context<R>(&mut self, f: impl FnOnce(&mut SceneWrapper, &mut Context) -> R) -> R {
+        let mut context = Context {
+            rng: &mut self.rng,
+            input: &mut self.input,
+            output: Output::new(&*self.buzzer, &mut self.melody_player),
+            pet: &mut self.pet,
+            settings: &mut self.settings,
+            logs: &self.logs,
+            uptime: &self.uptime,
+            toasts: &mut self.toasts,
+            day_clock: &mut self.day_clock,
+            date_seed: self.date_seed,
+            dt_ms: 0,
+        };
+
+        let scene = self.scenes.last_mut().expect("scene stack can't be empty");
+        f(scene, &mut context)
     }
 
     pub fn play_tone(&self, frequency_hz: u32, duration_ms: u32) {
         self.buzzer.beep(frequency_hz, duration_ms);
     }
 
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
     pub fn input_mut(&mut self) -> &mut Input {
         &mut self.input
     }
+
+    pub fn pet(&self) -> &Pet {
+        &self.pet
+    }
+
+    /// The scene being updated, what the tests check the engine got to
+    #[cfg(test)]
+    pub(crate) fn active_scene(&self) -> &SceneWrapper {
+        self.scenes.last().expect("the scene stack is never empty")
+    }
+
+    /// Time source for `stats`, usually a copy of the one the platform times its frames with
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
+    /// Unix seconds from an RTC or NTP. The pet and the day clock catch up on the time since
+    /// the settings were last saved, and saves are stamped with it so the next boot can do
+    /// the same.
+    pub fn set_wall_clock(&mut self, wall_clock: fn() -> u64) {
+        self.wall_clock = Some(wall_clock);
+        let now_s = wall_clock();
+        let saved_at_s = self.settings.saved_at_s;
+        if saved_at_s != 0 && now_s > saved_at_s {
+            log::info!("Pet catching up on {} s powered off", now_s - saved_at_s);
+            self.pet.catch_up((now_s - saved_at_s) * 1000);
+            let offline_s = ((now_s - saved_at_s) % SECONDS_PER_DAY as u64) as u32;
+            self.day_clock.advance(offline_s * 1000);
+        }
+    }
+
+    /// Show `message` over the scenes for `duration_ms`, after the toasts already queued
+    pub fn toast(&mut self, message: &str, duration_ms: u32) {
+        self.toasts.push(message, duration_ms);
+    }
+
+    /// Log lines scenes can show, the platform copies what it captured in here
+    pub fn logs_mut(&mut self) -> &mut LogBuffer {
+        &mut self.logs
+    }
+
+    /// The battery ran out, the platform should `shutdown` and power off (deep sleep)
+    pub fn power_off_requested(&self) -> bool {
+        self.power_off_requested
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Time updated since boot, the sum of every `dt_ms` passed to `update`
+    pub fn uptime_ms(&self) -> u64 {
+        self.uptime.session_ms()
+    }
+
+    pub fn uptime_secs(&self) -> u64 {
+        self.uptime.session_ms() / 1000
+    }
+
+    /// `uptime_ms` as "3d 04:05:06"
+    pub fn uptime_string(&self) -> heapless::String<DURATION_STRING_LEN> {
+        uptime::format_duration(self.uptime.session_ms())
+    }
+
+    /// Uptime over all sessions, kept in the settings
+    pub fn lifetime_uptime_ms(&self) -> u64 {
+        self.uptime.lifetime_ms()
+    }
+
+    pub fn day_clock(&self) -> &DayClock {
+        &self.day_clock
+    }
+
+    /// Set the time of day, e.g. from SNTP, in seconds past midnight local time
+    pub fn set_time_of_day(&mut self, seconds: u32) {
+        self.day_clock.set(seconds);
+        self.settings_unsaved = true;
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Set the per-day value used to seed daily challenge runs. Anything that stays
+    /// the same for a whole day works (RTC date, days since epoch, day counter).
+    pub fn set_date_seed(&mut self, date_seed: u32) {
+        self.date_seed = date_seed;
+    }
+}
+
+// Drawn by the engine on top of the scenes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Indicators {
+    low_battery: bool,
+    notice: bool,
+}
+
+// Small battery outline with a sliver of charge in the top left corner
+fn draw_low_battery_icon<D>(color: consts::ColorType, target: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = consts::ColorType>,
+{
+    Rectangle::new(Point::new(4, 4), Size::new(20, 10))
+        .into_styled(PrimitiveStyle::with_stroke(color, 1))
+        .draw(target)?;
+    Rectangle::new(Point::new(24, 7), Size::new(2, 4))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(target)?;
+    Rectangle::new(Point::new(6, 6), Size::new(4, 6))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(target)
+}
+
+fn draw_critical_battery<D>(countdown_ms: u32, target: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = consts::ColorType>,
+{
+    target.clear(consts::ColorType::BLACK)?;
+    let center = Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2);
+
+    let title_style = MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(consts::ColorType::RED)
+        .build();
+    Text::with_alignment("Battery empty", center, title_style, Alignment::Center).draw(target)?;
+
+    let hint_style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(consts::ColorType::WHITE)
+        .build();
+    let mut hint = heapless::String::<32>::new();
+    let _ = write!(hint, "Powering off in {} s", countdown_ms.div_ceil(1000));
+    Text::with_alignment(&hint, center + Point::new(0, 20), hint_style, Alignment::Center).draw(target)?;
+
+    Ok(())
 }
 
 pub struct Context<'a> {
-    pub rng: SmallRng,
-    pub input: Input,
+    pub rng: &'a mut SmallRng,
+    pub input: &'a mut Input,
     pub output: Output<'a>,
+    pub pet: &'a mut Pet,
+    /// saved when a scene changes them
+    pub settings: &'a mut Settings,
+    /// recent log lines, oldest first
+    pub logs: &'a LogBuffer,
+    /// this session's and the lifetime total, the session count is in `settings`
+    pub uptime: &'a Uptime,
+    pub toasts: &'a mut ToastQueue,
+    /// time of day, set from the settings scene
+    pub day_clock: &'a mut DayClock,
+    pub date_seed: u32,
+    /// time this update advances by, see `Scene::fixed_step_ms`
+    pub dt_ms: u32,
 }
 
-impl<'a> Context<'a> {
-    fn new(buzzer: &'a dyn BuzzerTrait) -> Self {
-        Self {
-            rng: SmallRng::seed_from_u64(2137),
-            input: Input::new(),
-            output: Output::new(buzzer),
-        }
+impl Context<'_> {
+    /// See `Engine::toast`
+    pub fn toast(&mut self, message: &str, duration_ms: u32) {
+        self.toasts.push(message, duration_ms);
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{
+        scenes::{flappy::FlappyScene, gameover::GameOverScene},
+        test_support::{SharedSettingsStore, engine_with_store, mock_display},
+    };
+
+    #[test]
+    fn shutdown_saves_once_and_stops_the_engine() {
+        let store = SharedSettingsStore::default();
+        let (mut engine, _buzzer) = engine_with_store(store.clone());
+        engine.update(1000);
+        let saves_before = store.saves().len();
+        engine.shutdown();
+        let saves = store.saves();
+        assert_eq!(saves.len(), saves_before + 1);
+        let saved = saves.last().unwrap();
+        assert_eq!(saved.lifetime_uptime_ms, 1000);
+        assert_eq!(saved.sessions, 1);
+
+        engine.update(1000);
+        assert_eq!(engine.uptime_ms(), 1000);
+        assert!(!engine.needs_redraw());
+        let mut display = mock_display();
+        engine.render(&mut display).unwrap();
+        assert!(display.affected_area().is_zero_sized());
+
+        engine.shutdown();
+        assert_eq!(store.saves().len(), saves_before + 1);
+        // kept, so a platform that still asks for the scene gets one
+        assert!(matches!(engine.active_scene(), SceneWrapper::SelfTestScene(_)));
+    }
+
+    #[test]
+    fn shutdown_runs_the_active_scenes_exit() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        assert!(engine.push_scene(GameOverScene::new(3, 7).into()));
+        assert!(engine.melody_player.is_playing(), "the jingle plays on entering");
+        engine.shutdown();
+        assert!(!engine.melody_player.is_playing());
+    }
+
+    #[test]
+    fn reset_after_shutdown_runs_again() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        engine.shutdown();
+        engine.reset();
+        engine.update(500);
+        assert_eq!(engine.uptime_ms(), 500);
+        assert!(engine.needs_redraw());
+    }
+
+    fn feed_battery(engine: &mut Engine, volts: f32, duration_ms: u32) {
+        for _ in 0..duration_ms / 100 {
+            engine.input_mut().update_sensor(SensorType::BatteryVoltage, volts, 0);
+            engine.update(100);
+        }
+    }
+
+    #[test]
+    fn low_battery_shows_a_toast() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        feed_battery(&mut engine, 3.4, 100);
+        assert_eq!(engine.toasts.current(), Some("Battery low"));
+        assert!(engine.indicators().low_battery);
+        assert!(!engine.power_off_requested());
+    }
+
+    #[test]
+    fn critical_battery_powers_off_after_the_countdown() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        feed_battery(&mut engine, 3.0, CRITICAL_BATTERY_SHUTDOWN_MS - 100);
+        assert!(engine.shutdown_countdown_ms.is_some());
+        assert!(!engine.power_off_requested());
+        feed_battery(&mut engine, 3.0, 200);
+        assert!(engine.power_off_requested());
+    }
+
+    #[test]
+    fn charging_during_the_countdown_cancels_it() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        feed_battery(&mut engine, 3.0, 1000);
+        engine.input_mut().set_sensor_alpha(SensorType::BatteryVoltage, 1.0);
+        feed_battery(&mut engine, 4.0, CRITICAL_BATTERY_SHUTDOWN_MS);
+        assert_eq!(engine.shutdown_countdown_ms, None);
+        assert!(!engine.power_off_requested());
+    }
+
+    #[test]
+    fn the_voltage_is_not_trusted_while_charging() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        engine.input_mut().set_charging(true);
+        feed_battery(&mut engine, 3.0, CRITICAL_BATTERY_SHUTDOWN_MS + 1000);
+        assert_eq!(engine.shutdown_countdown_ms, None);
+        assert!(!engine.power_off_requested());
+        assert!(!engine.indicators().low_battery);
+        assert_eq!(engine.toasts.current(), None);
+    }
+
+    #[test]
+    fn plugging_the_charger_in_cancels_the_countdown() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        feed_battery(&mut engine, 3.0, 1000);
+        assert!(engine.shutdown_countdown_ms.is_some());
+        engine.input_mut().set_charging(true);
+        feed_battery(&mut engine, 3.0, CRITICAL_BATTERY_SHUTDOWN_MS);
+        assert_eq!(engine.shutdown_countdown_ms, None);
+        assert!(!engine.power_off_requested());
+    }
+
+    // gaps of the pipes a run has on screen after a while, laid out with the engine rng
+    fn flappy_gaps(engine: &mut Engine) -> Vec<(i32, i32)> {
+        engine.with_context(|_, ctx| {
+            let mut scene = FlappyScene::new();
+            for _ in 0..400 {
+                scene.update(ctx);
+            }
+            scene.gaps().collect()
+        })
+    }
+
+    #[test]
+    fn seed_picks_the_pipe_layout() {
+        let mut first = Engine::new().with_seed(7);
+        let mut second = Engine::new().with_seed(7);
+        let mut other = Engine::new().with_seed(8);
+        let layout = flappy_gaps(&mut first);
+        assert!(layout.len() >= 3, "{} pipes", layout.len());
+        assert_eq!(flappy_gaps(&mut second), layout);
+        assert_ne!(flappy_gaps(&mut other), layout);
+
+        // unseeded engines play the same as each other
+        assert_eq!(flappy_gaps(&mut Engine::new()), flappy_gaps(&mut Engine::new()));
+    }
+
+    #[test]
+    fn reset_starts_the_seeded_layout_over() {
+        let mut engine = Engine::new().with_seed(7);
+        let layout = flappy_gaps(&mut engine);
+        assert_ne!(flappy_gaps(&mut engine), layout);
+        engine.reset();
+        assert_eq!(flappy_gaps(&mut engine), layout);
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_self_test_with_a_fresh_pet() {
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::default());
+        engine.settings.sessions = 5;
+        while !matches!(engine.active_scene(), SceneWrapper::MenuScene(_)) {
+            engine.update(16);
+        }
+        let uptime_ms = engine.uptime_ms();
+        assert!(engine.pet().age_ms() > 0);
+
+        engine.reset();
+        assert!(matches!(engine.active_scene(), SceneWrapper::SelfTestScene(_)));
+        assert_eq!(engine.scenes.len(), 1);
+        assert_eq!(engine.pet().age_ms(), 0);
+        // kept
+        assert_eq!(engine.settings().sessions, 5);
+        assert_eq!(engine.uptime_ms(), uptime_ms);
+    }
+
+    #[test]
+    fn the_pet_ages_while_powered_off() {
+        let saved = Settings { saved_at_s: 1_000_000, ..Settings::default() };
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::with(saved));
+        assert_eq!(engine.pet().age_ms(), 0);
+
+        // an hour later
+        engine.set_wall_clock(|| 1_000_000 + 60 * 60);
+        assert_eq!(engine.pet().age_ms(), 60 * 60 * 1000);
+        assert_eq!(engine.pet().stage(), crate::pet::LifeStage::Child);
+    }
+
+    #[test]
+    fn a_clock_behind_the_last_save_changes_nothing() {
+        let saved = Settings { saved_at_s: 1_000_000, ..Settings::default() };
+        let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::with(saved));
+        engine.set_wall_clock(|| 999_000);
+        assert_eq!(engine.pet().age_ms(), 0);
+    }
+
+    #[test]
+    fn the_time_of_day_survives_a_reboot() {
+        let store = SharedSettingsStore::default();
+        let (mut engine, _buzzer) = engine_with_store(store.clone());
+        engine.set_time_of_day(21 * 60 * 60);
+        engine.update(1000);
+        engine.shutdown();
+
+        let (engine, _buzzer) = engine_with_store(store);
+        assert_eq!(engine.day_clock().seconds(), 21 * 60 * 60 + 1);
+        assert!(engine.day_clock().is_night());
+    }
+
+    #[test]
+    fn neglect_survives_a_reboot() {
+        use crate::pet::{Condition, PetStats, STAT_MAX};
+
+        let starving = PetStats {
+            hunger: 0,
+            happiness: STAT_MAX,
+            energy: STAT_MAX,
+            condition: Condition::Awake,
+            // hatched a while ago
+            age_ms: 10 * 60 * 1000,
+            neglect_ms: 0,
+        };
+        let store = SharedSettingsStore::with(Settings { pet: starving, ..Settings::default() });
+        let (mut engine, _buzzer) = engine_with_store(store.clone());
+        // ten minutes of it make the pet sick
+        let half_ms = 5 * 60 * 1000 + 1000;
+        for _ in 0..half_ms / 1000 {
+            engine.update(1000);
+        }
+        engine.shutdown();
+
+        // restarting doesn't make up for it, the second half makes it sick
+        let (mut engine, _buzzer) = engine_with_store(store);
+        assert_eq!(engine.pet().stats().neglect_ms, half_ms);
+        for _ in 0..half_ms / 1000 {
+            engine.update(1000);
+        }
+        assert_eq!(engine.pet().condition(), Condition::Sick);
+    }
+}
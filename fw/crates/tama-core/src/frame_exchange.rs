@@ -0,0 +1,250 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use crate::framebuffer::{Framebuffer, PixelByteOrder};
+
+/// What to do when the render thread finishes a frame while the transfer thread is still
+/// busy with the previous one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameDropPolicy {
+    /// Replace the pending frame / skip the new one, the panel always gets the newest
+    /// frame that fits (low latency, games)
+    Latest,
+    /// Wait for the transfer thread to hand its buffer back, no frame is skipped
+    Block,
+}
+
+/// Hands finished frames from the render thread to the transfer thread. Of the two
+/// buffers, the render thread always owns the back one, the other one is either pending
+/// here, being transferred, or handed back as spare.
+pub struct FrameExchange {
+    slots: Mutex<FrameSlots>,
+    changed: Condvar,
+}
+
+struct FrameSlots {
+    ready: Option<Framebuffer>,
+    spare: Option<Framebuffer>,
+    // platform time the last transfer ended
+    transfer_done_us: u64,
+}
+
+impl FrameExchange {
+    /// Blocks until the render thread publishes a frame, the transfer thread owns it
+    /// afterwards. `None` after `timeout` without one, nothing gets published while the
+    /// screen is unchanged.
+    pub fn take_ready(&self, timeout: Duration) -> Option<Framebuffer> {
+        let slots = self.slots.lock().unwrap();
+        let (mut slots, _) = self
+            .changed
+            .wait_timeout_while(slots, timeout, |slots| slots.ready.is_none())
+            .unwrap();
+        slots.ready.take()
+    }
+
+    /// Returns a transferred frame so the render thread can draw into it again, `done_us`
+    /// is when the transfer ended
+    pub fn give_back(&self, fb: Framebuffer, done_us: u64) {
+        let mut slots = self.slots.lock().unwrap();
+        slots.spare = Some(fb);
+        slots.transfer_done_us = done_us;
+        self.changed.notify_all();
+    }
+}
+
+/// Double-buffered framebuffer shared between the render and the transfer thread. The
+/// back buffer is drawn into while the front one is sent, buffers are swapped under a
+/// short lock instead of holding one lock for the whole transfer.
+pub struct SharedFramebuffer {
+    back: Mutex<Framebuffer>,
+    exchange: Arc<FrameExchange>,
+    policy: FrameDropPolicy,
+    frames_dropped: AtomicU32,
+}
+
+impl SharedFramebuffer {
+    pub fn new(width: u32, height: u32, byte_order: PixelByteOrder) -> Self {
+        Self::with_policy(width, height, byte_order, FrameDropPolicy::Latest)
+    }
+
+    pub fn with_policy(width: u32, height: u32, byte_order: PixelByteOrder, policy: FrameDropPolicy) -> Self {
+        Self {
+            back: Mutex::new(Framebuffer::new(width, height, byte_order)),
+            exchange: Arc::new(FrameExchange {
+                slots: Mutex::new(FrameSlots {
+                    ready: None,
+                    spare: Some(Framebuffer::new(width, height, byte_order)),
+                    transfer_done_us: 0,
+                }),
+                changed: Condvar::new(),
+            }),
+            policy,
+            frames_dropped: AtomicU32::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> FrameDropPolicy {
+        self.policy
+    }
+
+    /// Number of rendered frames that were never transferred
+    pub fn frames_dropped(&self) -> u32 {
+        self.frames_dropped.load(Ordering::Relaxed)
+    }
+
+    /// The transfer thread's end
+    pub fn clone_for_transfer(&self) -> Arc<FrameExchange> {
+        Arc::clone(&self.exchange)
+    }
+
+    /// Blocks until no frame is pending or being transferred, returns when the last
+    /// transfer ended
+    pub fn wait_for_transfer(&self) -> u64 {
+        let slots = self.exchange.slots.lock().unwrap();
+        let slots = self
+            .exchange
+            .changed
+            .wait_while(slots, |slots| slots.ready.is_some() || slots.spare.is_none())
+            .unwrap();
+        slots.transfer_done_us
+    }
+
+    /// Back buffer the render thread draws into
+    pub fn lock(&self) -> MutexGuard<'_, Framebuffer> {
+        self.back.lock().unwrap()
+    }
+
+    /// The back buffer has changes the panel hasn't got, e.g. a frame `signal_frame_ready`
+    /// had to skip. It has to be signalled again even when nothing new gets drawn.
+    pub fn has_unsent_changes(&self) -> bool {
+        self.lock().dirty().is_some()
+    }
+
+    /// Publish the back buffer for transfer and swap in the spare one
+    pub fn signal_frame_ready(&self) {
+        let mut back = self.lock();
+        let mut slots = self.exchange.slots.lock().unwrap();
+
+        if self.policy == FrameDropPolicy::Block {
+            while slots.spare.is_none() {
+                slots = self.exchange.changed.wait(slots).unwrap();
+            }
+        }
+
+        // Scenes only redraw what changed, so whatever was drawn into the back buffer is
+        // copied into the buffer drawn into next, that one would be stale there otherwise
+        if let Some(mut spare) = slots.spare.take() {
+            if let Some(dirty) = back.dirty() {
+                spare.copy_region_from(&back, dirty);
+            }
+            core::mem::swap(&mut *back, &mut spare);
+            slots.ready = Some(spare);
+        } else if let Some(pending) = slots.ready.as_mut() {
+            // previous frame wasn't picked up yet, it gets replaced by this one and its
+            // dirty area is still owed to the panel
+            if let Some(dirty) = back.dirty() {
+                pending.copy_region_from(&back, dirty);
+            }
+            core::mem::swap(&mut *back, pending);
+            if let Some(owed) = back.take_dirty() {
+                pending.mark_dirty(owed);
+            }
+            self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+        } else {
+            // both buffers busy (front one is being transferred), this frame is skipped.
+            // Its dirty area stays on the back buffer and goes out with the next signal.
+            self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        self.exchange.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::thread;
+
+    use embedded_graphics::{
+        Pixel,
+        prelude::{DrawTarget as _, Point, RgbColor as _, Size},
+        primitives::Rectangle,
+    };
+
+    use super::*;
+    use crate::{consts::ColorType, gfx::ReadPixel as _};
+
+    const WAIT: Duration = Duration::from_millis(100);
+
+    fn draw(shared: &SharedFramebuffer, x: i32) {
+        shared.lock().draw_iter([Pixel(Point::new(x, 0), ColorType::RED)]).unwrap();
+    }
+
+    // what the panel gets from a frame
+    fn sent(mut fb: Framebuffer) -> (Option<ColorType>, Option<ColorType>, Framebuffer) {
+        let _ = fb.take_dirty();
+        (fb.read_pixel(Point::new(0, 0)), fb.read_pixel(Point::new(1, 0)), fb)
+    }
+
+    #[test]
+    fn latest_replaces_the_pending_frame() {
+        let shared = SharedFramebuffer::new(4, 4, PixelByteOrder::BigEndian);
+        let exchange = shared.clone_for_transfer();
+        draw(&shared, 0);
+        shared.signal_frame_ready();
+        draw(&shared, 1);
+        shared.signal_frame_ready();
+        assert_eq!(shared.frames_dropped(), 1);
+
+        // the frame that went out has the first one's pixels too
+        let front = exchange.take_ready(WAIT).unwrap();
+        assert_eq!(front.dirty(), Some(Rectangle::new(Point::zero(), Size::new(2, 1))));
+        let (first, second, front) = sent(front);
+        assert_eq!((first, second), (Some(ColorType::RED), Some(ColorType::RED)));
+        assert!(exchange.take_ready(Duration::ZERO).is_none());
+
+        // both buffers busy, skipped and kept for the next signal
+        draw(&shared, 2);
+        shared.signal_frame_ready();
+        assert_eq!(shared.frames_dropped(), 2);
+        assert!(shared.has_unsent_changes());
+        exchange.give_back(front, 1234);
+        assert_eq!(shared.wait_for_transfer(), 1234);
+        shared.signal_frame_ready();
+        assert!(!shared.has_unsent_changes());
+        assert!(exchange.take_ready(WAIT).unwrap().dirty().is_some());
+        assert_eq!(shared.frames_dropped(), 2);
+    }
+
+    #[test]
+    fn block_waits_for_the_transfer() {
+        let shared = SharedFramebuffer::with_policy(4, 4, PixelByteOrder::BigEndian, FrameDropPolicy::Block);
+        let exchange = shared.clone_for_transfer();
+        let transfer = thread::spawn(move || {
+            let mut frames = Vec::new();
+            for done_us in 1..=3 {
+                let (first, second, fb) = sent(exchange.take_ready(Duration::from_secs(5)).unwrap());
+                frames.push((first, second));
+                thread::sleep(Duration::from_millis(10));
+                exchange.give_back(fb, done_us);
+            }
+            frames
+        });
+
+        draw(&shared, 0);
+        shared.signal_frame_ready();
+        draw(&shared, 1);
+        shared.signal_frame_ready();
+        shared.lock().clear(ColorType::BLUE).unwrap();
+        shared.signal_frame_ready();
+        assert_eq!(shared.wait_for_transfer(), 3);
+
+        let red = Some(ColorType::RED);
+        let blue = Some(ColorType::BLUE);
+        let black = Some(ColorType::BLACK);
+        assert_eq!(transfer.join().unwrap(), [(red, black), (red, red), (blue, blue)]);
+        assert_eq!(shared.frames_dropped(), 0);
+    }
+}
@@ -0,0 +1,153 @@
+use alloc::{vec, vec::Vec};
+
+// Frame rates the main loop steps down through when frames don't fit their budget
+const FPS_STEPS: [u32; 3] = [30, 20, 15];
+// Frames over budget (minus the ones within it) before stepping down, ~0.5 s at 30 FPS
+const STEP_DOWN_AFTER: u32 = 15;
+// Frames in a row that would fit the next faster rate before stepping back up
+const STEP_UP_AFTER: u32 = 90;
+// Share of the faster rate's budget a frame may use to count as fitting it, the margin
+// keeps it from stepping up and straight back down
+const STEP_UP_HEADROOM_PERCENT: u64 = 70;
+
+/// Picks the frame rate a platform's main loop runs at. Starts at the target and drops
+/// to the next slower rate when frames keep overrunning their budget instead of letting
+/// the lag pile up, goes back up once frames would fit the faster rate with headroom.
+/// Only takes timestamps, so it doesn't depend on the platform's scheduler.
+pub struct FrameScheduler {
+    // the target first, then the slower steps
+    rates: Vec<u32>,
+    current: usize,
+    // goes up on frames over budget, down on the others
+    overruns: u32,
+    // frames in a row that would have fit the next faster rate
+    fitting: u32,
+}
+
+impl FrameScheduler {
+    pub fn new(target_fps: u32) -> Self {
+        let target_fps = target_fps.max(1);
+        let mut rates = vec![target_fps];
+        rates.extend(FPS_STEPS.iter().copied().filter(|&fps| fps < target_fps));
+        Self { rates, current: 0, overruns: 0, fitting: 0 }
+    }
+
+    /// Frame rate the loop should run at now
+    pub fn fps(&self) -> u32 {
+        self.rates[self.current]
+    }
+
+    pub fn frame_time_ms(&self) -> u32 {
+        1000 / self.fps()
+    }
+
+    /// Feed the time the frame's work (update, render) started and ended, before the
+    /// loop waits for the next frame. Returns the new rate when it changed.
+    pub fn end_frame(&mut self, start_us: u64, end_us: u64) -> Option<u32> {
+        let work_us = end_us.saturating_sub(start_us);
+
+        if work_us > budget_us(self.fps()) {
+            self.overruns = (self.overruns + 1).min(STEP_DOWN_AFTER);
+            self.fitting = 0;
+        } else {
+            self.overruns = self.overruns.saturating_sub(1);
+            let fits_faster = self.current > 0
+                && work_us * 100 <= budget_us(self.rates[self.current - 1]) * STEP_UP_HEADROOM_PERCENT;
+            self.fitting = if fits_faster { self.fitting + 1 } else { 0 };
+        }
+
+        if self.overruns >= STEP_DOWN_AFTER && self.current + 1 < self.rates.len() {
+            self.current += 1;
+        } else if self.fitting >= STEP_UP_AFTER {
+            self.current -= 1;
+        } else {
+            return None;
+        }
+        self.overruns = 0;
+        self.fitting = 0;
+        Some(self.fps())
+    }
+}
+
+fn budget_us(fps: u32) -> u64 {
+    1_000_000 / fps as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVER_30_FPS_US: u64 = 40_000;
+    // fits 20 FPS, but not 30 with headroom
+    const OVER_30_FITS_20_US: u64 = 30_000;
+    const FITS_30_US: u64 = 10_000;
+
+    // feeds `frames` frames of `work_us` each, returns the rate changes
+    fn run(scheduler: &mut FrameScheduler, frames: u32, work_us: u64) -> Vec<u32> {
+        (0..frames).filter_map(|_| scheduler.end_frame(1000, 1000 + work_us)).collect()
+    }
+
+    #[test]
+    fn steps_down_after_overrunning_for_a_while() {
+        let mut scheduler = FrameScheduler::new(30);
+        assert_eq!(scheduler.frame_time_ms(), 33);
+        assert_eq!(run(&mut scheduler, STEP_DOWN_AFTER - 1, OVER_30_FPS_US), []);
+        assert_eq!(run(&mut scheduler, 1, OVER_30_FPS_US), [20]);
+        assert_eq!(run(&mut scheduler, STEP_DOWN_AFTER, 60_000), [15]);
+        // nothing slower to go to
+        assert_eq!(run(&mut scheduler, 10 * STEP_DOWN_AFTER, 100_000), []);
+        assert_eq!(scheduler.fps(), 15);
+    }
+
+    #[test]
+    fn the_odd_slow_frame_doesnt_step_down() {
+        let mut scheduler = FrameScheduler::new(30);
+        for _ in 0..1000 {
+            assert_eq!(run(&mut scheduler, 1, OVER_30_FPS_US), []);
+            assert_eq!(run(&mut scheduler, 1, FITS_30_US), []);
+        }
+        assert_eq!(scheduler.fps(), 30);
+
+        // two slow frames for every fitting one add up
+        let changes: Vec<u32> = (0..STEP_DOWN_AFTER)
+            .flat_map(|_| [OVER_30_FPS_US, OVER_30_FPS_US, FITS_30_US])
+            .filter_map(|work_us| scheduler.end_frame(0, work_us))
+            .collect();
+        assert_eq!(changes, [20]);
+    }
+
+    #[test]
+    fn steps_back_up_once_frames_fit_with_headroom() {
+        let mut scheduler = FrameScheduler::new(30);
+        run(&mut scheduler, STEP_DOWN_AFTER, OVER_30_FPS_US);
+        assert_eq!(scheduler.fps(), 20);
+
+        // fitting the current rate isn't enough
+        assert_eq!(run(&mut scheduler, 10 * STEP_UP_AFTER, OVER_30_FITS_20_US), []);
+        assert_eq!(run(&mut scheduler, STEP_UP_AFTER - 1, FITS_30_US), []);
+        // one frame without headroom starts the count over
+        assert_eq!(run(&mut scheduler, 1, OVER_30_FITS_20_US), []);
+        assert_eq!(run(&mut scheduler, STEP_UP_AFTER - 1, FITS_30_US), []);
+        assert_eq!(run(&mut scheduler, 1, FITS_30_US), [30]);
+        // never past the target
+        assert_eq!(run(&mut scheduler, 10 * STEP_UP_AFTER, 0), []);
+        assert_eq!(scheduler.fps(), 30);
+    }
+
+    #[test]
+    fn odd_targets() {
+        let mut scheduler = FrameScheduler::new(0);
+        assert_eq!(scheduler.fps(), 1);
+        assert_eq!(scheduler.frame_time_ms(), 1000);
+        assert_eq!(run(&mut scheduler, 10 * STEP_DOWN_AFTER, 5_000_000), []);
+
+        // slower than all the steps, it stays there
+        let mut scheduler = FrameScheduler::new(10);
+        assert_eq!(run(&mut scheduler, 10 * STEP_DOWN_AFTER, 200_000), []);
+        assert_eq!(scheduler.fps(), 10);
+
+        // in between the steps, only the slower ones are used
+        let mut scheduler = FrameScheduler::new(25);
+        assert_eq!(run(&mut scheduler, 2 * STEP_DOWN_AFTER, 100_000), [20, 15]);
+    }
+}
@@ -0,0 +1,325 @@
+use alloc::{boxed::Box, vec};
+
+use embedded_graphics::{
+    Pixel,
+    pixelcolor::raw::{RawU16, ToBytes},
+    prelude::{Dimensions, DrawTarget, OriginDimensions, PointsIter, Point, RgbColor, Size},
+    primitives::Rectangle,
+};
+
+use crate::{consts::ColorType, gfx::ReadPixel};
+
+/// Byte order of the 16-bit pixels on the SPI bus. The ST7789 takes them big endian,
+/// panels strapped or configured for the other order show wrong colors with that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelByteOrder {
+    BigEndian,
+    Swapped,
+}
+
+impl PixelByteOrder {
+    /// Stored so the native bytes of the u16 are the wire bytes
+    pub fn encode(self, color: ColorType) -> u16 {
+        u16::from_ne_bytes(match self {
+            PixelByteOrder::BigEndian => color.to_be_bytes(),
+            PixelByteOrder::Swapped => color.to_le_bytes(),
+        })
+    }
+
+    pub fn decode(self, stored: u16) -> ColorType {
+        let bytes = stored.to_ne_bytes();
+        let raw = match self {
+            PixelByteOrder::BigEndian => u16::from_be_bytes(bytes),
+            PixelByteOrder::Swapped => u16::from_le_bytes(bytes),
+        };
+        ColorType::from(RawU16::new(raw))
+    }
+}
+
+/// Frame in memory for platforms that send it to the panel themselves. Keeps track of the
+/// area drawn to, so only that part has to be transferred.
+pub struct Framebuffer {
+    /// Pixels already in the panel's byte order, so whole rows can be handed to SPI as they are
+    data: Box<[u16]>,
+    width: u32,
+    height: u32,
+    byte_order: PixelByteOrder,
+    /// Area drawn to since the last `take_dirty`
+    dirty: Option<Rectangle>,
+}
+
+impl Framebuffer {
+    /// All black, and nothing dirty yet
+    pub fn new(width: u32, height: u32, byte_order: PixelByteOrder) -> Self {
+        let size = (width * height) as usize;
+        let data = vec![byte_order.encode(ColorType::BLACK); size].into_boxed_slice();
+        Self { data, width, height, byte_order, dirty: None }
+    }
+
+    // Pixels of `area` in row-major order, `area` has to be inside the buffer
+    fn region(&self, area: Rectangle) -> impl Iterator<Item = u16> + '_ {
+        let x = area.top_left.x as usize;
+        let width = area.size.width as usize;
+        (area.top_left.y as usize..area.top_left.y as usize + area.size.height as usize).flat_map(move |y| {
+            let start = y * self.width as usize + x;
+            self.data[start..start + width].iter().copied()
+        })
+    }
+
+    /// Pixels of `area` as they go over the wire, `area` has to be inside the buffer
+    pub fn region_bytes(&self, area: Rectangle) -> impl Iterator<Item = [u8; 2]> + '_ {
+        self.region(area).map(u16::to_ne_bytes)
+    }
+
+    /// `area` as one contiguous run of wire bytes, only possible when it spans whole rows
+    pub fn rows_bytes(&self, area: Rectangle) -> Option<&[u8]> {
+        if area.top_left.x != 0 || area.size.width != self.width {
+            return None;
+        }
+        let start = area.top_left.y as usize * self.width as usize;
+        let pixels = &self.data[start..start + (area.size.width * area.size.height) as usize];
+        // u16 has no padding and u8 no alignment, any u16 slice is a valid byte slice
+        Some(unsafe { core::slice::from_raw_parts(pixels.as_ptr().cast::<u8>(), pixels.len() * 2) })
+    }
+
+    /// Area drawn to since the last `take_dirty`, `None` if nothing was
+    pub fn dirty(&self) -> Option<Rectangle> {
+        self.dirty
+    }
+
+    pub fn take_dirty(&mut self) -> Option<Rectangle> {
+        self.dirty.take()
+    }
+
+    /// Grow the dirty area to cover `area` too, it must not be empty
+    pub fn mark_dirty(&mut self, area: Rectangle) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => bounding_union(dirty, area),
+            None => area,
+        });
+    }
+
+    /// Copy `area` over from a buffer of the same size, the dirty area is left alone
+    pub fn copy_region_from(&mut self, other: &Framebuffer, area: Rectangle) {
+        let x = area.top_left.x as usize;
+        let width = area.size.width as usize;
+        for y in area.top_left.y as usize..area.top_left.y as usize + area.size.height as usize {
+            let start = y * self.width as usize + x;
+            self.data[start..start + width].copy_from_slice(&other.data[start..start + width]);
+        }
+    }
+}
+
+// Smallest rectangle containing both, neither may be empty
+fn bounding_union(a: Rectangle, b: Rectangle) -> Rectangle {
+    let a_end = a.top_left + a.size;
+    let b_end = b.top_left + b.size;
+    Rectangle::with_corners(
+        a.top_left.component_min(b.top_left),
+        a_end.component_max(b_end) - Point::new(1, 1),
+    )
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = ColorType;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // bounds of this call are merged into `dirty` once instead of per pixel
+        let mut min = Point::new(i32::MAX, i32::MAX);
+        let mut max = Point::new(i32::MIN, i32::MIN);
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.x < self.width as i32
+                && point.y >= 0 && point.y < self.height as i32 {
+                let index = (point.y as u32 * self.width + point.x as u32) as usize;
+                self.data[index] = self.byte_order.encode(color);
+                min = min.component_min(point);
+                max = max.component_max(point);
+            }
+        }
+        if min.x <= max.x {
+            self.mark_dirty(Rectangle::with_corners(min, max));
+        }
+        Ok(())
+    }
+
+    // Scenes clear every frame, one slice fill instead of a pixel at a time
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.data.fill(self.byte_order.encode(color));
+        self.mark_dirty(self.bounding_box());
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+        let encoded = self.byte_order.encode(color);
+        let x = area.top_left.x as usize;
+        let width = area.size.width as usize;
+        for y in area.top_left.y as usize..area.top_left.y as usize + area.size.height as usize {
+            let start = y * self.width as usize + x;
+            self.data[start..start + width].fill(encoded);
+        }
+        self.mark_dirty(area);
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        // colors of clipped pixels would have to be skipped one by one
+        if area.intersection(&self.bounding_box()) != *area {
+            return self.draw_iter(area.points().zip(colors).map(|(point, color)| Pixel(point, color)));
+        }
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+        let mut colors = colors.into_iter();
+        let x = area.top_left.x as usize;
+        let width = area.size.width as usize;
+        for y in area.top_left.y as usize..area.top_left.y as usize + area.size.height as usize {
+            let start = y * self.width as usize + x;
+            for (pixel, color) in self.data[start..start + width].iter_mut().zip(colors.by_ref()) {
+                *pixel = self.byte_order.encode(color);
+            }
+        }
+        self.mark_dirty(*area);
+        Ok(())
+    }
+}
+
+// Alpha sprites blend with what's already in the buffer
+impl ReadPixel for Framebuffer {
+    fn read_pixel(&self, point: Point) -> Option<ColorType> {
+        if point.x < 0 || point.x >= self.width as i32 || point.y < 0 || point.y >= self.height as i32 {
+            return None;
+        }
+        let index = (point.y as u32 * self.width + point.x as u32) as usize;
+        Some(self.byte_order.decode(self.data[index]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        Drawable as _,
+        prelude::Primitive as _,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    use super::*;
+
+    fn framebuffer() -> Framebuffer {
+        Framebuffer::new(32, 16, PixelByteOrder::BigEndian)
+    }
+
+    #[test]
+    fn starts_clean() {
+        let mut fb = framebuffer();
+        assert_eq!(fb.dirty(), None);
+        assert_eq!(fb.take_dirty(), None);
+    }
+
+    #[test]
+    fn dirty_area_covers_everything_drawn_since_the_last_take() {
+        let mut fb = framebuffer();
+        fb.draw_iter([Pixel(Point::new(3, 4), ColorType::RED)]).unwrap();
+        assert_eq!(fb.dirty(), Some(Rectangle::new(Point::new(3, 4), Size::new(1, 1))));
+
+        Line::new(Point::new(10, 2), Point::new(12, 9))
+            .into_styled(PrimitiveStyle::with_stroke(ColorType::WHITE, 1))
+            .draw(&mut fb)
+            .unwrap();
+        assert_eq!(fb.take_dirty(), Some(Rectangle::with_corners(Point::new(3, 2), Point::new(12, 9))));
+        assert_eq!(fb.dirty(), None);
+    }
+
+    #[test]
+    fn pixels_off_the_buffer_are_dropped() {
+        let mut fb = framebuffer();
+        fb.draw_iter([Pixel(Point::new(-1, 0), ColorType::RED), Pixel(Point::new(0, 16), ColorType::RED)])
+            .unwrap();
+        assert_eq!(fb.dirty(), None);
+
+        fb.draw_iter([Pixel(Point::new(-1, 0), ColorType::RED), Pixel(Point::new(31, 15), ColorType::RED)])
+            .unwrap();
+        assert_eq!(fb.dirty(), Some(Rectangle::new(Point::new(31, 15), Size::new(1, 1))));
+        assert_eq!(fb.read_pixel(Point::new(31, 15)), Some(ColorType::RED));
+    }
+
+    #[test]
+    fn copying_a_region_leaves_the_rest() {
+        let mut front = framebuffer();
+        let mut back = framebuffer();
+        back.clear(ColorType::BLUE).unwrap();
+        let area = Rectangle::new(Point::new(4, 4), Size::new(2, 2));
+        front.copy_region_from(&back, area);
+        assert_eq!(front.read_pixel(Point::new(5, 5)), Some(ColorType::BLUE));
+        assert_eq!(front.read_pixel(Point::new(6, 5)), Some(ColorType::BLACK));
+        assert_eq!(front.dirty(), None);
+    }
+
+    #[test]
+    fn whole_rows_go_out_as_one_slice() {
+        let mut fb = framebuffer();
+        fb.draw_iter([Pixel(Point::new(0, 1), ColorType::RED)]).unwrap();
+        let rows = Rectangle::new(Point::new(0, 1), Size::new(32, 2));
+        let bytes = fb.rows_bytes(rows).unwrap();
+        assert_eq!(bytes.len(), 32 * 2 * 2);
+        assert_eq!(&bytes[..2], ColorType::RED.to_be_bytes());
+        assert!(bytes.iter().copied().eq(fb.region_bytes(rows).flatten()));
+        assert!(fb.rows_bytes(Rectangle::new(Point::new(1, 1), Size::new(31, 1))).is_none());
+    }
+
+    #[test]
+    fn clear_fills_and_dirties_everything() {
+        let mut fb = Framebuffer::new(4, 2, PixelByteOrder::Swapped);
+        fb.clear(ColorType::GREEN).unwrap();
+        assert_eq!(fb.take_dirty(), Some(fb.bounding_box()));
+        assert!(fb.bounding_box().points().all(|point| fb.read_pixel(point) == Some(ColorType::GREEN)));
+        assert_eq!(fb.region_bytes(fb.bounding_box()).next(), Some(ColorType::GREEN.to_le_bytes()));
+    }
+
+    #[test]
+    fn solid_fills_are_clipped_to_the_buffer() {
+        let mut fb = framebuffer();
+        fb.fill_solid(&Rectangle::new(Point::new(30, -2), Size::new(4, 4)), ColorType::RED).unwrap();
+        let inside = Rectangle::new(Point::new(30, 0), Size::new(2, 2));
+        assert_eq!(fb.take_dirty(), Some(inside));
+        assert_eq!(fb.read_pixel(Point::new(31, 1)), Some(ColorType::RED));
+        assert_eq!(fb.read_pixel(Point::new(29, 1)), Some(ColorType::BLACK));
+        assert_eq!(fb.read_pixel(Point::new(31, 2)), Some(ColorType::BLACK));
+
+        fb.fill_solid(&Rectangle::new(Point::new(40, 0), Size::new(4, 4)), ColorType::RED).unwrap();
+        assert_eq!(fb.dirty(), None);
+    }
+
+    #[test]
+    fn contiguous_fills_go_row_by_row() {
+        let colors = [ColorType::RED, ColorType::GREEN, ColorType::BLUE, ColorType::WHITE];
+        let mut fb = framebuffer();
+        let area = Rectangle::new(Point::new(2, 3), Size::new(2, 2));
+        fb.fill_contiguous(&area, colors).unwrap();
+        assert_eq!(fb.take_dirty(), Some(area));
+        let drawn: alloc::vec::Vec<_> = area.points().map(|point| fb.read_pixel(point).unwrap()).collect();
+        assert_eq!(drawn, colors);
+
+        // clipped, the colors of the pixels off the buffer are skipped
+        fb.fill_contiguous(&Rectangle::new(Point::new(-1, 0), Size::new(2, 2)), colors).unwrap();
+        assert_eq!(fb.take_dirty(), Some(Rectangle::new(Point::zero(), Size::new(1, 2))));
+        assert_eq!(fb.read_pixel(Point::new(0, 0)), Some(ColorType::GREEN));
+        assert_eq!(fb.read_pixel(Point::new(0, 1)), Some(ColorType::WHITE));
+    }
+}
@@ -1,7 +1,40 @@
 use embedded_graphics::{
-    Drawable, Pixel, pixelcolor::{Rgb555, Rgb565, Rgb888}, prelude::{DrawTarget, PixelColor, Point}
+    Drawable, Pixel, mono_font::MonoTextStyle, pixelcolor::{Rgb555, Rgb565, Rgb888}, prelude::{DrawTarget, OriginDimensions, PixelColor, Point, Primitive, RgbColor, Size}, primitives::{PrimitiveStyle, Rectangle}, text::Text
 };
-use tinybmp::Bmp;
+use tinybmp::{Bmp, ChannelMasks};
+
+use crate::{consts, palette::Theme};
+
+/// Most lines `wrap_text` returns, the rest of the text is dropped
+pub const WRAP_MAX_LINES: usize = 16;
+
+/// Why a frame couldn't be drawn, `E` is the draw target's error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderError<E> {
+    /// The draw target failed, drawing the frame again may work
+    Target(E),
+    /// The scene can't draw itself (an asset that didn't decode...), it won't get
+    /// better by trying again
+    Scene(&'static str),
+}
+
+// `?` on draw calls inside `Scene::draw`
+impl<E> From<E> for RenderError<E> {
+    fn from(error: E) -> Self {
+        RenderError::Target(error)
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for RenderError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RenderError::Target(error) => write!(f, "draw target error: {}", error),
+            RenderError::Scene(reason) => write!(f, "scene can't be drawn: {}", reason),
+        }
+    }
+}
+
+impl<E: core::fmt::Debug + core::fmt::Display> core::error::Error for RenderError<E> {}
 
 pub struct Sprite<'a, 'b, C>
 where
@@ -9,20 +42,50 @@ where
 {
     bmp_image: &'a Bmp<'b, C>,
     position: Point,
-    transparency_key: C,
+    /// pixels of this color aren't drawn
+    transparency_key: Option<C>,
+    flip_horizontal: bool,
+    flip_vertical: bool,
 }
 
 impl<'bmp_image, 'bmp_data, C> Sprite<'bmp_image, 'bmp_data, C>
 where
     C: PixelColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
 {
+    /// Magenta (0xff00ff) pixels are transparent
     pub fn new(bmp: &'bmp_image Bmp<'bmp_data, C>, position: Point) -> Self {
+        Self::with_transparency(bmp, position, C::from(Rgb888::new(0xff, 0, 0xff)))
+    }
+
+    pub fn with_transparency(bmp: &'bmp_image Bmp<'bmp_data, C>, position: Point, key: C) -> Self {
+        Self {
+            transparency_key: Some(key),
+            ..Self::opaque(bmp, position)
+        }
+    }
+
+    /// Every pixel is drawn
+    pub fn opaque(bmp: &'bmp_image Bmp<'bmp_data, C>, position: Point) -> Self {
         Self {
             bmp_image: bmp,
             position,
-            transparency_key: C::from(Rgb888::new(0xff, 0, 0xff)), // CYAN
+            transparency_key: None,
+            flip_horizontal: false,
+            flip_vertical: false,
         }
     }
+
+    /// Mirror left to right, the sprite stays at the same position
+    pub fn flip_horizontal(mut self) -> Self {
+        self.flip_horizontal = true;
+        self
+    }
+
+    /// Mirror top to bottom, the sprite stays at the same position
+    pub fn flip_vertical(mut self) -> Self {
+        self.flip_vertical = true;
+        self
+    }
 }
 
 impl<'bmp_image, 'bmp_data, C> Drawable for Sprite<'bmp_image, 'bmp_data, C>
@@ -36,17 +99,708 @@ where
     where
         D: DrawTarget<Color = Self::Color>,
     {
-        // This is probably horribly inefficient
-        for pixel in self.bmp_image.pixels() {
-            if pixel.1 == self.transparency_key {
-                continue;
-            }
+        let size = self.bmp_image.size();
+        let pixels = self.bmp_image.pixels().map(|Pixel(point, color)| {
+            let x = if self.flip_horizontal { size.width as i32 - 1 - point.x } else { point.x };
+            let y = if self.flip_vertical { size.height as i32 - 1 - point.y } else { point.y };
+            Pixel(Point::new(x, y), color)
+        });
+
+        draw_keyed(pixels, self.transparency_key, self.position, target)
+    }
+}
+
+/// Frame based animation from a sprite sheet. Frames are `frame_size` each, laid out
+/// left to right and continuing on the next row when the sheet is narrower than all of them.
+pub struct AnimatedSprite<'a, 'b, C>
+where
+    C: PixelColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+{
+    sheet: &'a Bmp<'b, C>,
+    frame_size: Size,
+    frame_count: u32,
+    frame_duration_ms: u32,
+    /// one-shot animations stop on their last frame
+    looping: bool,
+    transparency_key: Option<C>,
+    elapsed_ms: u32,
+}
+
+impl<'sheet, 'sheet_data, C> AnimatedSprite<'sheet, 'sheet_data, C>
+where
+    C: PixelColor + From<Rgb555> + From<Rgb565> + From<Rgb888>,
+{
+    /// Magenta (0xff00ff) pixels are transparent, like with `Sprite::new`
+    pub fn new(
+        sheet: &'sheet Bmp<'sheet_data, C>,
+        frame_size: Size,
+        frame_count: u32,
+        frame_duration_ms: u32,
+        looping: bool,
+    ) -> Self {
+        Self {
+            sheet,
+            frame_size,
+            frame_count: frame_count.max(1),
+            frame_duration_ms: frame_duration_ms.max(1),
+            looping,
+            transparency_key: Some(C::from(Rgb888::new(0xff, 0, 0xff))),
+            elapsed_ms: 0,
+        }
+    }
+
+    /// `None` draws every pixel
+    pub fn with_transparency(mut self, key: Option<C>) -> Self {
+        self.transparency_key = key;
+        self
+    }
+
+    pub fn tick(&mut self, dt_ms: u32) {
+        let total_ms = self.frame_count * self.frame_duration_ms;
+        self.elapsed_ms = if self.looping {
+            (self.elapsed_ms + dt_ms) % total_ms
+        } else {
+            self.elapsed_ms.saturating_add(dt_ms).min(total_ms)
+        };
+    }
+
+    pub fn frame_index(&self) -> u32 {
+        (self.elapsed_ms / self.frame_duration_ms).min(self.frame_count - 1)
+    }
+
+    /// Only ever true for one-shot animations
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.elapsed_ms >= self.frame_count * self.frame_duration_ms
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed_ms = 0;
+    }
+
+    /// Draw the current frame with its top left corner at `position`
+    pub fn draw<D>(&self, target: &mut D, position: Point) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let columns = (self.sheet.size().width / self.frame_size.width.max(1)).max(1);
+        let index = self.frame_index();
+        let frame = Rectangle::new(
+            Point::new(
+                ((index % columns) * self.frame_size.width) as i32,
+                ((index / columns) * self.frame_size.height) as i32,
+            ),
+            self.frame_size,
+        );
+
+        let pixels = self
+            .sheet
+            .pixels()
+            .filter(|Pixel(point, _)| frame.contains(*point))
+            .map(|Pixel(point, color)| Pixel(point - frame.top_left, color));
+
+        draw_keyed(pixels, self.transparency_key, position, target)
+    }
+}
 
-            let x = pixel.0.x + self.position.x;
-            let y = pixel.0.y + self.position.y;
-            Pixel(Point::new(x, y), pixel.1).draw(target)?; 
+// Draw `pixels` moved by `offset`, leaving out the ones matching the transparency key
+fn draw_keyed<C, D>(
+    pixels: impl Iterator<Item = Pixel<C>>,
+    transparency_key: Option<C>,
+    offset: Point,
+    target: &mut D,
+) -> Result<(), D::Error>
+where
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    // This is probably horribly inefficient
+    for Pixel(point, color) in pixels {
+        if Some(color) == transparency_key {
+            continue;
         }
 
+        Pixel(point + offset, color).draw(target)?;
+    }
+
+    Ok(())
+}
+
+/// Draw targets that can read back what was drawn, blending semi-transparent pixels
+/// needs the color already there
+pub trait ReadPixel: DrawTarget {
+    /// `None` outside the target
+    fn read_pixel(&self, point: Point) -> Option<Self::Color>;
+}
+
+// pixels at least this opaque are drawn when the target can't blend
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Sprite from a 32 bit BMP with an alpha channel (a BMP V3 header or newer carrying an
+/// alpha mask). Only targets implementing `ReadPixel` can blend, `draw_blended` does that.
+/// Plain `Drawable::draw` works on any target but can only draw a pixel or leave it out.
+/// BMPs without alpha are magenta keyed like `Sprite::new`.
+pub struct AlphaSprite<'a> {
+    source: AlphaSource<'a>,
+    position: Point,
+}
+
+enum AlphaSource<'a> {
+    Alpha(AlphaBmp<'a>),
+    Keyed(Bmp<'a, consts::ColorType>),
+}
+
+impl<'a> AlphaSprite<'a> {
+    /// Fails like `Bmp::from_slice` when `bytes` aren't a BMP it or this can read
+    pub fn from_slice(bytes: &'a [u8], position: Point) -> Result<Self, tinybmp::ParseError> {
+        let source = match AlphaBmp::parse(bytes) {
+            Some(bmp) => AlphaSource::Alpha(bmp),
+            None => AlphaSource::Keyed(Bmp::from_slice(bytes)?),
+        };
+        Ok(Self { source, position })
+    }
+
+    /// Blend every pixel with what's on `target` by its alpha
+    pub fn draw_blended<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: ReadPixel<Color = consts::ColorType>,
+    {
+        let bmp = match &self.source {
+            AlphaSource::Alpha(bmp) => bmp,
+            AlphaSource::Keyed(bmp) => return Sprite::new(bmp, self.position).draw(target),
+        };
+
+        for (point, color, alpha) in bmp.pixels() {
+            let point = point + self.position;
+            let color = match alpha {
+                0 => continue,
+                u8::MAX => color,
+                alpha => match target.read_pixel(point) {
+                    Some(below) => blend(color, Rgb888::from(below), alpha),
+                    None => continue,
+                },
+            };
+            Pixel(point, consts::ColorType::from(color)).draw(target)?;
+        }
         Ok(())
     }
 }
+
+impl Drawable for AlphaSprite<'_> {
+    type Color = consts::ColorType;
+    type Output = ();
+
+    /// Without reading the target, pixels are either drawn or left out by `ALPHA_THRESHOLD`
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let bmp = match &self.source {
+            AlphaSource::Alpha(bmp) => bmp,
+            AlphaSource::Keyed(bmp) => return Sprite::new(bmp, self.position).draw(target),
+        };
+
+        let pixels = bmp
+            .pixels()
+            .filter(|&(_, _, alpha)| alpha >= ALPHA_THRESHOLD)
+            .map(|(point, color, _)| Pixel(point, consts::ColorType::from(color)));
+        draw_keyed(pixels, None, self.position, target)
+    }
+}
+
+// Uncompressed 32 bit pixels with an alpha mask. tinybmp only takes 32 bit images without
+// alpha, so these are read here.
+struct AlphaBmp<'a> {
+    /// rows of 4 byte pixels, no padding needed
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    /// rows are stored bottom-up unless the height is negative
+    top_down: bool,
+    masks: ChannelMasks,
+}
+
+// BI_BITFIELDS and BI_ALPHABITFIELDS
+const BMP_BITFIELDS: u32 = 3;
+const BMP_ALPHA_BITFIELDS: u32 = 6;
+// size of the V3 header, the first one with an alpha mask
+const BMP_V3_HEADER_BYTES: u32 = 56;
+
+impl<'a> AlphaBmp<'a> {
+    /// `None` when `bytes` aren't a 32 bit BMP with alpha, or are cut short
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let u16_at = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+        let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+
+        if bytes.get(..2)? != b"BM" || u32_at(14)? < BMP_V3_HEADER_BYTES || u16_at(28)? != 32 {
+            return None;
+        }
+        if !matches!(u32_at(30)?, BMP_BITFIELDS | BMP_ALPHA_BITFIELDS) {
+            return None;
+        }
+        let masks = ChannelMasks {
+            red: u32_at(54)?,
+            green: u32_at(58)?,
+            blue: u32_at(62)?,
+            alpha: u32_at(66)?,
+        };
+        if masks.alpha == 0 {
+            return None;
+        }
+
+        let width = u32_at(18)? as i32;
+        let height = u32_at(22)? as i32;
+        if width <= 0 || height == 0 {
+            return None;
+        }
+        let (width, top_down) = (width as u32, height < 0);
+        let height = height.unsigned_abs();
+        let start = u32_at(10)? as usize;
+        let data = bytes.get(start..start.checked_add(width as usize * height as usize * 4)?)?;
+        Some(Self { data, width, height, top_down, masks })
+    }
+
+    // Color and alpha of every pixel, relative to the sprite's top left corner
+    fn pixels(&self) -> impl Iterator<Item = (Point, Rgb888, u8)> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y))).map(move |(x, y)| {
+            let row = if self.top_down { y } else { self.height - 1 - y };
+            let at = ((row * self.width + x) * 4) as usize;
+            let raw = u32::from_le_bytes([self.data[at], self.data[at + 1], self.data[at + 2], self.data[at + 3]]);
+            let masks = self.masks;
+            let rgb = Rgb888::new(channel(raw, masks.red), channel(raw, masks.green), channel(raw, masks.blue));
+            (Point::new(x as i32, y as i32), rgb, channel(raw, masks.alpha))
+        })
+    }
+}
+
+// Channel of a raw pixel scaled to 8 bits, masks are contiguous runs of bits
+fn channel(raw: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let value = (raw & mask) >> mask.trailing_zeros();
+    let max = mask >> mask.trailing_zeros();
+    (value * 255 / max) as u8
+}
+
+/// `color` over `below`, `alpha` 0 is only `below` and 255 only `color`
+pub fn blend(color: Rgb888, below: Rgb888, alpha: u8) -> Rgb888 {
+    let mix = |top: u8, bottom: u8| {
+        ((top as u32 * alpha as u32 + bottom as u32 * (255 - alpha as u32) + 127) / 255) as u8
+    };
+    Rgb888::new(
+        mix(color.r(), below.r()),
+        mix(color.g(), below.g()),
+        mix(color.b(), below.b()),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GaugeOrientation {
+    /// fills left to right
+    Horizontal,
+    /// fills bottom to top
+    Vertical,
+}
+
+/// Bordered bar filled to `ratio` (0.0 - 1.0)
+#[derive(Debug, Clone, Copy)]
+pub struct Gauge {
+    area: Rectangle,
+    ratio: f32,
+    fill_color: consts::ColorType,
+    border_color: consts::ColorType,
+    /// `None` leaves the empty part as it is
+    background_color: Option<consts::ColorType>,
+    orientation: GaugeOrientation,
+}
+
+impl Gauge {
+    /// Drawn in the light theme's foreground until the colors are set
+    pub fn new(top_left: Point, size: Size, ratio: f32) -> Self {
+        Self {
+            area: Rectangle::new(top_left, size),
+            ratio: ratio.clamp(0.0, 1.0),
+            fill_color: Theme::LIGHT.foreground,
+            border_color: Theme::LIGHT.foreground,
+            background_color: None,
+            orientation: GaugeOrientation::Horizontal,
+        }
+    }
+
+    pub fn fill_color(mut self, color: consts::ColorType) -> Self {
+        self.fill_color = color;
+        self
+    }
+
+    pub fn border_color(mut self, color: consts::ColorType) -> Self {
+        self.border_color = color;
+        self
+    }
+
+    pub fn background_color(mut self, color: consts::ColorType) -> Self {
+        self.background_color = Some(color);
+        self
+    }
+
+    pub fn orientation(mut self, orientation: GaugeOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+}
+
+impl Drawable for Gauge {
+    type Color = consts::ColorType;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        if let Some(background) = self.background_color {
+            self.area.into_styled(PrimitiveStyle::with_fill(background)).draw(target)?;
+        }
+
+        let Size { width, height } = self.area.size;
+        let filled = match self.orientation {
+            GaugeOrientation::Horizontal => {
+                Rectangle::new(self.area.top_left, Size::new((width as f32 * self.ratio) as u32, height))
+            }
+            GaugeOrientation::Vertical => {
+                let filled_height = (height as f32 * self.ratio) as u32;
+                let top = self.area.top_left + Point::new(0, (height - filled_height) as i32);
+                Rectangle::new(top, Size::new(width, filled_height))
+            }
+        };
+        filled.into_styled(PrimitiveStyle::with_fill(self.fill_color)).draw(target)?;
+
+        self.area
+            .into_styled(PrimitiveStyle::with_stroke(self.border_color, 1))
+            .draw(target)
+    }
+}
+
+/// Break `text` into lines of at most `max_chars` characters. Lines break at spaces
+/// where possible, words longer than a line are split, and `\n` always starts a new line.
+pub fn wrap_text(text: &str, max_chars: usize) -> heapless::Vec<&str, WRAP_MAX_LINES> {
+    let mut lines = heapless::Vec::new();
+    if text.is_empty() || max_chars == 0 {
+        return lines;
+    }
+
+    for paragraph in text.split('\n') {
+        let mut rest = paragraph.trim_start_matches(' ');
+        if rest.is_empty() && lines.push("").is_err() {
+            return lines;
+        }
+
+        while !rest.is_empty() {
+            // byte index of the first character that doesn't fit anymore
+            let Some((cut, _)) = rest.char_indices().nth(max_chars) else {
+                if lines.push(rest).is_err() {
+                    return lines;
+                }
+                break;
+            };
+
+            let line_end = if rest[cut..].starts_with(' ') {
+                cut
+            } else {
+                rest[..cut].rfind(' ').unwrap_or(cut)
+            };
+            if lines.push(rest[..line_end].trim_end_matches(' ')).is_err() {
+                return lines;
+            }
+            rest = rest[line_end..].trim_start_matches(' ');
+        }
+    }
+
+    lines
+}
+
+/// Draw `text` wrapped to `max_chars`, `position` is the baseline of the first line.
+/// Returns the position for whatever comes below the text.
+pub fn draw_wrapped<C, D>(
+    text: &str,
+    max_chars: usize,
+    position: Point,
+    style: MonoTextStyle<'_, C>,
+    line_height: i32,
+    target: &mut D,
+) -> Result<Point, D::Error>
+where
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    let mut position = position;
+    for line in wrap_text(text, max_chars) {
+        Text::new(line, position, style).draw(target)?;
+        position.y += line_height;
+    }
+    Ok(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::mock_display::MockDisplay;
+
+    use super::*;
+    use crate::test_support::mock_display;
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        assert_eq!(wrap_text("sensor not reporting", 10).as_slice(), ["sensor not", "reporting"]);
+        assert_eq!(wrap_text("a  b", 1).as_slice(), ["a", "b"]);
+        assert!(wrap_text("", 10).is_empty());
+        assert!(wrap_text("text", 0).is_empty());
+    }
+
+    #[test]
+    fn wrap_splits_words_longer_than_a_line() {
+        assert_eq!(wrap_text("abcdefghij", 4).as_slice(), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap_text("ok abcdefgh", 4).as_slice(), ["ok", "abcd", "efgh"]);
+    }
+
+    #[test]
+    fn wrap_keeps_empty_lines() {
+        assert_eq!(wrap_text("one\n\ntwo", 10).as_slice(), ["one", "", "two"]);
+        assert_eq!(wrap_text("one\n", 10).as_slice(), ["one", ""]);
+    }
+
+    #[test]
+    fn wrap_counts_characters_not_bytes() {
+        assert_eq!(wrap_text("żółw żółw", 4).as_slice(), ["żółw", "żółw"]);
+    }
+
+    #[test]
+    fn wrap_stops_at_the_line_limit() {
+        let text = "x\n".repeat(WRAP_MAX_LINES + 4);
+        let lines = wrap_text(&text, 10);
+        assert_eq!(lines.len(), WRAP_MAX_LINES);
+        assert!(lines.iter().all(|&line| line == "x"));
+    }
+
+    const RED: Rgb565 = Rgb565::RED;
+    const BLUE: Rgb565 = Rgb565::BLUE;
+    const MAGENTA: [u8; 3] = [0xff, 0, 0xff];
+
+    // Uncompressed 24 bit BMP of `rows` RGB pixels, top row first
+    fn bmp24(rows: &[&[[u8; 3]]]) -> alloc::vec::Vec<u8> {
+        let width = rows[0].len();
+        let stride = (width * 3).div_ceil(4) * 4;
+        let data_size = stride * rows.len();
+        let mut bytes = alloc::vec::Vec::new();
+        bytes.extend_from_slice(b"BM");
+        bytes.extend_from_slice(&(54 + data_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&54u32.to_le_bytes());
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(&(width as i32).to_le_bytes());
+        bytes.extend_from_slice(&(rows.len() as i32).to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&24u16.to_le_bytes());
+        // no compression
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(data_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        // stored bottom row first, as BGR
+        for row in rows.iter().rev() {
+            for &[r, g, b] in row.iter() {
+                bytes.extend_from_slice(&[b, g, r]);
+            }
+            bytes.resize(bytes.len() + stride - width * 3, 0);
+        }
+        bytes
+    }
+
+    fn pixels(display: &MockDisplay<Rgb565>, width: i32, height: i32) -> alloc::vec::Vec<Option<Rgb565>> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| Point::new(x, y)))
+            .map(|point| display.get_pixel(point))
+            .collect()
+    }
+
+    #[test]
+    fn magenta_is_left_out() {
+        let data = bmp24(&[&[[0xff, 0, 0], MAGENTA]]);
+        let bmp = Bmp::<Rgb565>::from_slice(&data).unwrap();
+
+        let mut display = mock_display();
+        Sprite::new(&bmp, Point::new(1, 1)).draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 3, 2), [None, None, None, None, Some(RED), None]);
+
+        let mut display = mock_display();
+        Sprite::opaque(&bmp, Point::zero()).draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 2, 1), [Some(RED), Some(Rgb565::MAGENTA)]);
+
+        let mut display = mock_display();
+        Sprite::with_transparency(&bmp, Point::zero(), RED).draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 2, 1), [None, Some(Rgb565::MAGENTA)]);
+    }
+
+    #[test]
+    fn flips_stay_in_place() {
+        let data = bmp24(&[&[[0xff, 0, 0], [0, 0, 0xff]], &[[0, 0, 0xff], [0, 0, 0xff]]]);
+        let bmp = Bmp::<Rgb565>::from_slice(&data).unwrap();
+
+        let mut display = mock_display();
+        Sprite::new(&bmp, Point::new(2, 0)).flip_horizontal().draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 4, 2), [None, None, Some(BLUE), Some(RED), None, None, Some(BLUE), Some(BLUE)]);
+
+        let mut display = mock_display();
+        Sprite::new(&bmp, Point::zero()).flip_vertical().flip_horizontal().draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 2, 2), [Some(BLUE), Some(BLUE), Some(BLUE), Some(RED)]);
+    }
+
+    // One row of 32 bit RGBA pixels with a V3 header carrying the channel masks
+    fn bmp32(row: &[[u8; 4]]) -> alloc::vec::Vec<u8> {
+        let data_size = row.len() * 4;
+        let mut bytes = alloc::vec::Vec::new();
+        bytes.extend_from_slice(b"BM");
+        bytes.extend_from_slice(&(70 + data_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&70u32.to_le_bytes());
+        bytes.extend_from_slice(&56u32.to_le_bytes());
+        bytes.extend_from_slice(&(row.len() as i32).to_le_bytes());
+        bytes.extend_from_slice(&1i32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&32u16.to_le_bytes());
+        // bitfields
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&(data_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+            bytes.extend_from_slice(&mask.to_le_bytes());
+        }
+        for &[r, g, b, a] in row {
+            bytes.extend_from_slice(&[b, g, r, a]);
+        }
+        bytes
+    }
+
+    // opaque red, invisible white, half white, opaque blue
+    fn alpha_row() -> alloc::vec::Vec<u8> {
+        bmp32(&[[0xff, 0, 0, 0xff], [0xff, 0xff, 0xff, 0], [0xff, 0xff, 0xff, 0x80], [0, 0, 0xff, 0xff]])
+    }
+
+    #[test]
+    fn blend_ends_are_either_color() {
+        let (top, below) = (Rgb888::new(200, 100, 0), Rgb888::new(0, 50, 250));
+        assert_eq!(blend(top, below, 0), below);
+        assert_eq!(blend(top, below, u8::MAX), top);
+        assert_eq!(blend(Rgb888::WHITE, Rgb888::BLACK, 0x80), Rgb888::new(0x80, 0x80, 0x80));
+    }
+
+    #[test]
+    fn alpha_sprites_blend_with_the_framebuffer() {
+        use crate::framebuffer::{Framebuffer, PixelByteOrder};
+
+        let data = alpha_row();
+        let mut fb = Framebuffer::new(4, 1, PixelByteOrder::BigEndian);
+        fb.clear(Rgb565::GREEN).unwrap();
+        AlphaSprite::from_slice(&data, Point::zero()).unwrap().draw_blended(&mut fb).unwrap();
+
+        let half = Rgb565::from(blend(Rgb888::WHITE, Rgb888::from(Rgb565::GREEN), 0x80));
+        let drawn: alloc::vec::Vec<_> = (0..4).map(|x| fb.read_pixel(Point::new(x, 0)).unwrap()).collect();
+        assert_eq!(drawn, [RED, Rgb565::GREEN, half, BLUE]);
+    }
+
+    #[test]
+    fn without_readback_alpha_is_a_threshold() {
+        let data = alpha_row();
+        let mut display = mock_display();
+        AlphaSprite::from_slice(&data, Point::zero()).unwrap().draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 4, 1), [Some(RED), None, Some(Rgb565::WHITE), Some(BLUE)]);
+    }
+
+    #[test]
+    fn bmps_without_alpha_are_magenta_keyed() {
+        let data = bmp24(&[&[[0xff, 0, 0], MAGENTA]]);
+        let mut display = mock_display();
+        AlphaSprite::from_slice(&data, Point::zero()).unwrap().draw(&mut display).unwrap();
+        assert_eq!(pixels(&display, 2, 1), [Some(RED), None]);
+    }
+
+    #[test]
+    fn cut_short_alpha_bmps_are_rejected() {
+        let data = alpha_row();
+        assert!(AlphaSprite::from_slice(&data[..data.len() - 1], Point::zero()).is_err());
+        assert!(AlphaSprite::from_slice(b"BM", Point::zero()).is_err());
+    }
+
+    // three 1x1 frames in a row: red, blue, magenta
+    fn sheet() -> alloc::vec::Vec<u8> {
+        bmp24(&[&[[0xff, 0, 0], [0, 0, 0xff], MAGENTA]])
+    }
+
+    #[test]
+    fn one_shot_animation_stops_on_its_last_frame() {
+        let data = sheet();
+        let bmp = Bmp::<Rgb565>::from_slice(&data).unwrap();
+        let mut animation = AnimatedSprite::new(&bmp, Size::new(1, 1), 3, 100, false);
+        assert_eq!(animation.frame_index(), 0);
+        animation.tick(199);
+        assert_eq!(animation.frame_index(), 1);
+        assert!(!animation.is_finished());
+
+        animation.tick(1000);
+        assert_eq!(animation.frame_index(), 2);
+        assert!(animation.is_finished());
+        // the last frame is all magenta, so nothing is drawn
+        let mut display = mock_display();
+        animation.draw(&mut display, Point::zero()).unwrap();
+        assert!(display.affected_area().is_zero_sized());
+
+        animation.restart();
+        assert_eq!(animation.frame_index(), 0);
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn looping_animation_wraps_around() {
+        let data = sheet();
+        let bmp = Bmp::<Rgb565>::from_slice(&data).unwrap();
+        let mut animation = AnimatedSprite::new(&bmp, Size::new(1, 1), 3, 100, true).with_transparency(None);
+        animation.tick(350);
+        assert_eq!(animation.frame_index(), 0);
+        assert!(!animation.is_finished());
+
+        animation.tick(100);
+        let mut display = mock_display();
+        animation.draw(&mut display, Point::new(5, 5)).unwrap();
+        assert_eq!(display.get_pixel(Point::new(5, 5)), Some(BLUE));
+        assert_eq!(display.affected_area(), Rectangle::new(Point::new(5, 5), Size::new(1, 1)));
+    }
+
+    fn gauge(size: Size, ratio: f32) -> Gauge {
+        Gauge::new(Point::zero(), size, ratio)
+            .fill_color(RED)
+            .border_color(BLUE)
+            .background_color(Rgb565::BLACK)
+    }
+
+    #[test]
+    fn horizontal_gauge_fills_from_the_left() {
+        let mut display = mock_display();
+        gauge(Size::new(10, 4), 0.5).draw(&mut display).unwrap();
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(BLUE));
+        assert_eq!(display.get_pixel(Point::new(4, 2)), Some(RED));
+        assert_eq!(display.get_pixel(Point::new(5, 2)), Some(Rgb565::BLACK));
+        assert_eq!(display.get_pixel(Point::new(10, 2)), None);
+
+        let mut display = mock_display();
+        gauge(Size::new(10, 4), 7.0).draw(&mut display).unwrap();
+        assert_eq!(display.get_pixel(Point::new(8, 2)), Some(RED));
+    }
+
+    #[test]
+    fn vertical_gauge_fills_from_the_bottom() {
+        let mut display = mock_display();
+        gauge(Size::new(4, 8), 0.25).orientation(GaugeOrientation::Vertical).draw(&mut display).unwrap();
+        assert_eq!(display.get_pixel(Point::new(1, 6)), Some(RED));
+        assert_eq!(display.get_pixel(Point::new(1, 5)), Some(Rgb565::BLACK));
+
+        let mut display = mock_display();
+        gauge(Size::new(4, 8), -1.0).orientation(GaugeOrientation::Vertical).draw(&mut display).unwrap();
+        assert_eq!(display.get_pixel(Point::new(1, 6)), Some(Rgb565::BLACK));
+    }
+}
@@ -1,7 +1,65 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use heapless::Deque;
+
 const MOVING_AVG_ALPHA: f32 = 0.1;
+// battery voltage sags with every beep and backlight change, smooth it heavily
+const BATTERY_ALPHA: f32 = 0.02;
+// loudness has to follow claps and voices, barely smooth it
+const MIC_ALPHA: f32 = 0.6;
+// tilt follows the hand but shouldn't jitter with every bump
+const TILT_ALPHA: f32 = 0.3;
+// battery reading has to be right at boot, average the first samples evenly
+const BATTERY_WARMUP_SAMPLES: u8 = 10;
+
+/// Smoothed accelerometer intensity (0..1) that counts as shaking
+pub const SHAKE_THRESHOLD: f32 = 0.5;
+/// Consecutive accelerometer samples above `SHAKE_THRESHOLD` needed for a shake
+pub const SHAKE_MIN_SAMPLES: u8 = 3;
+/// No new shake is reported for this long after one, so a long shake fires once
+pub const SHAKE_DEBOUNCE_MS: u32 = 1000;
+/// Rise of the mic loudness (0..1) over the background noise that can start a clap
+pub const CLAP_RISE: f32 = 0.4;
+/// A clap has to drop back to under half of `CLAP_RISE` above the background within
+/// this long, anything loud for longer is talking or music
+pub const CLAP_WINDOW_MS: u32 = 200;
+// background noise follows the room slowly, a clap barely moves it
+const CLAP_BACKGROUND_ALPHA: f32 = 0.05;
+
+/// Full scale of the light sensor's ADC, light readings are passed as `raw / LIGHT_ADC_MAX`
+pub const LIGHT_ADC_MAX: u16 = 4095;
+
+/// ADC readings of the light sensor covered and in bright light, they become 0 and 1.
+/// Every unit reads a bit differently, see the calibration in the sensor screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightCalibration {
+    pub dark_raw: u16,
+    pub bright_raw: u16,
+}
+
+impl Default for LightCalibration {
+    fn default() -> Self {
+        Self {
+            dark_raw: 0,
+            bright_raw: LIGHT_ADC_MAX,
+        }
+    }
+}
+
+impl LightCalibration {
+    /// Linear between the two points, clamped to 0..1
+    pub fn remap(&self, raw: u16) -> f32 {
+        if self.dark_raw == self.bright_raw {
+            return if raw >= self.bright_raw { 1.0 } else { 0.0 };
+        }
+        let level = (raw as f32 - self.dark_raw as f32) / (self.bright_raw as f32 - self.dark_raw as f32);
+        level.clamp(0.0, 1.0)
+    }
+}
 
 #[derive(PartialEq)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum SensorState {
     Uninitialized = 0,
     Normal,
@@ -16,6 +74,12 @@ pub struct SensorData {
     moving_avg: f32,
     state: SensorState,
     last_updated_ms: u32,
+    /// EMA weight of a new sample, higher follows the input faster
+    alpha: f32,
+    /// number of first samples averaged with a plain running mean before switching
+    /// to the EMA, so the initial value settles quickly
+    warmup_samples: u8,
+    sample_count: u8,
 }
 
 impl SensorData {
@@ -25,22 +89,46 @@ impl SensorData {
             moving_avg: 0.0,
             state: SensorState::Uninitialized,
             last_updated_ms: 0,
+            alpha: MOVING_AVG_ALPHA,
+            warmup_samples: 0,
+            sample_count: 0,
+        }
+    }
+
+    pub fn with_alpha(alpha: f32) -> Self {
+        Self {
+            alpha,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_warmup(warmup_samples: u8) -> Self {
+        Self {
+            warmup_samples,
+            ..Self::new()
         }
     }
 
     pub fn update(&mut self, raw_value: f32, current_time_ms: u32) {
         match self.state {
-            SensorState::SensorError | SensorState::Uninitialized => {
-                // Debug: sensor not initialized or in error state
-                return;
+            SensorState::SensorError => {
+                // Debug: sensor in error state
+            }
+            SensorState::Uninitialized => {
+                // first reading seeds the average
+                self.raw = raw_value;
+                self.moving_avg = raw_value;
+                self.sample_count = 1;
+                self.state = SensorState::Normal;
+                self.last_updated_ms = current_time_ms;
             }
             SensorState::Event | SensorState::Normal => {
                 self.raw = raw_value;
-                if self.state == SensorState::Uninitialized {
-                    self.moving_avg = raw_value;
-                    self.state = SensorState::Normal;
+                if self.sample_count < self.warmup_samples {
+                    self.sample_count += 1;
+                    self.moving_avg += (raw_value - self.moving_avg) / self.sample_count as f32;
                 } else {
-                    self.moving_avg = MOVING_AVG_ALPHA * raw_value + (1.0 - MOVING_AVG_ALPHA) * self.moving_avg;
+                    self.moving_avg = self.alpha * raw_value + (1.0 - self.alpha) * self.moving_avg;
                 }
                 self.last_updated_ms = current_time_ms;
             }
@@ -48,6 +136,7 @@ impl SensorData {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SensorType {
     BatteryVoltage = 0,
     Thermometer,
@@ -56,6 +145,72 @@ pub enum SensorType {
     MicLoudness,
 }
 
+impl SensorType {
+    pub const COUNT: usize = 5;
+
+    /// In discriminant order, `ALL[i] as usize == i`
+    pub const ALL: [SensorType; SensorType::COUNT] = [
+        SensorType::BatteryVoltage,
+        SensorType::Thermometer,
+        SensorType::LightSensor,
+        SensorType::Accelerometer,
+        SensorType::MicLoudness,
+    ];
+
+    pub fn all() -> impl Iterator<Item = SensorType> {
+        Self::ALL.into_iter()
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Short name used in text formats (`InputEvent`, sensor scripts)
+    pub fn name(self) -> &'static str {
+        match self {
+            SensorType::BatteryVoltage => "battery",
+            SensorType::Thermometer => "temperature",
+            SensorType::LightSensor => "light",
+            SensorType::Accelerometer => "accelerometer",
+            SensorType::MicLoudness => "mic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().find(|sensor| sensor.name() == name)
+    }
+
+    /// (min, max) of the values the platform reports, in `unit`. Gauges show where a
+    /// reading sits in it, the desktop sliders are limited to it.
+    pub const fn range(self) -> (f32, f32) {
+        match self {
+            // a LiPo from empty to fully charged
+            SensorType::BatteryVoltage => (2.5, 4.2),
+            SensorType::Thermometer => (-40.0, 80.0),
+            SensorType::LightSensor | SensorType::Accelerometer | SensorType::MicLoudness => (0.0, 1.0),
+        }
+    }
+
+    /// Unit of the values, empty for the ones normalized to 0..1. ASCII, so the device fonts
+    /// can draw it.
+    pub const fn unit(self) -> &'static str {
+        match self {
+            SensorType::BatteryVoltage => "V",
+            SensorType::Thermometer => "C",
+            SensorType::LightSensor | SensorType::Accelerometer | SensorType::MicLoudness => "",
+        }
+    }
+}
+
+impl TryFrom<usize> for SensorType {
+    /// the index that's out of range
+    type Error = usize;
+
+    fn try_from(index: usize) -> Result<Self, Self::Error> {
+        Self::ALL.get(index).copied().ok_or(index)
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Button {
@@ -68,6 +223,36 @@ pub enum Button {
     Pwr,
 }
 
+impl Button {
+    pub const ALL: [Button; 7] = [
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+        Button::A,
+        Button::B,
+        Button::Pwr,
+    ];
+}
+
+impl Button {
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::Up => "up",
+            Button::Down => "down",
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::A => "a",
+            Button::B => "b",
+            Button::Pwr => "pwr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ButtonState {
     JustPressed,
@@ -76,23 +261,79 @@ pub enum ButtonState {
     Released,
 }
 
+impl ButtonState {
+    const ALL: [ButtonState; 4] = [
+        ButtonState::JustPressed,
+        ButtonState::Pressed,
+        ButtonState::JustReleased,
+        ButtonState::Released,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ButtonState::JustPressed => "just_pressed",
+            ButtonState::Pressed => "pressed",
+            ButtonState::JustReleased => "just_released",
+            ButtonState::Released => "released",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.name() == name)
+    }
+}
+
 #[derive(Debug)]
 pub struct Input {
     buttons: [ButtonState; 7],
-    sensors: [SensorData; 5],
+    /// how long each button has been held, as of the last and the previous `tick`
+    hold_ms: [u32; 7],
+    prev_hold_ms: [u32; 7],
+    /// held buttons that don't count towards `hold_ms` until they're released
+    hold_suppressed: [bool; 7],
+    sensors: [SensorData; SensorType::COUNT],
+    shake: ShakeDetector,
+    clap: ClapDetector,
+    light_calibration: LightCalibration,
+    /// last light reading before calibration
+    light_adc: u16,
+    /// smoothed, see `tilt`
+    tilt_x: f32,
+    tilt_y: f32,
+    /// see `set_charging`
+    charging: bool,
+    /// presses no scene update has seen yet, they outlive frames that ran no update
+    unseen_presses: [bool; 7],
+    /// edges were already seen this frame, see `consume_edges`
+    edges_consumed: bool,
 }
 
 impl Input {
     pub fn new() -> Self {
         Self {
             buttons: [ButtonState::Released; 7],
+            hold_ms: [0; 7],
+            prev_hold_ms: [0; 7],
+            hold_suppressed: [false; 7],
             sensors: [
+                SensorData {
+                    warmup_samples: BATTERY_WARMUP_SAMPLES,
+                    ..SensorData::with_alpha(BATTERY_ALPHA)
+                },
                 SensorData::new(),
                 SensorData::new(),
                 SensorData::new(),
-                SensorData::new(),
-                SensorData::new(),
-            ], //TODO
+                SensorData::with_alpha(MIC_ALPHA),
+            ],
+            shake: ShakeDetector::new(),
+            clap: ClapDetector::new(),
+            light_calibration: LightCalibration::default(),
+            light_adc: 0,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            charging: false,
+            unseen_presses: [false; 7],
+            edges_consumed: false,
         }
     }
 
@@ -102,12 +343,115 @@ impl Input {
         raw_value: f32,
         current_time_ms: u32,
     ) {
-        let sensor = &mut self.sensors[sensor_type as usize];
+        let raw_value = if sensor_type == SensorType::LightSensor {
+            self.light_adc = (raw_value.clamp(0.0, 1.0) * LIGHT_ADC_MAX as f32 + 0.5) as u16;
+            self.light_calibration.remap(self.light_adc)
+        } else {
+            raw_value
+        };
+        let sensor = &mut self.sensors[sensor_type.index()];
         sensor.update(raw_value, current_time_ms);
+
+        if sensor_type == SensorType::Accelerometer && sensor.state == SensorState::Normal {
+            self.shake.sample(sensor.moving_avg);
+        }
+        if sensor_type == SensorType::MicLoudness && sensor.state == SensorState::Normal {
+            self.clap.sample(sensor.raw, current_time_ms);
+        }
+    }
+
+    /// True for one frame after the device was shaken, see `SHAKE_THRESHOLD`
+    pub fn shake_detected(&self) -> bool {
+        self.shake.detected && !self.edges_consumed
+    }
+
+    /// True for one frame after a clap, a short loud peak in the mic loudness,
+    /// see `CLAP_RISE` and `CLAP_WINDOW_MS`
+    pub fn clap_detected(&self) -> bool {
+        self.clap.detected && !self.edges_consumed
+    }
+
+    /// Applied to light readings from now on, the engine sets it from the settings
+    pub fn set_light_calibration(&mut self, calibration: LightCalibration) {
+        self.light_calibration = calibration;
+    }
+
+    /// Last light reading in ADC counts, before the calibration
+    pub fn light_adc_raw(&self) -> u16 {
+        self.light_adc
+    }
+
+    /// Feed the accelerometer's X and Y axes in g, in screen orientation: +x points
+    /// to the right edge of the screen, +y to the bottom edge (the way gravity pulls when
+    /// the device is held upright). The platform maps the board's axes to that, Z isn't
+    /// needed. Keep feeding `Accelerometer` too, shakes are detected from it.
+    pub fn update_tilt(&mut self, x_g: f32, y_g: f32) {
+        self.tilt_x += TILT_ALPHA * (x_g.clamp(-1.0, 1.0) - self.tilt_x);
+        self.tilt_y += TILT_ALPHA * (y_g.clamp(-1.0, 1.0) - self.tilt_y);
+    }
+
+    /// How far the device is tilted along the screen axes, -1..1 each, see `update_tilt`.
+    /// At rest that's the sine of the tilt angle, (0, 0) lying flat or on a platform
+    /// without an accelerometer.
+    pub fn tilt(&self) -> (f32, f32) {
+        (self.tilt_x, self.tilt_y)
+    }
+
+    /// Whether the charger is charging the battery, for platforms that can tell. The battery
+    /// voltage reads higher than the charge it holds then, so it isn't turned into a level.
+    pub fn set_charging(&mut self, charging: bool) {
+        self.charging = charging;
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.charging
+    }
+
+    /// Smoothed value of a sensor, 0 until its first reading
+    pub fn get_sensor_value(&self, sensor_type: SensorType) -> f32 {
+        self.sensors[sensor_type.index()].moving_avg
+    }
+
+    /// Last reading of a sensor, as passed to `update_sensor`
+    pub fn get_sensor_raw(&self, sensor_type: SensorType) -> f32 {
+        self.sensors[sensor_type.index()].raw
+    }
+
+    pub fn get_sensor_state(&self, sensor_type: SensorType) -> SensorState {
+        self.sensors[sensor_type.index()].state
+    }
+
+    /// Set how many first samples of a sensor use a running mean instead of the EMA.
+    /// Samples already taken aren't re-averaged.
+    pub fn set_sensor_warmup(&mut self, sensor_type: SensorType, warmup_samples: u8) {
+        self.sensors[sensor_type.index()].warmup_samples = warmup_samples;
+    }
+
+    /// Set the EMA weight of new samples for a sensor, clamped to 0..=1
+    pub fn set_sensor_alpha(&mut self, sensor_type: SensorType, alpha: f32) {
+        self.sensors[sensor_type.index()].alpha = alpha.clamp(0.0, 1.0);
     }
 
     pub fn set_button(&mut self, button: Button, state: ButtonState) {
         self.buttons[button as usize] = state;
+        if state == ButtonState::JustPressed {
+            self.unseen_presses[button as usize] = true;
+        }
+    }
+
+    /// Set a button from its raw level, the just pressed/released states are worked out
+    /// from the previous one. Call once per frame for every button, before `tick`.
+    pub fn update_button_level(&mut self, button: Button, pressed: bool) {
+        self.set_button(button, self.next_button_state(button, pressed));
+    }
+
+    fn next_button_state(&self, button: Button, pressed: bool) -> ButtonState {
+        match (self.is_pressed(button), pressed) {
+            (false, true) => ButtonState::JustPressed,
+            (true, true) => ButtonState::Pressed,
+            (true, false) => ButtonState::JustReleased,
+            (false, false) => ButtonState::Released,
+        }
     }
 
     pub fn is_pressed(&self, button: Button) -> bool {
@@ -115,8 +459,758 @@ impl Input {
         state == ButtonState::JustPressed || state == ButtonState::Pressed
     }
 
+    /// Pressed since the last `consume_edges`, even if it was released again since
     pub fn is_just_pressed(&self, button: Button) -> bool {
-        let state = self.buttons[button as usize];
-        state == ButtonState::JustPressed
+        self.unseen_presses[button as usize] && !self.edges_consumed
+    }
+
+    /// Mark the one-frame checks (`is_just_pressed`, `just_long_pressed`, `shake_detected`,
+    /// `clap_detected`) as seen, they're false until new edges come in. The engine calls it
+    /// after every scene update. Until then edges are kept across `tick`s, so frames that
+    /// run no fixed step don't lose them.
+    pub(crate) fn consume_edges(&mut self) {
+        self.edges_consumed = true;
+        self.unseen_presses = [false; 7];
+    }
+
+    /// Advance hold timers, call once per frame after the button states are set
+    pub fn tick(&mut self, dt_ms: u32) {
+        for i in 0..self.buttons.len() {
+            let pressed = matches!(self.buttons[i], ButtonState::JustPressed | ButtonState::Pressed);
+            // a long press nobody saw yet stays reported
+            if self.edges_consumed {
+                self.prev_hold_ms[i] = self.hold_ms[i];
+            }
+
+            if !pressed {
+                self.hold_ms[i] = 0;
+                self.hold_suppressed[i] = false;
+            } else if !self.hold_suppressed[i] {
+                self.hold_ms[i] = self.hold_ms[i].saturating_add(dt_ms);
+            }
+        }
+
+        self.shake.tick(dt_ms, self.edges_consumed);
+        self.clap.tick(self.edges_consumed);
+        self.edges_consumed = false;
+    }
+
+    /// Make buttons that are currently held not count as held until they're released,
+    /// so a press that started before a scene change can't fire a long press in the new one
+    pub fn suppress_holds(&mut self) {
+        for i in 0..self.buttons.len() {
+            if self.hold_ms[i] > 0 {
+                self.hold_suppressed[i] = true;
+                self.hold_ms[i] = 0;
+                self.prev_hold_ms[i] = 0;
+            }
+        }
+    }
+
+    pub fn is_held_for(&self, button: Button, ms: u32) -> bool {
+        self.hold_ms[button as usize] >= ms
+    }
+
+    /// True from the tick the hold time crosses `threshold_ms` until the edges are consumed
+    pub fn just_long_pressed(&self, button: Button, threshold_ms: u32) -> bool {
+        let i = button as usize;
+        self.hold_ms[i] >= threshold_ms && self.prev_hold_ms[i] < threshold_ms && !self.edges_consumed
+    }
+}
+
+// Counts accelerometer samples over the threshold. A shake found since the last
+// tick is reported from the next tick until the one after it, i.e. for one frame.
+#[derive(Debug)]
+struct ShakeDetector {
+    samples_above: u8,
+    pending: bool,
+    detected: bool,
+    cooldown_ms: u32,
+}
+
+impl ShakeDetector {
+    const fn new() -> Self {
+        Self {
+            samples_above: 0,
+            pending: false,
+            detected: false,
+            cooldown_ms: 0,
+        }
+    }
+
+    fn sample(&mut self, intensity: f32) {
+        if intensity < SHAKE_THRESHOLD {
+            self.samples_above = 0;
+            return;
+        }
+
+        self.samples_above = self.samples_above.saturating_add(1);
+        if self.samples_above >= SHAKE_MIN_SAMPLES && self.cooldown_ms == 0 && !self.pending {
+            self.pending = true;
+            self.cooldown_ms = SHAKE_DEBOUNCE_MS;
+        }
+    }
+
+    // `seen` when a scene update saw the last shake reported, or there was none
+    fn tick(&mut self, dt_ms: u32, seen: bool) {
+        self.detected = self.pending || (self.detected && !seen);
+        self.pending = false;
+        if !self.detected {
+            self.cooldown_ms = self.cooldown_ms.saturating_sub(dt_ms);
+        }
+    }
+}
+
+// Looks for the loudness jumping over the background and falling back soon after.
+// Reported for one frame like a shake.
+#[derive(Debug)]
+struct ClapDetector {
+    background: f32,
+    /// when the loudness jumped, while waiting for it to fall again
+    peak_start_ms: Option<u32>,
+    pending: bool,
+    detected: bool,
+}
+
+impl ClapDetector {
+    const fn new() -> Self {
+        Self {
+            background: 0.0,
+            peak_start_ms: None,
+            pending: false,
+            detected: false,
+        }
+    }
+
+    fn sample(&mut self, loudness: f32, time_ms: u32) {
+        let above = loudness - self.background;
+        match self.peak_start_ms {
+            None if above >= CLAP_RISE => self.peak_start_ms = Some(time_ms),
+            None => self.background += CLAP_BACKGROUND_ALPHA * above,
+            Some(start_ms) if above < CLAP_RISE / 2.0 => {
+                self.peak_start_ms = None;
+                if time_ms.wrapping_sub(start_ms) <= CLAP_WINDOW_MS {
+                    self.pending = true;
+                }
+            }
+            Some(start_ms) if time_ms.wrapping_sub(start_ms) > CLAP_WINDOW_MS => {
+                // stayed loud, the room got noisier
+                self.peak_start_ms = None;
+                self.background = loudness;
+            }
+            Some(_) => (),
+        }
+    }
+
+    fn tick(&mut self, seen: bool) {
+        self.detected = self.pending || (self.detected && !seen);
+        self.pending = false;
+    }
+}
+
+/// Integrate-and-threshold debouncing for platforms reading raw button pins. The count
+/// goes up while the pin reads pressed and down while it reads released, the level only
+/// flips once the count hits 0 or `depth`, so bounces shorter than that are ignored.
+pub struct Debouncer {
+    depth: u8,
+    count: u8,
+    pressed: bool,
+}
+
+impl Debouncer {
+    /// `depth` readings in a row flip the level, at least 1
+    pub fn new(depth: u8) -> Self {
+        Self { depth: depth.max(1), count: 0, pressed: false }
+    }
+
+    /// An edge seen by an interrupt counts as a press right away, the count then has to
+    /// run down before it's released. Ignored unless the button has settled as released,
+    /// which also drops the edges of the contacts bouncing on release.
+    pub fn press_edge(&mut self) {
+        if !self.pressed && self.count == 0 {
+            self.count = self.depth;
+            self.pressed = true;
+        }
+    }
+
+    /// Feed one raw reading per frame, returns the debounced level
+    pub fn update(&mut self, raw_pressed: bool) -> bool {
+        if raw_pressed {
+            self.count = (self.count + 1).min(self.depth);
+        } else {
+            self.count = self.count.saturating_sub(1);
+        }
+
+        if self.count == self.depth {
+            self.pressed = true;
+        } else if self.count == 0 {
+            self.pressed = false;
+        }
+        self.pressed
+    }
+}
+
+const COMBO_HISTORY_LEN: usize = 8;
+
+/// Detects button sequences (cheat codes) and chords, feed it with `update` every frame
+#[derive(Debug, Default)]
+pub struct ComboDetector {
+    /// last presses with their timestamps, oldest first
+    presses: Deque<(Button, u32), COMBO_HISTORY_LEN>,
+    held: [bool; 7],
+}
+
+impl ComboDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, input: &Input, now_ms: u32) {
+        for button in Button::ALL {
+            self.held[button as usize] = input.is_pressed(button);
+
+            if input.is_just_pressed(button) {
+                if self.presses.is_full() {
+                    self.presses.pop_front();
+                }
+                let _ = self.presses.push_back((button, now_ms));
+            }
+        }
+    }
+
+    /// True if the latest presses are exactly `sequence`, all within `window_ms`.
+    /// Sequences longer than the history never match.
+    pub fn matches(&self, sequence: &[Button], window_ms: u32) -> bool {
+        if sequence.is_empty() || sequence.len() > self.presses.len() {
+            return false;
+        }
+
+        let mut recent = self.presses.iter().skip(self.presses.len() - sequence.len());
+        let Some(&(first_button, first_ms)) = recent.next() else {
+            return false;
+        };
+        if first_button != sequence[0] {
+            return false;
+        }
+
+        let mut last_ms = first_ms;
+        for (&(button, time_ms), &expected) in recent.zip(&sequence[1..]) {
+            if button != expected {
+                return false;
+            }
+            last_ms = time_ms;
+        }
+
+        last_ms.wrapping_sub(first_ms) <= window_ms
+    }
+
+    /// True while all `buttons` are held together
+    pub fn all_pressed(&self, buttons: &[Button]) -> bool {
+        !buttons.is_empty() && buttons.iter().all(|&button| self.held[button as usize])
+    }
+
+    /// Forget recorded presses, so a matched sequence doesn't match again
+    pub fn clear(&mut self) {
+        self.presses.clear();
+    }
+}
+
+/// One call on `Input`, as recorded by `InputRecorder`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Button { button: Button, state: ButtonState },
+    Sensor { sensor: SensorType, value: f32 },
+    /// `Input::tick`, the end of a frame's input
+    Tick { dt_ms: u32 },
+}
+
+/// An `InputEvent` with the platform time it happened at.
+///
+/// As text it's one line of comma separated fields, `time_ms,kind,...`:
+/// - `1200,button,a,just_pressed` (names from `Button::name` and `ButtonState::name`)
+/// - `1200,sensor,light,0.42` (names from `SensorType::name`)
+/// - `1232,tick,32`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedInputEvent {
+    pub time_ms: u32,
+    pub event: InputEvent,
+}
+
+impl TimedInputEvent {
+    /// Parse a line written by the `Display` impl, `None` if it's malformed
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim().split(',').map(str::trim);
+        let time_ms = fields.next()?.parse().ok()?;
+        let event = match fields.next()? {
+            "button" => InputEvent::Button {
+                button: Button::from_name(fields.next()?)?,
+                state: ButtonState::from_name(fields.next()?)?,
+            },
+            "sensor" => InputEvent::Sensor {
+                sensor: SensorType::from_name(fields.next()?)?,
+                value: fields.next()?.parse().ok()?,
+            },
+            "tick" => InputEvent::Tick {
+                dt_ms: fields.next()?.parse().ok()?,
+            },
+            _ => return None,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self { time_ms, event })
+    }
+
+    fn apply(&self, input: &mut Input) {
+        match self.event {
+            InputEvent::Button { button, state } => input.set_button(button, state),
+            InputEvent::Sensor { sensor, value } => input.update_sensor(sensor, value, self.time_ms),
+            InputEvent::Tick { dt_ms } => input.tick(dt_ms),
+        }
+    }
+}
+
+impl fmt::Display for TimedInputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.event {
+            InputEvent::Button { button, state } => {
+                write!(f, "{},button,{},{}", self.time_ms, button.name(), state.name())
+            }
+            InputEvent::Sensor { sensor, value } => {
+                write!(f, "{},sensor,{},{}", self.time_ms, sensor.name(), value)
+            }
+            InputEvent::Tick { dt_ms } => write!(f, "{},tick,{}", self.time_ms, dt_ms),
+        }
+    }
+}
+
+/// Forwards calls to an `Input` and hands each one to `sink` as a `TimedInputEvent`.
+/// With the engine's fixed rng seed, replaying them with `InputPlayer` gives the same game.
+pub struct InputRecorder<F: FnMut(TimedInputEvent)> {
+    sink: F,
+}
+
+impl<F: FnMut(TimedInputEvent)> InputRecorder<F> {
+    pub fn new(sink: F) -> Self {
+        Self { sink }
+    }
+
+    pub fn set_button(&mut self, input: &mut Input, button: Button, state: ButtonState, time_ms: u32) {
+        self.record(input, time_ms, InputEvent::Button { button, state });
+    }
+
+    /// Like `Input::update_button_level`, only changes of the state are recorded
+    pub fn update_button_level(&mut self, input: &mut Input, button: Button, pressed: bool, time_ms: u32) {
+        let state = input.next_button_state(button, pressed);
+        if state != input.buttons[button as usize] {
+            self.set_button(input, button, state, time_ms);
+        }
+    }
+
+    pub fn update_sensor(&mut self, input: &mut Input, sensor: SensorType, value: f32, time_ms: u32) {
+        self.record(input, time_ms, InputEvent::Sensor { sensor, value });
+    }
+
+    pub fn tick(&mut self, input: &mut Input, dt_ms: u32, time_ms: u32) {
+        self.record(input, time_ms, InputEvent::Tick { dt_ms });
+    }
+
+    fn record(&mut self, input: &mut Input, time_ms: u32, event: InputEvent) {
+        let event = TimedInputEvent { time_ms, event };
+        event.apply(input);
+        (self.sink)(event);
+    }
+}
+
+/// Feeds recorded events back into an `Input` once their time comes
+pub struct InputPlayer {
+    /// sorted by time
+    events: Vec<TimedInputEvent>,
+    next: usize,
+}
+
+impl InputPlayer {
+    pub fn new(mut events: Vec<TimedInputEvent>) -> Self {
+        // stable, so events of the same time keep their recorded order
+        events.sort_by_key(|event| event.time_ms);
+        Self { events, next: 0 }
+    }
+
+    /// Apply every event due at `now_ms`. Returns the time the applied ticks
+    /// advanced by, which is what the engine should be updated with this frame.
+    pub fn advance(&mut self, input: &mut Input, now_ms: u32) -> Option<u32> {
+        let mut ticked_ms = None;
+        while let Some(event) = self.events.get(self.next) {
+            if event.time_ms > now_ms {
+                break;
+            }
+            event.apply(input);
+            if let InputEvent::Tick { dt_ms } = event.event {
+                ticked_ms = Some(ticked_ms.unwrap_or(0) + dt_ms);
+            }
+            self.next += 1;
+        }
+        ticked_ms
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmup_averages_the_first_samples_evenly() {
+        let mut sensor = SensorData::with_warmup(4);
+        for (i, value) in [1.0, 2.0, 3.0, 6.0].into_iter().enumerate() {
+            sensor.update(value, i as u32);
+        }
+        assert_eq!(sensor.moving_avg, 3.0);
+
+        // the EMA takes over from there
+        sensor.update(13.0, 4);
+        assert_eq!(sensor.moving_avg, 3.0 + MOVING_AVG_ALPHA * 10.0);
+    }
+
+    #[test]
+    fn battery_settles_within_its_warmup() {
+        let mut input = Input::new();
+        for time_ms in 0..BATTERY_WARMUP_SAMPLES as u32 {
+            input.update_sensor(SensorType::BatteryVoltage, 3.7 + 0.01 * (time_ms % 2) as f32, time_ms);
+        }
+        assert!((input.get_sensor_value(SensorType::BatteryVoltage) - 3.705).abs() < 0.001);
+    }
+
+    fn press(input: &mut Input, combo: &mut ComboDetector, button: Button, now_ms: u32) {
+        for pressed in [true, false] {
+            input.update_button_level(button, pressed);
+            combo.update(input, now_ms);
+            input.consume_edges();
+            input.tick(16);
+        }
+    }
+
+    #[test]
+    fn combo_matches_the_latest_presses_within_the_window() {
+        let mut input = Input::new();
+        let mut combo = ComboDetector::new();
+        let sequence = [Button::Up, Button::Up, Button::Down];
+        for (i, button) in [Button::A, Button::Up, Button::Up, Button::Down].into_iter().enumerate() {
+            press(&mut input, &mut combo, button, i as u32 * 100);
+        }
+        assert!(combo.matches(&sequence, 200));
+        assert!(!combo.matches(&sequence, 199));
+        assert!(!combo.matches(&[Button::Up, Button::Down, Button::Down], 1000));
+
+        combo.clear();
+        assert!(!combo.matches(&sequence, 1000));
+    }
+
+    #[test]
+    fn combo_longer_than_the_history_never_matches() {
+        let mut input = Input::new();
+        let mut combo = ComboDetector::new();
+        let sequence = [Button::A; COMBO_HISTORY_LEN + 1];
+        for i in 0..sequence.len() {
+            press(&mut input, &mut combo, Button::A, i as u32);
+        }
+        assert!(!combo.matches(&sequence, u32::MAX));
+        assert!(combo.matches(&sequence[1..], u32::MAX));
+        assert!(!combo.matches(&[], u32::MAX));
+    }
+
+    #[test]
+    fn chord_needs_every_button_held() {
+        let mut input = Input::new();
+        let mut combo = ComboDetector::new();
+        input.update_button_level(Button::A, true);
+        combo.update(&input, 0);
+        assert!(!combo.all_pressed(&[Button::A, Button::B]));
+
+        input.update_button_level(Button::B, true);
+        combo.update(&input, 10);
+        assert!(combo.all_pressed(&[Button::A, Button::B]));
+        assert!(!combo.all_pressed(&[]));
+    }
+
+    #[test]
+    fn higher_alpha_follows_a_step_faster() {
+        let mut input = Input::new();
+        input.set_sensor_alpha(SensorType::Thermometer, 0.5);
+        input.set_sensor_alpha(SensorType::LightSensor, 2.0);
+        let mut slow = SensorData::with_alpha(0.1);
+        for time_ms in 0..5 {
+            let value = if time_ms == 0 { 0.0 } else { 1.0 };
+            input.update_sensor(SensorType::Thermometer, value, time_ms);
+            input.update_sensor(SensorType::LightSensor, value, time_ms);
+            slow.update(value, time_ms);
+        }
+        let fast = input.get_sensor_value(SensorType::Thermometer);
+        assert!(fast > slow.moving_avg);
+        assert!(fast < 1.0);
+        // clamped to 1, which takes every sample as is
+        assert_eq!(input.get_sensor_value(SensorType::LightSensor), 1.0);
+    }
+
+    #[test]
+    fn first_reading_seeds_the_average() {
+        let mut input = Input::new();
+        assert_eq!(input.get_sensor_state(SensorType::Thermometer), SensorState::Uninitialized);
+        assert_eq!(input.get_sensor_value(SensorType::Thermometer), 0.0);
+
+        input.update_sensor(SensorType::Thermometer, 21.5, 100);
+        assert_eq!(input.get_sensor_state(SensorType::Thermometer), SensorState::Normal);
+        assert_eq!(input.get_sensor_value(SensorType::Thermometer), 21.5);
+        assert_eq!(input.get_sensor_raw(SensorType::Thermometer), 21.5);
+    }
+
+    // one accelerometer sample per frame, `frames` of `intensity` each
+    fn shake_frames(input: &mut Input, intensity: f32, frames: u32) -> u32 {
+        let mut detected = 0;
+        for _ in 0..frames {
+            input.update_sensor(SensorType::Accelerometer, intensity, 0);
+            input.tick(16);
+            detected += input.shake_detected() as u32;
+            input.consume_edges();
+        }
+        detected
+    }
+
+    #[test]
+    fn shake_needs_several_strong_samples() {
+        let mut input = Input::new();
+        input.set_sensor_alpha(SensorType::Accelerometer, 1.0);
+        assert_eq!(shake_frames(&mut input, 0.9, SHAKE_MIN_SAMPLES as u32 - 1), 0);
+        assert_eq!(shake_frames(&mut input, 0.1, 1), 0);
+        assert_eq!(shake_frames(&mut input, 0.9, SHAKE_MIN_SAMPLES as u32 - 1), 0);
+        assert_eq!(shake_frames(&mut input, 0.9, 1), 1);
+    }
+
+    #[test]
+    fn long_shake_fires_once_per_debounce() {
+        let mut input = Input::new();
+        input.set_sensor_alpha(SensorType::Accelerometer, 1.0);
+        // reported for one frame, then not again while the debounce runs
+        assert_eq!(shake_frames(&mut input, 0.9, SHAKE_DEBOUNCE_MS / 16), 1);
+        assert_eq!(shake_frames(&mut input, 0.9, SHAKE_DEBOUNCE_MS / 16), 1);
+    }
+
+    #[test]
+    fn shakes_are_kept_until_consumed() {
+        let mut input = Input::new();
+        input.set_sensor_alpha(SensorType::Accelerometer, 1.0);
+        for _ in 0..SHAKE_MIN_SAMPLES {
+            input.update_sensor(SensorType::Accelerometer, 0.9, 0);
+        }
+        input.tick(16);
+        // frames without a scene update don't lose it
+        input.tick(16);
+        assert!(input.shake_detected());
+        input.consume_edges();
+        assert!(!input.shake_detected());
+        input.tick(16);
+        assert!(!input.shake_detected());
+    }
+
+    #[test]
+    fn presses_are_kept_until_consumed() {
+        let mut input = Input::new();
+        // tapped within a frame that ran no update
+        input.update_button_level(Button::Up, true);
+        input.tick(16);
+        input.update_button_level(Button::Up, false);
+        input.tick(16);
+        assert!(input.is_just_pressed(Button::Up));
+        input.consume_edges();
+        assert!(!input.is_just_pressed(Button::Up));
+
+        input.update_button_level(Button::Up, true);
+        input.tick(16);
+        input.consume_edges();
+        input.update_button_level(Button::Up, true);
+        input.tick(16);
+        assert!(!input.is_just_pressed(Button::Up));
+    }
+
+    // `Input::sensors` is indexed by discriminant, so they have to be 0..COUNT in order
+    #[test]
+    fn sensor_discriminants_are_their_indices() {
+        for (i, sensor) in SensorType::ALL.into_iter().enumerate() {
+            assert_eq!(sensor as usize, i);
+            assert_eq!(SensorType::try_from(i), Ok(sensor));
+            assert_eq!(SensorType::from_name(sensor.name()), Some(sensor));
+        }
+        assert_eq!(SensorType::try_from(SensorType::COUNT), Err(SensorType::COUNT));
+        assert_eq!(SensorType::all().count(), SensorType::COUNT);
+    }
+
+    #[test]
+    fn recorded_input_replays_the_same() {
+        let mut events = Vec::new();
+        let mut recorded = Input::new();
+        let mut recorder = InputRecorder::new(|event| events.push(event));
+        recorder.update_button_level(&mut recorded, Button::A, true, 0);
+        recorder.update_sensor(&mut recorded, SensorType::LightSensor, 0.25, 0);
+        recorder.tick(&mut recorded, 16, 0);
+        recorder.update_button_level(&mut recorded, Button::A, true, 16);
+        recorder.update_sensor(&mut recorded, SensorType::Thermometer, 21.5, 16);
+        recorder.tick(&mut recorded, 16, 16);
+        // still held, the state doesn't change so nothing is recorded
+        recorder.update_button_level(&mut recorded, Button::A, true, 32);
+        recorder.tick(&mut recorded, 16, 32);
+        assert_eq!(events.len(), 7);
+
+        // through the text format and back
+        let lines: Vec<_> = events.iter().map(alloc::string::ToString::to_string).collect();
+        assert_eq!(lines[0], "0,button,a,just_pressed");
+        let parsed: Vec<_> = lines.iter().map(|line| TimedInputEvent::parse(line).unwrap()).collect();
+        assert_eq!(parsed, events);
+
+        let mut replayed = Input::new();
+        let mut player = InputPlayer::new(parsed);
+        assert_eq!(player.advance(&mut replayed, 0), Some(16));
+        assert!(replayed.is_just_pressed(Button::A));
+        assert_eq!(player.advance(&mut replayed, 20), Some(16));
+        assert!(!player.is_finished());
+        assert_eq!(player.advance(&mut replayed, 100), Some(16));
+        assert!(player.is_finished());
+        assert_eq!(player.advance(&mut replayed, 200), None);
+
+        assert_eq!(replayed.buttons, recorded.buttons);
+        assert_eq!(replayed.sensors, recorded.sensors);
+    }
+
+    #[test]
+    fn malformed_event_lines_are_rejected() {
+        for line in ["", "12", "12,button,a", "12,button,z,pressed", "x,tick,16", "12,tick,16,4", "12,jump"] {
+            assert_eq!(TimedInputEvent::parse(line), None, "{:?}", line);
+        }
+        assert!(TimedInputEvent::parse(" 12, sensor, mic, 0.5 ").is_some());
+    }
+
+    #[test]
+    fn light_calibration_stretches_the_readings() {
+        let calibration = LightCalibration { dark_raw: 1000, bright_raw: 3000 };
+        assert_eq!(calibration.remap(500), 0.0);
+        assert_eq!(calibration.remap(2000), 0.5);
+        assert_eq!(calibration.remap(4000), 1.0);
+        assert_eq!(LightCalibration::default().remap(LIGHT_ADC_MAX), 1.0);
+
+        // no span, every reading is either side of it
+        let flat = LightCalibration { dark_raw: 2000, bright_raw: 2000 };
+        assert_eq!((flat.remap(1999), flat.remap(2000)), (0.0, 1.0));
+
+        let mut input = Input::new();
+        input.set_sensor_alpha(SensorType::LightSensor, 1.0);
+        input.set_light_calibration(calibration);
+        input.update_sensor(SensorType::LightSensor, 2000.0 / LIGHT_ADC_MAX as f32, 0);
+        assert_eq!(input.light_adc_raw(), 2000);
+        assert_eq!(input.get_sensor_value(SensorType::LightSensor), 0.5);
+    }
+
+    // Feeds one mic frame and tells if it was reported as a clap
+    fn hear(input: &mut Input, loudness: f32, time_ms: u32) -> bool {
+        input.update_sensor(SensorType::MicLoudness, loudness, time_ms);
+        input.tick(16);
+        let heard = input.clap_detected();
+        input.consume_edges();
+        heard
+    }
+
+    #[test]
+    fn a_short_peak_is_a_clap() {
+        let mut input = Input::new();
+        assert!(!hear(&mut input, 0.1, 0));
+        assert!(!hear(&mut input, 0.1 + CLAP_RISE, 16));
+        assert!(!hear(&mut input, 0.9, 32));
+        assert!(hear(&mut input, 0.1, 48));
+        assert!(!hear(&mut input, 0.1, 64));
+    }
+
+    #[test]
+    fn staying_loud_is_not_a_clap() {
+        let mut input = Input::new();
+        hear(&mut input, 0.0, 0);
+        let mut claps = 0;
+        for time_ms in (16..=CLAP_WINDOW_MS + 16).step_by(16) {
+            claps += hear(&mut input, 0.8, time_ms) as u32;
+        }
+        // the loud room is the new background, going quiet again isn't a clap either
+        claps += hear(&mut input, 0.8, CLAP_WINDOW_MS + 32) as u32;
+        claps += hear(&mut input, 0.0, CLAP_WINDOW_MS + 48) as u32;
+        assert_eq!(claps, 0);
+
+        // and it takes a rise over the louder background to start one
+        assert!(!hear(&mut input, 0.8 + CLAP_RISE / 2.0, CLAP_WINDOW_MS + 64));
+        assert!(!hear(&mut input, 0.8, CLAP_WINDOW_MS + 80));
+    }
+
+    #[test]
+    fn sensor_ranges_are_not_empty() {
+        // gauges divide by the width of the range
+        for sensor in SensorType::ALL {
+            let (min, max) = sensor.range();
+            assert!(min < max, "{sensor:?} range is empty");
+            assert!(sensor.unit().is_ascii());
+        }
+        assert_eq!(SensorType::LightSensor.range(), (0.0, 1.0));
+        assert_eq!(SensorType::LightSensor.unit(), "");
+    }
+
+    // debounced level after each reading
+    fn debounce(debouncer: &mut Debouncer, readings: &[bool]) -> Vec<bool> {
+        readings.iter().map(|&raw| debouncer.update(raw)).collect()
+    }
+
+    #[test]
+    fn short_bounces_are_ignored() {
+        let mut debouncer = Debouncer::new(3);
+        assert_eq!(debounce(&mut debouncer, &[true, true, false, true, false, false]), [false; 6]);
+
+        // and on the way back up
+        assert_eq!(debounce(&mut debouncer, &[true, true, true]), [false, false, true]);
+        assert_eq!(debounce(&mut debouncer, &[false, false, true, false, true]), [true; 5]);
+    }
+
+    #[test]
+    fn the_level_flips_once_the_count_saturates() {
+        let mut debouncer = Debouncer::new(3);
+        assert_eq!(debounce(&mut debouncer, &[true, true, true, true]), [false, false, true, true]);
+        assert_eq!(debounce(&mut debouncer, &[false, false, false]), [true, true, false]);
+    }
+
+    #[test]
+    fn a_depth_of_one_passes_the_level_through() {
+        for depth in [0, 1] {
+            let mut debouncer = Debouncer::new(depth);
+            let readings = [true, false, true, true, false, false, true];
+            assert_eq!(debounce(&mut debouncer, &readings), readings);
+        }
+    }
+
+    #[test]
+    fn press_edges_only_count_once_released() {
+        let mut debouncer = Debouncer::new(3);
+        debouncer.press_edge();
+        assert!(debouncer.update(false), "pressed on the edge");
+        assert!(debouncer.update(false));
+        assert!(!debouncer.update(false));
+
+        // the contacts bouncing on release don't press it again
+        debounce(&mut debouncer, &[true, true, true]);
+        debouncer.update(false);
+        debouncer.press_edge();
+        assert_eq!(debounce(&mut debouncer, &[false, false]), [true, false]);
+        debouncer.press_edge();
+        assert!(debouncer.update(false));
+
+        // nor does one while a bounce is still being counted
+        debounce(&mut debouncer, &[false, false]);
+        assert!(!debouncer.update(true));
+        debouncer.press_edge();
+        assert!(!debouncer.update(false));
     }
 }
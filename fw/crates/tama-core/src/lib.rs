@@ -1,14 +1,32 @@
 #![no_std]
 
 extern crate alloc;
+// for the test helpers, which run on the host, and the parts for platforms with threads
+#[cfg(any(test, feature = "test-support", feature = "std"))]
+extern crate std;
 
+pub mod battery;
+pub mod brightness;
 pub mod buzzer;
+pub mod clock;
 pub mod consts;
 pub mod engine;
+#[cfg(any(test, feature = "std"))]
+pub mod frame_exchange;
+pub mod frame_scheduler;
+pub mod framebuffer;
+pub mod gfx;
 pub mod input;
+pub mod log_buffer;
+pub mod menu;
 pub mod output;
+pub mod palette;
+pub mod pet;
+pub mod settings;
+pub mod toast;
+pub mod uptime;
 
 mod scenes;
 mod assets;
-mod gfx;
-
+#[cfg(test)]
+mod test_support;
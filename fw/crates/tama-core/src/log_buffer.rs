@@ -0,0 +1,278 @@
+// Ring buffer of recent log lines the platform captures, so scenes can show them on-device
+
+use core::fmt::Write;
+
+use embedded_graphics::prelude::RgbColor;
+use heapless::{Deque, String};
+
+use crate::consts::ColorType;
+
+/// Entries `LogBuffer` keeps
+pub const LOG_BUFFER_CAPACITY: usize = 32;
+/// Longer messages are cut off
+pub const LOG_LINE_MAX_LEN: usize = 96;
+/// Longer tags are cut off
+pub const LOG_TAG_MAX_LEN: usize = 16;
+
+/// Default `SizedLogBuffer::min_level`, the per-frame debug/trace output would
+/// push everything else out right away
+pub const CAPTURE_MIN_LEVEL: log::Level = log::Level::Info;
+
+/// Target used by `notice!`, the `log` crate has no level between warn and info
+pub const NOTICE_TARGET: &str = "notice";
+
+/// Log an important message that should always be captured, even with verbose levels filtered out
+#[macro_export]
+macro_rules! notice {
+    ($($arg:tt)+) => {
+        log::info!(target: $crate::log_buffer::NOTICE_TARGET, $($arg)+)
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Notice,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn from_record(level: log::Level, target: &str) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info if target == NOTICE_TARGET => LogLevel::Notice,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            LogLevel::Error => "E",
+            LogLevel::Warn => "W",
+            LogLevel::Notice => "N",
+            LogLevel::Info => "I",
+            LogLevel::Debug => "D",
+            LogLevel::Trace => "T",
+        }
+    }
+
+    /// What the level's lines are drawn in, picked to read well on a dark background
+    pub const fn color(self) -> ColorType {
+        match self {
+            LogLevel::Error => ColorType::RED,
+            LogLevel::Warn => ColorType::YELLOW,
+            LogLevel::Notice => ColorType::MAGENTA,
+            LogLevel::Info => ColorType::CYAN,
+            LogLevel::Debug => ColorType::GREEN,
+            LogLevel::Trace => ColorType::new(20, 40, 20),
+        }
+    }
+}
+
+/// `SizedLogEntry` with the default line length
+pub type LogEntry = SizedLogEntry<LOG_LINE_MAX_LEN>;
+
+/// Log line of up to `LINE` bytes
+#[derive(Debug, Clone)]
+pub struct SizedLogEntry<const LINE: usize> {
+    pub level: LogLevel,
+    /// Where it was logged from, see `tag_from_target`. Empty when unknown.
+    pub tag: String<LOG_TAG_MAX_LEN>,
+    pub message: String<LINE>,
+}
+
+impl<const LINE: usize> SizedLogEntry<LINE> {
+    pub fn from_record(record: &log::Record) -> Self {
+        let mut message = TruncatingWriter(String::new());
+        let _ = write!(message, "{}", record.args());
+        Self {
+            level: LogLevel::from_record(record.level(), record.target()),
+            tag: tag_from_target(record.target()),
+            message: message.0,
+        }
+    }
+}
+
+/// Short source tag for a log target: the last segment of a module path
+/// ("tama_core::scenes::sensors" is "sensors"), cut off at `LOG_TAG_MAX_LEN`.
+/// `NOTICE_TARGET` gives no tag, the level already says it's a notice.
+pub fn tag_from_target(target: &str) -> String<LOG_TAG_MAX_LEN> {
+    let mut tag = TruncatingWriter(String::new());
+    if target != NOTICE_TARGET {
+        let _ = tag.write_str(target.rsplit("::").next().unwrap_or(target));
+    }
+    tag.0
+}
+
+// Keeps whatever fits instead of failing the whole write
+struct TruncatingWriter<const N: usize>(String<N>);
+
+impl<const N: usize> Write for TruncatingWriter<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `SizedLogBuffer` with the default sizes, what the engine and the platforms' captures use
+pub type LogBuffer = SizedLogBuffer<LOG_BUFFER_CAPACITY, LOG_LINE_MAX_LEN>;
+
+/// Keeps the last `CAP` entries of up to `LINE` bytes each, the oldest one is dropped when full
+#[derive(Debug)]
+pub struct SizedLogBuffer<const CAP: usize, const LINE: usize> {
+    entries: Deque<SizedLogEntry<LINE>, CAP>,
+    /// a notice came in since `acknowledge_notices`, stays set if it's pushed out
+    unacknowledged_notice: bool,
+    min_level: log::Level,
+}
+
+impl<const CAP: usize, const LINE: usize> Default for SizedLogBuffer<CAP, LINE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAP: usize, const LINE: usize> SizedLogBuffer<CAP, LINE> {
+    pub const fn new() -> Self {
+        Self {
+            entries: Deque::new(),
+            unacknowledged_notice: false,
+            min_level: CAPTURE_MIN_LEVEL,
+        }
+    }
+
+    /// Capture records down to `level` instead of `CAPTURE_MIN_LEVEL`, usable in a `static`
+    pub const fn min_level(mut self, level: log::Level) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// `target` is turned into the entry's tag like a record's, see `tag_from_target`
+    pub fn push(&mut self, level: LogLevel, target: &str, message: &str) {
+        let mut line = TruncatingWriter(String::new());
+        let _ = line.write_str(message);
+        self.push_entry(SizedLogEntry {
+            level,
+            tag: tag_from_target(target),
+            message: line.0,
+        });
+    }
+
+    /// For `log::Log` implementations, records below the min level are skipped
+    pub fn capture(&mut self, record: &log::Record) {
+        if record.level() <= self.min_level {
+            self.push_entry(SizedLogEntry::from_record(record));
+        }
+    }
+
+    pub fn push_entry(&mut self, entry: SizedLogEntry<LINE>) {
+        if entry.level == LogLevel::Notice {
+            self.unacknowledged_notice = true;
+        }
+        if self.entries.is_full() {
+            self.entries.pop_front();
+        }
+        let _ = self.entries.push_back(entry);
+    }
+
+    /// Remove and return the oldest entry
+    pub fn pop_oldest(&mut self) -> Option<SizedLogEntry<LINE>> {
+        self.entries.pop_front()
+    }
+
+    /// Move every entry over to `other`, oldest first. `other` may keep fewer entries,
+    /// then only the newest ones are left
+    pub fn drain_into<const OTHER_CAP: usize>(&mut self, other: &mut SizedLogBuffer<OTHER_CAP, LINE>) {
+        while let Some(entry) = self.pop_oldest() {
+            other.push_entry(entry);
+        }
+    }
+
+    /// Oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &SizedLogEntry<LINE>> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.unacknowledged_notice = false;
+    }
+
+    /// A `Notice` entry was pushed since the last `acknowledge_notices`
+    pub fn has_unacknowledged_notice(&self) -> bool {
+        self.unacknowledged_notice
+    }
+
+    pub fn acknowledge_notices(&mut self) {
+        self.unacknowledged_notice = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_is_the_last_path_segment() {
+        assert_eq!(tag_from_target("tama_core::scenes::sensors"), "sensors");
+        assert_eq!(tag_from_target("wifi"), "wifi");
+        assert_eq!(tag_from_target(""), "");
+    }
+
+    #[test]
+    fn long_tags_are_cut_off() {
+        let tag = tag_from_target("tama_core::a_module_with_a_long_name");
+        assert_eq!(tag.len(), LOG_TAG_MAX_LEN);
+        assert_eq!(tag, "a_module_with_a_");
+        // on a character boundary
+        assert_eq!(tag_from_target("źźźźźźźźź"), "źźźźźźźź");
+    }
+
+    #[test]
+    fn notices_have_no_tag() {
+        let mut buffer = LogBuffer::new();
+        let record = log::Record::builder()
+            .level(log::Level::Info)
+            .target(NOTICE_TARGET)
+            .args(format_args!("battery low"))
+            .build();
+        buffer.capture(&record);
+
+        let entry = buffer.iter().next().unwrap();
+        assert_eq!(entry.level, LogLevel::Notice);
+        assert_eq!(entry.tag, "");
+        assert_eq!(entry.message, "battery low");
+        assert!(buffer.has_unacknowledged_notice());
+    }
+
+    #[test]
+    fn captured_records_keep_their_tag() {
+        let mut buffer = LogBuffer::new();
+        let record = log::Record::builder()
+            .level(log::Level::Warn)
+            .target("tama_core::input")
+            .args(format_args!("sensor stuck"))
+            .build();
+        buffer.capture(&record);
+        assert_eq!(buffer.iter().next().unwrap().tag, "input");
+        assert!(!buffer.has_unacknowledged_notice());
+    }
+}
@@ -0,0 +1,177 @@
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::FONT_6X10},
+    prelude::{DrawTarget, Point},
+    text::{Alignment, Text},
+};
+
+use crate::{
+    consts,
+    input::{Button, Input},
+    palette::Theme,
+};
+
+const LINE_HEIGHT: i32 = 14;
+// FONT_6X10 is 6 px wide
+const CHAR_WIDTH: i32 = 6;
+
+/// List of labeled entries, Up/Down move the cursor (wrapping around at the ends)
+/// and A picks the entry under it. `A` is whatever the scene wants back for an entry.
+#[derive(Debug)]
+pub struct Menu<A: Copy, const N: usize> {
+    entries: heapless::Vec<(&'static str, A), N>,
+    cursor: usize,
+}
+
+impl<A: Copy, const N: usize> Default for Menu<A, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Copy, const N: usize> Menu<A, N> {
+    pub fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    /// Adds an entry at the bottom, gives it back when the menu is full
+    pub fn push(&mut self, label: &'static str, action: A) -> Result<(), (&'static str, A)> {
+        self.entries.push((label, action))
+    }
+
+    /// Call once per update, returns the action of the entry picked with A
+    pub fn update(&mut self, input: &Input) -> Option<A> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        // before moving, on the device one button is both A and Up
+        if input.is_just_pressed(Button::A) {
+            return self.selected();
+        }
+        if input.is_just_pressed(Button::Up) {
+            self.move_cursor(-1);
+        }
+        if input.is_just_pressed(Button::Down) {
+            self.move_cursor(1);
+        }
+        None
+    }
+
+    fn move_cursor(&mut self, by: isize) {
+        let len = self.entries.len() as isize;
+        self.cursor = (self.cursor as isize + by).rem_euclid(len) as usize;
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Action of the entry under the cursor, `None` when there are no entries
+    pub fn selected(&self) -> Option<A> {
+        self.entries.get(self.cursor).map(|&(_, action)| action)
+    }
+
+    /// One entry per line, centered on `top_center`, the first line's baseline
+    pub fn draw<D>(&self, target: &mut D, top_center: Point, theme: &Theme) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        let style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        let accent_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.accent)
+            .build();
+
+        for (i, (label, _)) in self.entries.iter().enumerate() {
+            let position = top_center + Point::new(0, i as i32 * LINE_HEIGHT);
+            if i != self.cursor {
+                Text::with_alignment(label, position, style, Alignment::Center).draw(target)?;
+                continue;
+            }
+
+            Text::with_alignment(label, position, accent_style, Alignment::Center).draw(target)?;
+            let marker_x = position.x - label.len() as i32 * CHAR_WIDTH / 2 - 2 * CHAR_WIDTH;
+            Text::new(">", Point::new(marker_x, position.y), accent_style).draw(target)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu() -> Menu<char, 3> {
+        let mut menu = Menu::new();
+        for (label, action) in [("One", '1'), ("Two", '2'), ("Three", '3')] {
+            menu.push(label, action).unwrap();
+        }
+        menu
+    }
+
+    // Presses `button` for one update, and lets go of it again
+    fn press(menu: &mut Menu<char, 3>, input: &mut Input, button: Button) -> Option<char> {
+        input.update_button_level(button, true);
+        let picked = menu.update(input);
+        input.consume_edges();
+        input.update_button_level(button, false);
+        input.tick(16);
+        picked
+    }
+
+    #[test]
+    fn cursor_wraps_around_both_ends() {
+        let mut menu = menu();
+        let mut input = Input::new();
+        assert_eq!(press(&mut menu, &mut input, Button::Up), None);
+        assert_eq!(menu.cursor(), 2);
+        press(&mut menu, &mut input, Button::Down);
+        assert_eq!(menu.cursor(), 0);
+        press(&mut menu, &mut input, Button::Down);
+        assert_eq!(menu.selected(), Some('2'));
+    }
+
+    #[test]
+    fn a_picks_the_entry_under_the_cursor() {
+        let mut menu = menu();
+        let mut input = Input::new();
+        press(&mut menu, &mut input, Button::Down);
+        press(&mut menu, &mut input, Button::Down);
+        input.update_button_level(Button::A, true);
+        assert_eq!(menu.update(&input), Some('3'));
+        input.consume_edges();
+        // held down over the next frame, it doesn't pick again
+        input.tick(16);
+        input.update_button_level(Button::A, true);
+        assert_eq!(menu.update(&input), None);
+    }
+
+    #[test]
+    fn a_pressed_with_up_picks_without_moving() {
+        let mut menu = menu();
+        let mut input = Input::new();
+        input.update_button_level(Button::A, true);
+        input.update_button_level(Button::Up, true);
+        assert_eq!(menu.update(&input), Some('1'));
+        assert_eq!(menu.cursor(), 0);
+    }
+
+    #[test]
+    fn full_and_empty_menus() {
+        let mut menu = menu();
+        assert_eq!(menu.push("Four", '4'), Err(("Four", '4')));
+
+        let mut empty = Menu::<char, 3>::new();
+        let mut input = Input::new();
+        assert_eq!(press(&mut empty, &mut input, Button::Down), None);
+        assert_eq!(press(&mut empty, &mut input, Button::A), None);
+        assert_eq!(empty.selected(), None);
+    }
+}
@@ -1,15 +1,30 @@
-use crate::buzzer::BuzzerTrait;
+use crate::buzzer::{BuzzerTrait, Melody, MelodyPlayer};
 
 pub struct Output<'a> {
     buzzer: &'a dyn BuzzerTrait,
+    melody_player: &'a mut MelodyPlayer,
 }
 
 impl<'a> Output<'a> {
-    pub fn new(buzzer: &'a dyn BuzzerTrait) -> Self {
-        Self { buzzer }
+    pub fn new(buzzer: &'a dyn BuzzerTrait, melody_player: &'a mut MelodyPlayer) -> Self {
+        Self { buzzer, melody_player }
     }
 
     pub fn play_tone(&self, frequency_hz: u32, duration_ms: u32) {
         self.buzzer.beep(frequency_hz, duration_ms);
     }
+
+    /// Queue a melody, replacing the one that's playing
+    pub fn play_melody(&mut self, melody: Melody) {
+        self.melody_player.play(melody, self.buzzer);
+    }
+
+    /// Cut the melody short, e.g. when the scene that started it is left
+    pub fn stop_melody(&mut self) {
+        self.melody_player.stop(self.buzzer);
+    }
+
+    pub fn is_melody_playing(&self) -> bool {
+        self.melody_player.is_playing()
+    }
 }
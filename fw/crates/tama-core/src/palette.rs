@@ -0,0 +1,136 @@
+//! Semantic colors the scenes draw with, so they don't name `RgbColor` constants of
+//! `consts::ColorType` themselves. Retargeting the color type (a mono panel, Rgb888) or
+//! restyling the UI only means changing these.
+
+use embedded_graphics::prelude::RgbColor;
+
+use crate::consts::ColorType;
+
+/// One set of the semantic colors, the active one is picked in the settings and
+/// handed to `Scene::draw`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// What scenes clear the screen to
+    pub background: ColorType,
+    /// Text, outlines and anything else drawn on the background
+    pub foreground: ColorType,
+    /// Highlights, like the entry under a menu's cursor
+    pub accent: ColorType,
+    /// Errors and things that need attention (low battery...)
+    pub warning: ColorType,
+    /// Something that isn't an error yet but worth a look (sensor events...)
+    pub caution: ColorType,
+    /// Working as it should
+    pub success: ColorType,
+}
+
+impl Theme {
+    pub const LIGHT: Theme = Theme {
+        background: ColorType::WHITE,
+        foreground: ColorType::BLACK,
+        accent: ColorType::BLUE,
+        warning: ColorType::RED,
+        caution: ColorType::YELLOW,
+        success: ColorType::GREEN,
+    };
+
+    pub const DARK: Theme = Theme {
+        background: ColorType::BLACK,
+        foreground: ColorType::WHITE,
+        accent: ColorType::CYAN,
+        warning: ColorType::RED,
+        caution: ColorType::YELLOW,
+        success: ColorType::GREEN,
+    };
+
+    /// Only full-intensity colors on black, for bright sunlight and weak eyes
+    pub const HIGH_CONTRAST: Theme = Theme {
+        background: ColorType::BLACK,
+        foreground: ColorType::WHITE,
+        accent: ColorType::YELLOW,
+        warning: ColorType::MAGENTA,
+        caution: ColorType::YELLOW,
+        success: ColorType::GREEN,
+    };
+}
+
+/// The themes to choose from, this is what the settings keep
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThemeId {
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl ThemeId {
+    /// In the order the settings screen steps through them
+    pub const ALL: [ThemeId; 3] = [ThemeId::Light, ThemeId::Dark, ThemeId::HighContrast];
+
+    pub fn theme(self) -> &'static Theme {
+        match self {
+            ThemeId::Light => &Theme::LIGHT,
+            ThemeId::Dark => &Theme::DARK,
+            ThemeId::HighContrast => &Theme::HIGH_CONTRAST,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeId::Light => "light",
+            ThemeId::Dark => "dark",
+            ThemeId::HighContrast => "high contrast",
+        }
+    }
+
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            ThemeId::Light => 0,
+            ThemeId::Dark => 1,
+            ThemeId::HighContrast => 2,
+        }
+    }
+
+    /// Unknown values fall back to the default theme
+    pub(crate) fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => ThemeId::Dark,
+            2 => ThemeId::HighContrast,
+            _ => ThemeId::Light,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        settings::Settings,
+        test_support::{SharedSettingsStore, count_pixels, engine_with_store, render_engine},
+    };
+
+    #[test]
+    fn every_theme_survives_a_byte_round_trip() {
+        for id in ThemeId::ALL {
+            assert_eq!(ThemeId::from_byte(id.to_byte()), id);
+        }
+        assert_eq!(ThemeId::from_byte(0xff), ThemeId::default());
+    }
+
+    #[test]
+    fn switching_themes_changes_the_colors() {
+        assert_eq!(ThemeId::Light.theme().background, ColorType::WHITE);
+        assert_eq!(ThemeId::Dark.theme().background, ColorType::BLACK);
+        assert_ne!(ThemeId::Dark.theme().accent, ThemeId::HighContrast.theme().accent);
+    }
+
+    #[test]
+    fn the_engine_draws_with_the_saved_theme() {
+        for id in ThemeId::ALL {
+            let saved = Settings { theme: id, ..Settings::default() };
+            let (mut engine, _buzzer) = engine_with_store(SharedSettingsStore::with(saved));
+            let background = count_pixels(&render_engine(&mut engine), id.theme().background);
+            assert!(background > 64 * 64 / 2, "{} theme: {background} background pixels", id.name());
+        }
+    }
+}
@@ -0,0 +1,452 @@
+// Virtual pet state, all timing is driven by `dt_ms` so it doesn't depend on the frame rate
+
+pub const STAT_MAX: u8 = 100;
+
+// Pet age at which each stage is reached
+const BABY_AGE_MS: u64 = 60 * 1000; // 1 min in the egg
+const CHILD_AGE_MS: u64 = 30 * 60 * 1000; // 30 min
+const ADULT_AGE_MS: u64 = 4 * 60 * 60 * 1000; // 4 h
+
+// One stat point is lost every interval, twice as slow while sleeping
+const HUNGER_DECAY_INTERVAL_MS: u32 = 36 * 1000; // 100 -> 0 in 1 h
+const HAPPINESS_DECAY_INTERVAL_MS: u32 = 54 * 1000; // 100 -> 0 in 1.5 h
+const ENERGY_DECAY_INTERVAL_MS: u32 = 72 * 1000; // 100 -> 0 in 2 h awake
+const SLEEP_DECAY_FACTOR: u32 = 2;
+// Energy is regained while sleeping, one point per interval
+const ENERGY_RECOVER_INTERVAL_MS: u32 = 12 * 1000; // 0 -> 100 in 20 min
+
+// Care actions
+const FEED_AMOUNT: u8 = 25;
+const PLAY_HAPPINESS: u8 = 20;
+const PLAY_ENERGY_COST: u8 = 10;
+const PLAY_HUNGER_COST: u8 = 5;
+
+// Time spent starving or miserable before the pet gets sick
+const SICK_NEGLECT_MS: u32 = 10 * 60 * 1000;
+// Both stats have to get back above this for a sick pet to recover
+const RECOVER_THRESHOLD: u8 = 50;
+
+// Time powered off the pet catches up on is simulated in steps this long, so it can
+// fall asleep and wake up along the way
+const CATCH_UP_STEP_MS: u32 = 60 * 1000;
+// Longer than this powered off counts as this long
+const MAX_CATCH_UP_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifeStage {
+    Egg,
+    Baby,
+    Child,
+    Adult,
+}
+
+impl LifeStage {
+    fn for_age(age_ms: u64) -> Self {
+        if age_ms >= ADULT_AGE_MS {
+            LifeStage::Adult
+        } else if age_ms >= CHILD_AGE_MS {
+            LifeStage::Child
+        } else if age_ms >= BABY_AGE_MS {
+            LifeStage::Baby
+        } else {
+            LifeStage::Egg
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Awake,
+    Sleeping,
+    Sick,
+}
+
+/// How fast the stats drop, one point per interval while awake
+#[derive(Debug, Clone, Copy)]
+pub struct DecayRates {
+    pub hunger_interval_ms: u32,
+    pub happiness_interval_ms: u32,
+    pub energy_interval_ms: u32,
+}
+
+impl Default for DecayRates {
+    fn default() -> Self {
+        Self {
+            hunger_interval_ms: HUNGER_DECAY_INTERVAL_MS,
+            happiness_interval_ms: HAPPINESS_DECAY_INTERVAL_MS,
+            energy_interval_ms: ENERGY_DECAY_INTERVAL_MS,
+        }
+    }
+}
+
+/// The part of a pet that's kept across reboots, the decay timers restart from zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PetStats {
+    pub hunger: u8,
+    pub happiness: u8,
+    pub energy: u8,
+    pub condition: Condition,
+    pub age_ms: u64,
+    /// time spent with hunger or happiness at 0, kept so a reboot doesn't forgive it
+    pub neglect_ms: u32,
+}
+
+impl Default for PetStats {
+    fn default() -> Self {
+        Pet::new().stats()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pet {
+    stage: LifeStage,
+    condition: Condition,
+    /// 100 is full, 0 is starving
+    hunger: u8,
+    happiness: u8,
+    energy: u8,
+    age_ms: u64,
+    /// time spent with hunger or happiness at 0, slowly forgiven when cared for
+    neglect_ms: u32,
+    hunger_timer_ms: u32,
+    happiness_timer_ms: u32,
+    energy_timer_ms: u32,
+    rates: DecayRates,
+}
+
+impl Default for Pet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pet {
+    pub fn new() -> Self {
+        Self::with_rates(DecayRates::default())
+    }
+
+    pub fn with_rates(rates: DecayRates) -> Self {
+        Self {
+            stage: LifeStage::Egg,
+            condition: Condition::Awake,
+            hunger: STAT_MAX,
+            happiness: STAT_MAX,
+            energy: STAT_MAX,
+            age_ms: 0,
+            neglect_ms: 0,
+            hunger_timer_ms: 0,
+            happiness_timer_ms: 0,
+            energy_timer_ms: 0,
+            rates,
+        }
+    }
+
+    pub fn from_stats(stats: PetStats) -> Self {
+        let stage = LifeStage::for_age(stats.age_ms);
+        Self {
+            stage,
+            // an egg can't be asleep or sick
+            condition: if stage == LifeStage::Egg { Condition::Awake } else { stats.condition },
+            hunger: stats.hunger.min(STAT_MAX),
+            happiness: stats.happiness.min(STAT_MAX),
+            energy: stats.energy.min(STAT_MAX),
+            age_ms: stats.age_ms,
+            neglect_ms: stats.neglect_ms,
+            ..Self::new()
+        }
+    }
+
+    /// Live through `elapsed_ms` the device was off for (capped at a week), with the lights on
+    pub fn catch_up(&mut self, elapsed_ms: u64) {
+        let mut remaining_ms = elapsed_ms.min(MAX_CATCH_UP_MS);
+        while remaining_ms > 0 {
+            let step_ms = remaining_ms.min(CATCH_UP_STEP_MS as u64) as u32;
+            self.update(step_ms, false);
+            remaining_ms -= step_ms as u64;
+        }
+    }
+
+    pub fn stats(&self) -> PetStats {
+        PetStats {
+            hunger: self.hunger,
+            happiness: self.happiness,
+            energy: self.energy,
+            condition: self.condition,
+            age_ms: self.age_ms,
+            neglect_ms: self.neglect_ms,
+        }
+    }
+
+    /// Advance the pet by `dt_ms`. A hatched pet falls asleep when `lights_off` is set
+    /// or it runs out of energy, and wakes up rested once the lights are back on.
+    pub fn update(&mut self, dt_ms: u32, lights_off: bool) {
+        self.age_ms += dt_ms as u64;
+
+        // sick pets don't grow until they get better
+        if self.condition != Condition::Sick {
+            let stage = LifeStage::for_age(self.age_ms);
+            if stage != self.stage {
+                log::info!("Pet evolved: {:?} -> {:?}", self.stage, stage);
+                self.stage = stage;
+            }
+        }
+
+        if self.stage == LifeStage::Egg {
+            return;
+        }
+
+        match self.condition {
+            Condition::Awake if lights_off || self.energy == 0 => self.condition = Condition::Sleeping,
+            Condition::Sleeping if !lights_off && self.energy == STAT_MAX => self.condition = Condition::Awake,
+            _ => (),
+        }
+
+        let sleeping = self.condition == Condition::Sleeping;
+        let decay_factor = if sleeping { SLEEP_DECAY_FACTOR } else { 1 };
+        let hunger_lost = elapsed_points(
+            &mut self.hunger_timer_ms,
+            dt_ms,
+            self.rates.hunger_interval_ms * decay_factor,
+        );
+        self.hunger = self.hunger.saturating_sub(hunger_lost);
+        let happiness_lost = elapsed_points(
+            &mut self.happiness_timer_ms,
+            dt_ms,
+            self.rates.happiness_interval_ms * decay_factor,
+        );
+        self.happiness = self.happiness.saturating_sub(happiness_lost);
+        if sleeping {
+            let regained = elapsed_points(&mut self.energy_timer_ms, dt_ms, ENERGY_RECOVER_INTERVAL_MS);
+            self.energy = add_clamped(self.energy, regained);
+        } else {
+            let lost = elapsed_points(&mut self.energy_timer_ms, dt_ms, self.rates.energy_interval_ms);
+            self.energy = self.energy.saturating_sub(lost);
+        }
+
+        if self.hunger == 0 || self.happiness == 0 {
+            self.neglect_ms = self.neglect_ms.saturating_add(dt_ms);
+        } else {
+            self.neglect_ms = self.neglect_ms.saturating_sub(dt_ms);
+        }
+
+        match self.condition {
+            Condition::Sick if self.hunger >= RECOVER_THRESHOLD && self.happiness >= RECOVER_THRESHOLD => {
+                log::info!("Pet recovered");
+                self.condition = Condition::Awake;
+                self.neglect_ms = 0;
+            }
+            Condition::Sick => (),
+            _ if self.neglect_ms >= SICK_NEGLECT_MS => {
+                log::info!("Pet got sick from neglect");
+                self.condition = Condition::Sick;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn feed(&mut self) {
+        if !self.can_be_cared_for() {
+            return;
+        }
+        self.hunger = add_clamped(self.hunger, FEED_AMOUNT);
+    }
+
+    /// Playing makes the pet happier, but tires it out and makes it hungry
+    pub fn play(&mut self) {
+        if !self.can_be_cared_for() || self.condition == Condition::Sleeping {
+            return;
+        }
+        self.happiness = add_clamped(self.happiness, PLAY_HAPPINESS);
+        self.energy = self.energy.saturating_sub(PLAY_ENERGY_COST);
+        self.hunger = self.hunger.saturating_sub(PLAY_HUNGER_COST);
+    }
+
+    /// Put the pet to bed, it wakes up on its own once rested
+    pub fn sleep(&mut self) {
+        if self.can_be_cared_for() && self.condition == Condition::Awake {
+            self.condition = Condition::Sleeping;
+        }
+    }
+
+    fn can_be_cared_for(&self) -> bool {
+        self.stage != LifeStage::Egg
+    }
+
+    pub fn stage(&self) -> LifeStage {
+        self.stage
+    }
+
+    pub fn condition(&self) -> Condition {
+        self.condition
+    }
+
+    pub fn hunger(&self) -> u8 {
+        self.hunger
+    }
+
+    pub fn happiness(&self) -> u8 {
+        self.happiness
+    }
+
+    pub fn energy(&self) -> u8 {
+        self.energy
+    }
+
+    pub fn age_ms(&self) -> u64 {
+        self.age_ms
+    }
+}
+
+/// Stored form of a condition in the settings blob
+pub(crate) fn condition_to_byte(condition: Condition) -> u8 {
+    match condition {
+        Condition::Awake => 0,
+        Condition::Sleeping => 1,
+        Condition::Sick => 2,
+    }
+}
+
+/// Unknown values read as awake
+pub(crate) fn condition_from_byte(byte: u8) -> Condition {
+    match byte {
+        1 => Condition::Sleeping,
+        2 => Condition::Sick,
+        _ => Condition::Awake,
+    }
+}
+
+fn add_clamped(value: u8, amount: u8) -> u8 {
+    value.saturating_add(amount).min(STAT_MAX)
+}
+
+// Number of full `interval_ms` accumulated in `timer_ms`, the remainder is kept
+fn elapsed_points(timer_ms: &mut u32, dt_ms: u32, interval_ms: u32) -> u8 {
+    *timer_ms += dt_ms;
+    let points = *timer_ms / interval_ms;
+    *timer_ms %= interval_ms;
+    points.min(STAT_MAX as u32) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grown_pet() -> Pet {
+        let mut pet = Pet::new();
+        pet.catch_up(CHILD_AGE_MS + 12_345);
+        pet.feed();
+        pet
+    }
+
+    #[test]
+    fn stats_round_trip() {
+        let mut pet = grown_pet();
+        pet.neglect_ms = 1234;
+        let restored = Pet::from_stats(pet.stats());
+        assert_eq!(restored.stats(), pet.stats());
+        assert_eq!(restored.stage(), LifeStage::Child);
+        assert_eq!(restored.neglect_ms, 1234);
+    }
+
+    #[test]
+    fn catching_up_ages_the_pet_up_to_a_week() {
+        let mut pet = Pet::new();
+        pet.catch_up(BABY_AGE_MS);
+        assert_eq!(pet.age_ms(), BABY_AGE_MS);
+        assert_eq!(pet.stage(), LifeStage::Baby);
+        assert!(pet.hunger() < STAT_MAX);
+
+        let mut pet = Pet::new();
+        pet.catch_up(u64::MAX);
+        assert_eq!(pet.age_ms(), MAX_CATCH_UP_MS);
+    }
+
+    // A hatched pet with the given stats, the rest as new
+    fn hatched(hunger: u8, happiness: u8, energy: u8, condition: Condition) -> Pet {
+        Pet::from_stats(PetStats { hunger, happiness, energy, condition, age_ms: BABY_AGE_MS, neglect_ms: 0 })
+    }
+
+    // Live a second at a time until `age_ms`, fed and played with whenever it wants
+    fn cared_for_until(pet: &mut Pet, age_ms: u64) {
+        while pet.age_ms() < age_ms {
+            pet.update(1000, false);
+            if pet.hunger() < 80 {
+                pet.feed();
+            }
+            if pet.happiness() < 80 {
+                pet.play();
+            }
+            assert_ne!(pet.condition(), Condition::Sick, "sick at {} ms", pet.age_ms());
+        }
+    }
+
+    #[test]
+    fn a_cared_for_pet_grows_up_on_time() {
+        let mut pet = Pet::new();
+        for (stage_age_ms, stage) in [
+            (BABY_AGE_MS, LifeStage::Baby),
+            (CHILD_AGE_MS, LifeStage::Child),
+            (ADULT_AGE_MS, LifeStage::Adult),
+        ] {
+            cared_for_until(&mut pet, stage_age_ms - 1000);
+            assert_ne!(pet.stage(), stage);
+            cared_for_until(&mut pet, stage_age_ms);
+            assert_eq!(pet.stage(), stage);
+        }
+    }
+
+    #[test]
+    fn neglect_makes_it_sick() {
+        let mut pet = hatched(0, STAT_MAX, STAT_MAX, Condition::Awake);
+        for _ in 0..SICK_NEGLECT_MS / 1000 - 1 {
+            pet.update(1000, false);
+        }
+        assert_eq!(pet.condition(), Condition::Awake);
+        pet.update(1000, false);
+        assert_eq!(pet.condition(), Condition::Sick);
+    }
+
+    #[test]
+    fn a_sick_pet_stops_growing_until_it_recovers() {
+        let mut pet = Pet::from_stats(PetStats {
+            hunger: 0,
+            happiness: STAT_MAX,
+            energy: STAT_MAX,
+            condition: Condition::Sick,
+            age_ms: CHILD_AGE_MS - 1000,
+            neglect_ms: 0,
+        });
+        pet.update(2000, false);
+        assert_eq!(pet.stage(), LifeStage::Baby);
+        assert_eq!(pet.condition(), Condition::Sick);
+
+        // one meal isn't enough to get over it
+        pet.feed();
+        pet.update(10, false);
+        assert_eq!(pet.condition(), Condition::Sick);
+        pet.feed();
+        pet.update(10, false);
+        assert!(pet.hunger() >= RECOVER_THRESHOLD);
+        assert_eq!(pet.condition(), Condition::Awake);
+        pet.update(10, false);
+        assert_eq!(pet.stage(), LifeStage::Child);
+    }
+
+    #[test]
+    fn sleeps_with_the_lights_off_and_wakes_up_rested() {
+        let mut pet = hatched(STAT_MAX, STAT_MAX, 60, Condition::Awake);
+        pet.update(1000, true);
+        assert_eq!(pet.condition(), Condition::Sleeping);
+
+        // dark for ten minutes, then it sleeps on with the lights on until it's rested
+        let mut slept_ms = 0;
+        while pet.condition() == Condition::Sleeping {
+            pet.update(1000, slept_ms < 10 * 60 * 1000);
+            slept_ms += 1000;
+            assert!(slept_ms < 60 * 60 * 1000, "never woke up");
+        }
+        assert_eq!(pet.energy(), STAT_MAX);
+        assert!(slept_ms >= 10 * 60 * 1000);
+        assert!(slept_ms >= 40 * ENERGY_RECOVER_INTERVAL_MS);
+    }
+}
@@ -0,0 +1,102 @@
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_8X13}},
+    prelude::{DrawTarget, Point},
+    text::Text,
+};
+use heapless::String;
+
+use crate::{
+    consts,
+    engine::Context,
+    gfx::RenderError,
+    input::Button,
+    palette::Theme,
+    scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene},
+    uptime,
+};
+
+const ROWS_TOP: i32 = 50;
+const ROW_HEIGHT: i32 = 20;
+const MS_PER_HOUR: u64 = 60 * 60 * 1000;
+
+/// Firmware version and how long the device has been running, B goes back to the menu
+pub struct AboutScene {
+    // whole seconds, so the screen is only redrawn when the clock ticks over
+    uptime_s: u64,
+    lifetime_h: u64,
+    sessions: u32,
+    changed: bool,
+}
+
+impl AboutScene {
+    pub fn new() -> Self {
+        Self {
+            uptime_s: 0,
+            lifetime_h: 0,
+            sessions: 0,
+            changed: true,
+        }
+    }
+}
+
+impl Scene for AboutScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+
+        let uptime_s = ctx.uptime.session_ms() / 1000;
+        let lifetime_h = ctx.uptime.lifetime_ms() / MS_PER_HOUR;
+        self.changed = uptime_s != self.uptime_s || lifetime_h != self.lifetime_h || ctx.settings.sessions != self.sessions;
+        self.uptime_s = uptime_s;
+        self.lifetime_h = lifetime_h;
+        self.sessions = ctx.settings.sessions;
+
+        UpdateResult::None
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.changed
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        target.clear(theme.background)?;
+
+        let title_style = MonoTextStyleBuilder::new()
+            .font(&FONT_8X13)
+            .text_color(theme.foreground)
+            .build();
+        Text::new("About", Point::new(20, 20), title_style).draw(target)?;
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        let uptime = uptime::format_duration(self.uptime_s * 1000);
+        let mut lifetime = String::<24>::new();
+        let _ = write!(lifetime, "{} h", self.lifetime_h);
+        let mut sessions = String::<12>::new();
+        let _ = write!(sessions, "{}", self.sessions);
+        let rows: [(&str, &str); 4] = [
+            ("Version", env!("CARGO_PKG_VERSION")),
+            ("Uptime", &uptime),
+            ("Lifetime", &lifetime),
+            ("Boots", &sessions),
+        ];
+        for (i, (label, value)) in rows.iter().enumerate() {
+            let top = ROWS_TOP + i as i32 * ROW_HEIGHT;
+            Text::new(label, Point::new(20, top), text_style).draw(target)?;
+            Text::new(value, Point::new(100, top), text_style).draw(target)?;
+        }
+
+        Text::new("B: back", Point::new(20, consts::HEIGHT as i32 - 14), text_style).draw(target)?;
+
+        Ok(())
+    }
+}
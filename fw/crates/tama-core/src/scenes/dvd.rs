@@ -1,22 +1,22 @@
 use embedded_graphics::{
     Drawable,
-    prelude::{DrawTarget, Point, Primitive, RgbColor},
-    primitives::{Circle, PrimitiveStyle},
+    prelude::{DrawTarget, Point},
 };
 
-use crate::{assets, consts, engine::Context, gfx::Sprite, scenes::{Scene, UpdateResult}};
+use crate::{assets::images::{self, ImageId}, consts, engine::Context, gfx::{RenderError, Sprite}, input::Button, palette::Theme, scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene}};
 
-/// Very simple test scene
+/// Very simple test scene, doubles as the screensaver
 pub struct DvdScene {
     x: i32,
     y: i32,
     vel_x: i32,
     vel_y: i32,
     radius: u32,
+    /// started by the engine on top of whatever was running, any button goes back to it
+    is_screensaver: bool,
 }
 
 impl DvdScene {
-    #[allow(dead_code)]
     pub fn new() -> Self {
         Self {
             x: consts::WIDTH as i32 / 2,
@@ -24,12 +24,32 @@ impl DvdScene {
             vel_x: 1,
             vel_y: -1,
             radius: 64,
+            is_screensaver: false,
         }
     }
+
+    pub fn screensaver() -> Self {
+        Self {
+            is_screensaver: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn is_screensaver(&self) -> bool {
+        self.is_screensaver
+    }
 }
 
 impl Scene for DvdScene {
-    fn update(&mut self, _ctx: &mut Context) -> UpdateResult {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if self.is_screensaver {
+            if Button::ALL.iter().any(|&button| ctx.input.is_just_pressed(button)) {
+                return UpdateResult::PopScene;
+            }
+        } else if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+
         self.x += self.vel_x;
         if self.x <= self.radius as i32 || self.x >= (consts::WIDTH - self.radius) as i32 {
             self.vel_x = -self.vel_x;
@@ -43,18 +63,19 @@ impl Scene for DvdScene {
         UpdateResult::None
     }
 
-    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
     where
         D: DrawTarget<Color = consts::ColorType>,
     {
-        target.clear(consts::ColorType::WHITE)?;
+        target.clear(theme.background)?;
 
-        // let fill = PrimitiveStyle::with_fill(consts::ColorType::RED);
-        // Circle::with_center(Point::new(self.x, self.y), self.radius * 2)
-        //     .into_styled(fill)
-        //     .draw(target)?;
-
-        Sprite::new(&*assets::images::PAPAJ, Point::new(self.x - self.radius as i32, self.y - self.radius as i32)).draw(target)?;
+        // face the way it's moving
+        let sprite = Sprite::new(images::image(ImageId::Papaj), Point::new(self.x - self.radius as i32, self.y - self.radius as i32));
+        if self.vel_x < 0 {
+            sprite.flip_horizontal().draw(target)?;
+        } else {
+            sprite.draw(target)?;
+        }
 
         Ok(())
     }
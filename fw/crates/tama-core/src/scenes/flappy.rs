@@ -1,31 +1,94 @@
 use core::ops::Range;
 
+use core::fmt::Write as _;
+
 use embedded_graphics::{
-    Drawable as _, image::Image, prelude::{DrawTarget, Point, Primitive, RgbColor, Size}, primitives::{Circle, PrimitiveStyle, Rectangle}
+    Drawable as _, image::Image, mono_font::{MonoTextStyleBuilder, ascii::FONT_10X20}, prelude::{DrawTarget, Point, Primitive, RgbColor, Size}, primitives::{Circle, PrimitiveStyle, Rectangle}, text::{Alignment, Text}
 };
-use heapless::Deque;
-use rand::Rng;
+use heapless::{Deque, String};
+use rand::{Rng, SeedableRng, rngs::SmallRng};
 
 use crate::{
-    assets, consts, engine::Context, gfx::Sprite, input::Button, scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene}
+    assets::images::{self, ImageId}, consts, engine::Context, gfx::{RenderError, Sprite}, input::Button, palette::Theme, scenes::{Scene, SceneWrapper, UpdateResult, gameover::GameOverScene, pause::PauseScene}
 };
 
-const SCROLL_SPEED: i32 = 1;
 const SPACING: i32 = 100;
 const PIPE_WIDTH: u32 = 32;
-const GAP_HEIGHT_RANGE: Range<i32> = 64..128;
 const GAP_CENTER_RANGE: Range<i32> = -64..64;
 
 const PLAYER_RADIUS: u32 = 8;
 const PLAYER_GRAVITY: f32 = 0.7;
 const PLAYER_JUMP_VELOCITY: f32 = 7.0;
 
+// speeds and gravity are per step, so the game plays the same at any frame rate
+const STEP_MS: u32 = 32;
+
+// mixed into the date seed so daily layouts don't line up with plain `with_seed` runs
+const DAILY_SEED_SALT: u64 = 0x7a3a_da11_c4a1_1e96;
+
+/// How the game speeds up and the gaps narrow as the score goes up
+#[derive(Debug, Clone)]
+pub struct FlappyDifficulty {
+    /// pixels per step at the start
+    pub start_scroll_speed: i32,
+    pub max_scroll_speed: i32,
+    /// pipes passed per +1 scroll speed
+    pub pipes_per_speed_up: u32,
+    /// gap heights of the first pipes
+    pub start_gap_height: Range<i32>,
+    /// both ends of the gap range shrink by this much per pipe passed...
+    pub gap_shrink_per_pipe: i32,
+    /// ...but no gap gets smaller than this
+    pub min_gap_height: i32,
+}
+
+impl Default for FlappyDifficulty {
+    fn default() -> Self {
+        Self {
+            start_scroll_speed: 1,
+            max_scroll_speed: 3,
+            pipes_per_speed_up: 10,
+            start_gap_height: 64..128,
+            gap_shrink_per_pipe: 2,
+            min_gap_height: 40,
+        }
+    }
+}
+
+impl FlappyDifficulty {
+    pub fn scroll_speed(&self, score: u32) -> i32 {
+        let speed_ups = (score / self.pipes_per_speed_up.max(1)) as i32;
+        (self.start_scroll_speed + speed_ups).min(self.max_scroll_speed)
+    }
+
+    /// Range the next gap height is picked from, never empty
+    pub fn gap_height_range(&self, score: u32) -> Range<i32> {
+        let shrink = self.gap_shrink_per_pipe.saturating_mul(score as i32);
+        let low = (self.start_gap_height.start - shrink).max(self.min_gap_height);
+        let high = (self.start_gap_height.end - shrink).max(low + 1);
+        low..high
+    }
+
+    // Going `n` times faster is like running time `n` times faster, jump speed scales
+    // by `n` and gravity by `n²`, so the jump arcs cover the same distance between pipes
+    fn physics_scale(&self, score: u32) -> f32 {
+        self.scroll_speed(score) as f32 / self.start_scroll_speed.max(1) as f32
+    }
+}
+
 pub struct FlappyScene {
     pipes: Deque<Pipe, 8>,
 
     player_x: i32,
     player_y: f32,
     player_y_speed: f32,
+
+    /// pipes passed this run
+    score: u32,
+    difficulty: FlappyDifficulty,
+
+    /// Scene-local rng for seeded runs, `None` uses the engine rng
+    rng: Option<SmallRng>,
 }
 
 impl FlappyScene {
@@ -35,25 +98,71 @@ impl FlappyScene {
             player_x: 32,
             player_y: (consts::HEIGHT / 2) as f32,
             player_y_speed: 0.0,
+            score: 0,
+            difficulty: FlappyDifficulty::default(),
+            rng: None,
         }
     }
+
+    /// Run with a fixed pipe layout, independent of the engine rng state
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: Some(SmallRng::seed_from_u64(seed)),
+            ..Self::new()
+        }
+    }
+
+    /// Daily challenge run. `date_seed` can be any value the platform keeps stable
+    /// for a whole day (RTC date, day counter...), so every device gets the same
+    /// layout on the same day and scores can be compared.
+    pub fn daily(date_seed: u32) -> Self {
+        Self::with_seed(DAILY_SEED_SALT ^ date_seed as u64)
+    }
+
+    // Records a new high score and hands the run over to the game over screen,
+    // which plays the jingle. Returned right away, so a run ends only once.
+    fn game_over(&self, ctx: &mut Context) -> UpdateResult {
+        let high_score = ctx.settings.flappy_high_score;
+        if self.score > high_score {
+            ctx.settings.flappy_high_score = self.score;
+        }
+        UpdateResult::ChangeScene(SceneWrapper::from(GameOverScene::new(self.score, high_score)))
+    }
+
+    /// (center_y, gap_height) of the pipes on screen, oldest first
+    #[cfg(test)]
+    pub(crate) fn gaps(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.pipes.iter().map(|pipe| (pipe.center_y, pipe.gap_height))
+    }
 }
 
 impl Scene for FlappyScene {
     fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::PushScene(SceneWrapper::from(PauseScene::new()));
+        }
+
         // Pipes
         if self.pipes.is_empty() || self.pipes.back().unwrap().x < consts::WIDTH as i32 - SPACING {
+            let rng = self.rng.as_mut().unwrap_or(&mut ctx.rng);
             self.pipes
                 .push_back(Pipe {
                     x: consts::WIDTH as i32,
-                    center_y: consts::HEIGHT as i32 / 2 + ctx.rng.random_range(GAP_CENTER_RANGE),
-                    gap_height: ctx.rng.random_range(GAP_HEIGHT_RANGE),
+                    center_y: consts::HEIGHT as i32 / 2 + rng.random_range(GAP_CENTER_RANGE),
+                    gap_height: rng.random_range(self.difficulty.gap_height_range(self.score)),
+                    passed: false,
                 })
                 .expect("queue capacity isn't big enough for the pipe parameters");
         }
 
+        let scroll_speed = self.difficulty.scroll_speed(self.score);
         for pipe in self.pipes.iter_mut() {
-            pipe.x -= SCROLL_SPEED;
+            pipe.x -= scroll_speed;
+            if !pipe.passed && pipe.is_behind(self.player_x) {
+                pipe.passed = true;
+                self.score += 1;
+                ctx.output.play_tone(880, 30);
+            }
         }
 
         if let Some(front) = self.pipes.front()
@@ -63,13 +172,14 @@ impl Scene for FlappyScene {
         }
 
         // player
-        if ctx.input.is_just_pressed(Button::Up) {
-            self.player_y_speed = -PLAYER_JUMP_VELOCITY;
+        let physics_scale = self.difficulty.physics_scale(self.score);
+        if ctx.input.is_just_pressed(Button::Up) || ctx.input.shake_detected() {
+            self.player_y_speed = -PLAYER_JUMP_VELOCITY * physics_scale;
             ctx.output.play_tone(40, 20);
         }
 
         self.player_y += self.player_y_speed;
-        self.player_y_speed += PLAYER_GRAVITY;
+        self.player_y_speed += PLAYER_GRAVITY * physics_scale * physics_scale;
 
         // collision
         let radius = PLAYER_RADIUS as i32;
@@ -78,8 +188,7 @@ impl Scene for FlappyScene {
             && self.player_y as i32 + radius <= consts::HEIGHT as i32;
 
         if !is_in_bounds {
-            ctx.output.play_tone(60, 500);
-            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+            return self.game_over(ctx);
         }
 
         for pipe in self.pipes.iter() {
@@ -91,45 +200,44 @@ impl Scene for FlappyScene {
             let has_x_overlap = self.player_x + radius > pipe.x
                 && self.player_x - radius < pipe.x + PIPE_WIDTH as i32;
             let y = self.player_y as i32;
-            let has_y_overlap = y - radius < pipe.center_y - pipe.gap_height / 2
-                || y + radius > pipe.center_y + pipe.gap_height / 2;
+            let has_y_overlap = y - radius < pipe.gap_top() || y + radius > pipe.gap_bottom();
 
             if has_x_overlap && has_y_overlap {
-                ctx.output.play_tone(58, 500);
-                return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+                return self.game_over(ctx);
             }
         }
 
         UpdateResult::None
     }
 
-    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    fn fixed_step_ms(&self) -> Option<u32> {
+        Some(STEP_MS)
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
     where
         D: DrawTarget<Color = consts::ColorType>,
     {
-        target.clear(consts::ColorType::WHITE)?;
+        target.clear(theme.background)?;
 
-        let black_fill = PrimitiveStyle::with_fill(consts::ColorType::BLACK);
+        let pipe_fill = PrimitiveStyle::with_fill(theme.foreground);
         let green_fill = PrimitiveStyle::with_fill(consts::ColorType::GREEN);
 
         for pipe in self.pipes.iter() {
             // top pipe
             Rectangle::new(
                 Point::new(pipe.x, 0),
-                Size::new(PIPE_WIDTH, (pipe.center_y - pipe.gap_height / 2) as u32),
+                Size::new(PIPE_WIDTH, pipe.gap_top() as u32),
             )
-            .into_styled(black_fill)
+            .into_styled(pipe_fill)
             .draw(target)?;
 
             // bottom pipe
             Rectangle::new(
-                Point::new(pipe.x, pipe.center_y + pipe.gap_height / 2),
-                Size::new(
-                    PIPE_WIDTH,
-                    (consts::HEIGHT as i32 - pipe.center_y - pipe.gap_height / 2) as u32,
-                ),
+                Point::new(pipe.x, pipe.gap_bottom()),
+                Size::new(PIPE_WIDTH, (consts::HEIGHT as i32 - pipe.gap_bottom()) as u32),
             )
-            .into_styled(black_fill)
+            .into_styled(pipe_fill)
             .draw(target)?;
 
             // player
@@ -140,9 +248,18 @@ impl Scene for FlappyScene {
             // .into_styled(green_fill)
             // .draw(target)?;
 
-            Sprite::new(&*assets::images::PAPAJ_SMOL, Point::new(self.player_x - PLAYER_RADIUS as i32, self.player_y as i32 - PLAYER_RADIUS as i32)).draw(target)?;
+            Sprite::new(images::image(ImageId::PapajSmol), Point::new(self.player_x - PLAYER_RADIUS as i32, self.player_y as i32 - PLAYER_RADIUS as i32)).draw(target)?;
         }
 
+        let mut score = String::<12>::new();
+        let _ = write!(score, "{}", self.score);
+        let score_style = MonoTextStyleBuilder::new()
+            .font(&FONT_10X20)
+            .text_color(theme.accent)
+            .build();
+        Text::with_alignment(&score, Point::new(consts::WIDTH as i32 / 2, 20), score_style, Alignment::Center)
+            .draw(target)?;
+
         Ok(())
     }
 }
@@ -152,4 +269,160 @@ struct Pipe {
     x: i32,
     center_y: i32,
     gap_height: i32,
+    /// already counted towards the score
+    passed: bool,
+}
+
+impl Pipe {
+    // The gap edges are kept on screen, so the pipe heights drawn from them can't go
+    // negative (and wrap to a huge u32) for gaps reaching past the top or bottom.
+    fn gap_top(&self) -> i32 {
+        (self.center_y - self.gap_height / 2).clamp(0, consts::HEIGHT as i32)
+    }
+
+    fn gap_bottom(&self) -> i32 {
+        (self.center_y + self.gap_height / 2).clamp(0, consts::HEIGHT as i32)
+    }
+
+    // the pipe's right edge is left of `player_x`
+    fn is_behind(&self, player_x: i32) -> bool {
+        self.x + (PIPE_WIDTH as i32) < player_x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::test_support::{ContextParts, render_scene};
+
+    fn pipe(x: i32) -> Pipe {
+        Pipe { x, center_y: consts::HEIGHT as i32 / 2, gap_height: 100, passed: false }
+    }
+
+    // flown off the top, the next update ends the run
+    fn crashing(score: u32) -> FlappyScene {
+        FlappyScene { player_y: -20.0, score, ..FlappyScene::with_seed(1) }
+    }
+
+    #[test]
+    fn passing_a_pipe_scores_and_beeps() {
+        let mut parts = ContextParts::new();
+        let mut scene = FlappyScene::with_seed(1);
+        // its right edge passes the player on the next step
+        let _ = scene.pipes.push_back(pipe(scene.player_x - PIPE_WIDTH as i32));
+        assert!(matches!(scene.update(&mut parts.context(STEP_MS)), UpdateResult::None));
+        assert_eq!(scene.score, 1);
+        assert_eq!(parts.buzzer.tones(), [(880, 30)]);
+
+        scene.update(&mut parts.context(STEP_MS));
+        assert_eq!(scene.score, 1, "a pipe counts once");
+    }
+
+    #[test]
+    fn a_better_run_sets_the_high_score() {
+        let mut parts = ContextParts::new();
+        parts.settings.flappy_high_score = 4;
+        let result = crashing(7).update(&mut parts.context(STEP_MS));
+        assert!(matches!(result, UpdateResult::ChangeScene(SceneWrapper::GameOverScene(_))));
+        assert_eq!(parts.settings.flappy_high_score, 7);
+    }
+
+    #[test]
+    fn a_worse_run_keeps_the_high_score() {
+        let mut parts = ContextParts::new();
+        parts.settings.flappy_high_score = 9;
+        crashing(3).update(&mut parts.context(STEP_MS));
+        assert_eq!(parts.settings.flappy_high_score, 9);
+    }
+
+    #[test]
+    fn gaps_past_the_screen_edges_are_clamped() {
+        let top = Pipe { center_y: 10, gap_height: 128, ..pipe(0) };
+        assert_eq!((top.gap_top(), top.gap_bottom()), (0, 74));
+        let bottom = Pipe { center_y: consts::HEIGHT as i32 - 10, gap_height: 128, ..pipe(100) };
+        assert_eq!((bottom.gap_top(), bottom.gap_bottom()), (consts::HEIGHT as i32 - 74, consts::HEIGHT as i32));
+
+        // every gap a run can spawn, from the first pipe's heights down to the smallest
+        let difficulty = FlappyDifficulty::default();
+        let heights = difficulty.gap_height_range(u32::MAX).start..difficulty.gap_height_range(0).end;
+        for offset in GAP_CENTER_RANGE {
+            for gap_height in heights.clone() {
+                let spawned = Pipe { center_y: consts::HEIGHT as i32 / 2 + offset, gap_height, ..pipe(0) };
+                let (gap_top, gap_bottom) = (spawned.gap_top(), spawned.gap_bottom());
+                assert!(
+                    0 <= gap_top && gap_top <= gap_bottom && gap_bottom <= consts::HEIGHT as i32,
+                    "gap {gap_top}..{gap_bottom} for center {} height {gap_height}",
+                    spawned.center_y,
+                );
+            }
+        }
+
+        // a zero height top pipe and bottom pipe, not ones wrapping around to 4 billion pixels
+        let mut scene = FlappyScene::new();
+        let _ = scene.pipes.push_back(top);
+        let _ = scene.pipes.push_back(bottom);
+        let _ = scene.pipes.push_back(Pipe { center_y: 100, gap_height: 64, ..pipe(40) });
+        let display = render_scene(&scene, &Theme::LIGHT);
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(Theme::LIGHT.background));
+        assert_eq!(display.get_pixel(Point::new(0, 63)), Some(Theme::LIGHT.background));
+        assert_eq!(display.get_pixel(Point::new(40, 0)), Some(Theme::LIGHT.foreground));
+        assert_eq!(display.get_pixel(Point::new(40, 63)), Some(Theme::LIGHT.foreground));
+    }
+
+    #[test]
+    fn difficulty_ramps_up_to_its_limits() {
+        let difficulty = FlappyDifficulty::default();
+        assert_eq!(difficulty.scroll_speed(0), 1);
+        assert_eq!(difficulty.scroll_speed(9), 1);
+        assert_eq!(difficulty.scroll_speed(10), 2);
+        assert_eq!(difficulty.scroll_speed(1000), 3);
+
+        assert_eq!(difficulty.gap_height_range(0), 64..128);
+        assert_eq!(difficulty.gap_height_range(5), 54..118);
+        assert_eq!(difficulty.gap_height_range(1000), 40..41);
+        assert_eq!(difficulty.physics_scale(20), 3.0);
+    }
+
+    #[test]
+    fn odd_difficulties_still_give_usable_ranges() {
+        let difficulty = FlappyDifficulty {
+            start_scroll_speed: 0,
+            pipes_per_speed_up: 0,
+            start_gap_height: 80..80,
+            ..FlappyDifficulty::default()
+        };
+        assert!(!difficulty.gap_height_range(0).is_empty());
+        assert!(!difficulty.gap_height_range(u32::MAX).is_empty());
+        assert_eq!(difficulty.scroll_speed(4), 3);
+        assert!(difficulty.physics_scale(4).is_finite());
+    }
+
+    // (center_y, gap_height) of every pipe the scene spawns in `steps` steps
+    fn layout(mut scene: FlappyScene, parts: &mut ContextParts, steps: u32) -> Vec<(i32, i32)> {
+        let mut pipes = Vec::new();
+        for _ in 0..steps {
+            scene.update(&mut parts.context(STEP_MS));
+            if let Some(pipe) = scene.pipes.back()
+                && pipe.x == consts::WIDTH as i32 - scene.difficulty.scroll_speed(scene.score)
+            {
+                pipes.push((pipe.center_y, pipe.gap_height));
+            }
+        }
+        pipes
+    }
+
+    #[test]
+    fn a_day_has_one_pipe_layout() {
+        const DAY: u32 = 20_000;
+        let mut parts = ContextParts::new();
+        let today = layout(FlappyScene::daily(DAY), &mut parts, 500);
+        assert!(today.len() >= 4, "{} pipes", today.len());
+        // the engine rng has moved on since, the daily run doesn't use it
+        assert_eq!(layout(FlappyScene::daily(DAY), &mut parts, 500), today);
+
+        assert_ne!(layout(FlappyScene::daily(DAY + 1), &mut parts, 500), today);
+        assert_ne!(layout(FlappyScene::with_seed(DAY as u64), &mut parts, 500), today);
+    }
 }
@@ -0,0 +1,103 @@
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_10X20}},
+    prelude::{DrawTarget, Point},
+    text::{Alignment, Text},
+};
+use heapless::String;
+
+use crate::{
+    buzzer::{Melody, melodies}, consts, engine::Context, gfx::RenderError, input::Button, palette::Theme, scenes::{Scene, SceneWrapper, UpdateResult, flappy::FlappyScene, menu::MenuScene}
+};
+
+// back to the menu when nothing is pressed for this long
+const IDLE_TIMEOUT_MS: u32 = 10_000;
+
+/// Shown after a flappy run ends
+pub struct GameOverScene {
+    score: u32,
+    /// best score before this run
+    high_score: u32,
+    elapsed_ms: u32,
+}
+
+impl GameOverScene {
+    pub fn new(score: u32, high_score: u32) -> Self {
+        Self {
+            score,
+            high_score,
+            elapsed_ms: 0,
+        }
+    }
+}
+
+impl Scene for GameOverScene {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        ctx.output.play_melody(Melody::from_notes(melodies::GAME_OVER));
+    }
+
+    // a retry shouldn't start to the end of the jingle
+    fn on_exit(&mut self, ctx: &mut Context) {
+        ctx.output.stop_melody();
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        self.elapsed_ms += ctx.dt_ms;
+        if self.elapsed_ms >= IDLE_TIMEOUT_MS {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+
+        if ctx.input.is_just_pressed(Button::A) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(FlappyScene::new()));
+        }
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        target.clear(theme.background)?;
+
+        let center = Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2);
+
+        let title_style = MonoTextStyleBuilder::new()
+            .font(&FONT_10X20)
+            .text_color(theme.foreground)
+            .build();
+        Text::with_alignment("Game Over", center - Point::new(0, 30), title_style, Alignment::Center)
+            .draw(target)?;
+
+        let mut score = String::<24>::new();
+        let _ = write!(score, "Score: {}", self.score);
+        Text::with_alignment(&score, center, title_style, Alignment::Center).draw(target)?;
+
+        let hint_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        let mut best = String::<24>::new();
+        if self.score > self.high_score {
+            let _ = write!(best, "New best!");
+        } else {
+            let _ = write!(best, "Best: {}", self.high_score);
+        }
+        Text::with_alignment(&best, center + Point::new(0, 20), hint_style, Alignment::Center)
+            .draw(target)?;
+
+        Text::with_alignment(
+            "A to retry, B for menu",
+            center + Point::new(0, 40),
+            hint_style,
+            Alignment::Center,
+        )
+        .draw(target)?;
+
+        Ok(())
+    }
+}
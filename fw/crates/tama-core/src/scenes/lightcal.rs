@@ -0,0 +1,186 @@
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_8X13}},
+    prelude::{DrawTarget, Point},
+    text::{Alignment, Text},
+};
+use heapless::String;
+
+use crate::{
+    consts,
+    engine::Context,
+    gfx::RenderError,
+    input::{Button, LightCalibration, SensorState, SensorType},
+    palette::Theme,
+    scenes::{Scene, SceneWrapper, UpdateResult, sensors::SensorScene},
+};
+
+// the two readings have to be at least this far apart, closer means the sensor
+// wasn't really covered or lit
+const MIN_SPAN_RAW: u16 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Dark,
+    Bright { dark_raw: u16 },
+}
+
+/// Takes the light sensor's reading covered and in bright light and stores them as
+/// the calibration. A takes a reading, B goes back without changing anything.
+pub struct LightCalibrationScene {
+    step: Step,
+    // copied from the input every update
+    reading: Option<u16>,
+    /// the last two readings were too close, starting over
+    too_close: bool,
+}
+
+impl LightCalibrationScene {
+    pub fn new() -> Self {
+        Self {
+            step: Step::Dark,
+            reading: None,
+            too_close: false,
+        }
+    }
+}
+
+impl Scene for LightCalibrationScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(SensorScene::new()));
+        }
+
+        self.reading = (ctx.input.get_sensor_state(SensorType::LightSensor) == SensorState::Normal)
+            .then(|| ctx.input.light_adc_raw());
+        let Some(reading) = self.reading else {
+            return UpdateResult::None;
+        };
+
+        if ctx.input.is_just_pressed(Button::A) {
+            match self.step {
+                Step::Dark => {
+                    self.step = Step::Bright { dark_raw: reading };
+                }
+                Step::Bright { dark_raw } if reading.abs_diff(dark_raw) < MIN_SPAN_RAW => {
+                    self.too_close = true;
+                    self.step = Step::Dark;
+                }
+                Step::Bright { dark_raw } => {
+                    log::info!("Light sensor calibrated, dark {} bright {}", dark_raw, reading);
+                    ctx.settings.light_calibration = LightCalibration {
+                        dark_raw,
+                        bright_raw: reading,
+                    };
+                    return UpdateResult::ChangeScene(SceneWrapper::from(SensorScene::new()));
+                }
+            }
+        }
+
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        target.clear(theme.background)?;
+
+        let center = Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2);
+        let title_style = MonoTextStyleBuilder::new()
+            .font(&FONT_8X13)
+            .text_color(theme.foreground)
+            .build();
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        let warning_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.warning)
+            .build();
+
+        Text::with_alignment("Light calibration", Point::new(center.x, 20), title_style, Alignment::Center)
+            .draw(target)?;
+
+        let instruction = match self.step {
+            Step::Dark => "Cover the light sensor",
+            Step::Bright { .. } => "Shine a light on the sensor",
+        };
+        Text::with_alignment(instruction, center - Point::new(0, 20), text_style, Alignment::Center)
+            .draw(target)?;
+
+        let mut reading = String::<24>::new();
+        match self.reading {
+            Some(raw) => {
+                let _ = write!(reading, "Reading: {}", raw);
+            }
+            None => {
+                let _ = write!(reading, "No light readings");
+            }
+        }
+        Text::with_alignment(&reading, center, text_style, Alignment::Center).draw(target)?;
+
+        if self.too_close {
+            Text::with_alignment(
+                "Readings too close, try again",
+                center + Point::new(0, 20),
+                warning_style,
+                Alignment::Center,
+            )
+            .draw(target)?;
+        }
+
+        Text::with_alignment(
+            "A to take reading, B to cancel",
+            Point::new(center.x, consts::HEIGHT as i32 - 20),
+            text_style,
+            Alignment::Center,
+        )
+        .draw(target)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ContextParts;
+
+    // Shows the sensor `raw` ADC counts and takes a reading
+    fn take_reading(scene: &mut LightCalibrationScene, parts: &mut ContextParts, raw: u16) -> UpdateResult {
+        parts.input.update_sensor(SensorType::LightSensor, raw as f32 / crate::input::LIGHT_ADC_MAX as f32, 0);
+        parts.input.update_button_level(Button::A, true);
+        let result = scene.update(&mut parts.context(16));
+        parts.input.consume_edges();
+        parts.input.update_button_level(Button::A, false);
+        parts.input.tick(16);
+        result
+    }
+
+    #[test]
+    fn two_readings_become_the_calibration() {
+        let mut parts = ContextParts::new();
+        let mut scene = LightCalibrationScene::new();
+        assert!(matches!(take_reading(&mut scene, &mut parts, 200), UpdateResult::None));
+        assert_eq!(scene.step, Step::Bright { dark_raw: 200 });
+
+        let result = take_reading(&mut scene, &mut parts, 3500);
+        assert!(matches!(result, UpdateResult::ChangeScene(SceneWrapper::SensorScene(_))));
+        assert_eq!(parts.settings.light_calibration, LightCalibration { dark_raw: 200, bright_raw: 3500 });
+    }
+
+    #[test]
+    fn readings_too_close_start_over() {
+        let mut parts = ContextParts::new();
+        let mut scene = LightCalibrationScene::new();
+        take_reading(&mut scene, &mut parts, 2000);
+        take_reading(&mut scene, &mut parts, 2000 + MIN_SPAN_RAW - 1);
+        assert_eq!(scene.step, Step::Dark);
+        assert!(scene.too_close);
+        assert_eq!(parts.settings.light_calibration, LightCalibration::default());
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::boxed::Box;
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::FONT_6X10},
+    prelude::{DrawTarget, Point, RgbColor},
+    text::Text,
+};
+
+use crate::{
+    consts,
+    engine::Context,
+    gfx::RenderError,
+    input::Button,
+    log_buffer::{LOG_TAG_MAX_LEN, LogLevel},
+    palette::Theme,
+    scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene},
+};
+
+const LINE_HEIGHT: i32 = 11;
+// first log line's baseline, the header sits above it
+const LINES_TOP: i32 = 30;
+const PAGE_LINES: usize = (consts::HEIGHT as usize - LINES_TOP as usize) / LINE_HEIGHT as usize;
+// FONT_6X10 is 6 px wide, longer lines are cut off
+const CHAR_WIDTH: i32 = 6;
+const LINE_CHARS: usize = consts::WIDTH as usize / CHAR_WIDTH as usize;
+// source tags are drawn before the message, darker so the messages stand out
+const TAG_COLOR: consts::ColorType = consts::ColorType::new(14, 28, 14);
+
+// one entry as it's drawn, "<level letter> <tag> <message>" with the message cut off to fit
+struct Line {
+    level: LogLevel,
+    tag: heapless::String<LOG_TAG_MAX_LEN>,
+    message: heapless::String<LINE_CHARS>,
+}
+
+/// Up/Down scroll a line, Left/Right a page, B goes back to the menu
+pub struct LogScene {
+    /// index of the first entry on screen, starts at the newest page
+    first: Option<usize>,
+    total: usize,
+    // what's on screen, copied in update since draw doesn't get the context.
+    // Boxed, it would make every SceneWrapper this big otherwise.
+    lines: Box<heapless::Vec<Line, PAGE_LINES>>,
+}
+
+impl LogScene {
+    pub fn new() -> Self {
+        Self {
+            first: None,
+            total: 0,
+            lines: Box::new(heapless::Vec::new()),
+        }
+    }
+}
+
+impl Scene for LogScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+
+        self.total = ctx.logs.len();
+        let last_page = self.total.saturating_sub(PAGE_LINES);
+        let mut first = self.first.unwrap_or(last_page);
+        if ctx.input.is_just_pressed(Button::Up) {
+            first = first.saturating_sub(1);
+        }
+        if ctx.input.is_just_pressed(Button::Down) {
+            first += 1;
+        }
+        if ctx.input.is_just_pressed(Button::Left) {
+            first = first.saturating_sub(PAGE_LINES);
+        }
+        if ctx.input.is_just_pressed(Button::Right) {
+            first += PAGE_LINES;
+        }
+        let first = first.min(last_page);
+        self.first = Some(first);
+
+        self.lines.clear();
+        for entry in ctx.logs.iter().skip(first).take(PAGE_LINES) {
+            let mut prefix_chars = entry.level.prefix().chars().count() + 1;
+            if !entry.tag.is_empty() {
+                prefix_chars += entry.tag.chars().count() + 1;
+            }
+            // cut off at the screen edge
+            let mut message = heapless::String::new();
+            for c in entry.message.chars().take(LINE_CHARS.saturating_sub(prefix_chars)) {
+                let _ = message.push(c);
+            }
+            let _ = self.lines.push(Line {
+                level: entry.level,
+                tag: entry.tag.clone(),
+                message,
+            });
+        }
+
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, _theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        target.clear(consts::ColorType::BLACK)?;
+
+        let style = |color| MonoTextStyleBuilder::new().font(&FONT_6X10).text_color(color).build();
+
+        let mut header = heapless::String::<LINE_CHARS>::new();
+        if self.lines.is_empty() {
+            let _ = header.push_str("Logs - no entries");
+        } else {
+            let first = self.first.unwrap_or(0);
+            let _ = write!(header, "Logs {}-{} of {}", first + 1, first + self.lines.len(), self.total);
+        }
+        Text::new(&header, Point::new(4, 14), style(consts::ColorType::WHITE)).draw(target)?;
+
+        for (i, line) in self.lines.iter().enumerate() {
+            let color = line.level.color();
+            let position = Point::new(4, LINES_TOP + i as i32 * LINE_HEIGHT);
+            let mut next = Text::new(line.level.prefix(), position, style(color)).draw(target)?;
+            next += Point::new(CHAR_WIDTH, 0);
+            if !line.tag.is_empty() {
+                next = Text::new(&line.tag, next, style(TAG_COLOR)).draw(target)? + Point::new(CHAR_WIDTH, 0);
+            }
+            Text::new(&line.message, next, style(color)).draw(target)?;
+        }
+
+        Ok(())
+    }
+}
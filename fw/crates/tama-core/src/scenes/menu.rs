@@ -1,53 +1,101 @@
 use embedded_graphics::{
     Drawable as _,
     image::Image,
-    mono_font::{MonoTextStyleBuilder, ascii::FONT_4X6},
-    prelude::{DrawTarget, Point, RgbColor},
-    text::{Alignment, Text},
+    prelude::{DrawTarget, Point},
 };
 
 use crate::{
-    assets, consts, gfx::Sprite, input::Button, scenes::{Scene, SceneWrapper, UpdateResult, flappy::FlappyScene}
+    assets::images::{self, ImageId}, consts, gfx::{RenderError, Sprite}, input::{Button, ComboDetector}, menu::Menu, palette::Theme, scenes::{Scene, SceneWrapper, UpdateResult, about::AboutScene, dvd::DvdScene, flappy::FlappyScene, log::LogScene, pet::PetScene, sensors::SensorScene, settings::SettingsScene}
 };
 
-pub struct MenuScene;
+// hidden screen
+const SECRET_SEQUENCE: [Button; 4] = [Button::Left, Button::Right, Button::Left, Button::Right];
+const SECRET_WINDOW_MS: u32 = 1500;
+const ENTRIES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+    Flappy,
+    DailyChallenge,
+    Pet,
+    Sensors,
+    Settings,
+    Logs,
+    About,
+}
+
+pub struct MenuScene {
+    elapsed_ms: u32,
+    combo: ComboDetector,
+    menu: Menu<MenuAction, ENTRIES>,
+    /// the cursor moved in the last update, the menu is static otherwise
+    cursor_moved: bool,
+}
 
 impl MenuScene {
     pub fn new() -> Self {
-        Self {}
+        let mut menu = Menu::new();
+        for (label, action) in [
+            ("Play", MenuAction::Flappy),
+            ("Daily challenge", MenuAction::DailyChallenge),
+            ("Visit your pet", MenuAction::Pet),
+            ("Sensors", MenuAction::Sensors),
+            ("Settings", MenuAction::Settings),
+            ("Logs", MenuAction::Logs),
+            ("About", MenuAction::About),
+        ] {
+            let _ = menu.push(label, action);
+        }
+
+        Self {
+            elapsed_ms: 0,
+            combo: ComboDetector::new(),
+            menu,
+            cursor_moved: false,
+        }
     }
 }
 
 impl Scene for MenuScene {
     fn update(&mut self, ctx: &mut crate::engine::Context) -> UpdateResult {
-        if ctx.input.is_just_pressed(Button::A) {
-            return UpdateResult::ChangeScene(SceneWrapper::from(FlappyScene::new()));
+        self.elapsed_ms += ctx.dt_ms;
+        self.combo.update(ctx.input, self.elapsed_ms);
+        if self.combo.matches(&SECRET_SEQUENCE, SECRET_WINDOW_MS) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(DvdScene::new()));
         }
-        UpdateResult::None
+
+        let cursor = self.menu.cursor();
+        let picked = self.menu.update(ctx.input);
+        self.cursor_moved = self.menu.cursor() != cursor;
+
+        let scene = match picked {
+            Some(MenuAction::Flappy) => SceneWrapper::from(FlappyScene::new()),
+            Some(MenuAction::DailyChallenge) => SceneWrapper::from(FlappyScene::daily(ctx.date_seed)),
+            Some(MenuAction::Pet) => SceneWrapper::from(PetScene::new()),
+            Some(MenuAction::Sensors) => SceneWrapper::from(SensorScene::new()),
+            Some(MenuAction::Settings) => SceneWrapper::from(SettingsScene::new(*ctx.settings)),
+            Some(MenuAction::Logs) => SceneWrapper::from(LogScene::new()),
+            Some(MenuAction::About) => SceneWrapper::from(AboutScene::new()),
+            None => return UpdateResult::None,
+        };
+        UpdateResult::ChangeScene(scene)
     }
 
-    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    fn needs_redraw(&self) -> bool {
+        self.cursor_moved
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
     where
         D: DrawTarget<Color = consts::ColorType>,
     {
-        target.clear(consts::ColorType::WHITE)?;
-
-        let text_style = MonoTextStyleBuilder::new()
-            .font(&FONT_4X6)
-            .text_color(consts::ColorType::BLACK)
-            .build();
+        target.clear(theme.background)?;
 
-        Text::with_alignment(
-            "Press A to start",
-            Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2),
-            text_style,
-            Alignment::Center,
-        )
-        .draw(target)?;
+        self.menu.draw(target, Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2), theme)?;
 
         // deref to unwrap the lazy_static
         // Image::new(&*assets::images::PAPAJ, Point::new(0, 0)).draw(target)?;
-        Sprite::new(&*assets::images::PAPAJ, Point::new(10, 0)).draw(target)?;
+        Sprite::new(images::image(ImageId::Papaj), Point::new(10, 0)).draw(target)?;
 
         Ok(())
     }
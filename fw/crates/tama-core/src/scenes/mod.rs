@@ -1,25 +1,65 @@
 use embedded_graphics::prelude::DrawTarget;
 use enum_dispatch::enum_dispatch;
 
-use crate::{consts, engine::Context, scenes::{flappy::FlappyScene, menu::MenuScene, selftest::SelfTestScene}};
+use crate::{consts, engine::Context, gfx::RenderError, palette::Theme, scenes::{about::AboutScene, dvd::DvdScene, flappy::FlappyScene, gameover::GameOverScene, lightcal::LightCalibrationScene, log::LogScene, menu::MenuScene, pause::PauseScene, pet::PetScene, selftest::SelfTestScene, sensors::SensorScene, settings::SettingsScene}};
 
+pub mod about;
 pub mod dvd;
 pub mod flappy;
+pub mod gameover;
+pub mod lightcal;
+pub mod log;
 pub mod menu;
+pub mod pause;
+pub mod pet;
 pub mod selftest;
+pub mod sensors;
+pub mod settings;
 
 pub enum UpdateResult {
     None,
-    // will have more than one scene type
+    /// Replace the current scene
     ChangeScene(SceneWrapper),
+    /// Pause the current scene and run the new one on top of it
+    PushScene(SceneWrapper),
+    /// Drop the current scene and resume the one below it
+    PopScene,
 }
 
 #[enum_dispatch]
 pub trait Scene {
     fn update(&mut self, ctx: &mut Context) -> UpdateResult;
-    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    /// Draw with the colors of `theme`, the one picked in the settings
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
     where
         D: DrawTarget<Color = consts::ColorType>;
+
+    /// Overlays are drawn on top of the scene below them instead of replacing it
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// False when the scene would draw the same frame as last time, the engine skips
+    /// drawing and the display transfer then. The first frame after a scene change is
+    /// always drawn, so this only has to cover changes made in `update`.
+    fn needs_redraw(&self) -> bool {
+        true
+    }
+
+    /// Scenes with frame-based physics return their step here and are updated in steps
+    /// of exactly that long, `ctx.dt_ms` is the real frame time for the others
+    fn fixed_step_ms(&self) -> Option<u32> {
+        None
+    }
+
+    /// Called when the scene becomes the active one: when it's the first scene, is changed
+    /// to or pushed, and when the scene pushed over it is popped. Runs after the previous
+    /// scene's `on_exit` and before the scene's next `update`.
+    fn on_enter(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the scene stops being the active one: when it's replaced or popped,
+    /// when another scene is pushed over it, and for the active scene on engine shutdown
+    fn on_exit(&mut self, _ctx: &mut Context) {}
 }
 
 // need a better name
@@ -28,4 +68,160 @@ pub enum SceneWrapper {
     MenuScene,
     FlappyScene,
     SelfTestScene,
+    PetScene,
+    PauseScene,
+    DvdScene,
+    LogScene,
+    GameOverScene,
+    SensorScene,
+    LightCalibrationScene,
+    SettingsScene,
+    AboutScene,
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::{Dimensions, Point, RgbColor};
+
+    use super::*;
+    use crate::{
+        buzzer::melodies,
+        input::Button,
+        palette::ThemeId,
+        settings::Settings,
+        test_support::{count_pixels, engine, render_engine, render_scene},
+    };
+
+    const FRAME_MS: u32 = 16;
+
+    fn every_scene() -> [SceneWrapper; 12] {
+        [
+            MenuScene::new().into(),
+            FlappyScene::new().into(),
+            SelfTestScene::new().into(),
+            PetScene::new().into(),
+            PauseScene::new().into(),
+            DvdScene::new().into(),
+            LogScene::new().into(),
+            GameOverScene::new(3, 7).into(),
+            SensorScene::new().into(),
+            LightCalibrationScene::new().into(),
+            SettingsScene::new(Settings::default()).into(),
+            AboutScene::new().into(),
+        ]
+    }
+
+    fn press(engine: &mut crate::engine::Engine, button: Button) {
+        for pressed in [true, false] {
+            engine.input_mut().update_button_level(button, pressed);
+            engine.input_mut().tick(FRAME_MS);
+            engine.update(FRAME_MS);
+        }
+    }
+
+    #[test]
+    fn every_scene_draws_with_every_theme() {
+        for theme in ThemeId::ALL.map(ThemeId::theme) {
+            for scene in every_scene() {
+                let display = render_scene(&scene, theme);
+                // overlays only draw over the middle of the screen, past the pixels kept
+                if !scene.is_overlay() {
+                    // cleared, so every pixel kept is set
+                    assert_eq!(display.affected_area(), display.bounding_box());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scenes_clear_to_the_theme_background() {
+        let display = render_scene(&MenuScene::new(), &Theme::DARK);
+        assert_eq!(display.get_pixel(Point::zero()), Some(Theme::DARK.background));
+        let display = render_scene(&MenuScene::new(), &Theme::LIGHT);
+        assert_eq!(display.get_pixel(Point::zero()), Some(Theme::LIGHT.background));
+        let background = count_pixels(&display, Theme::LIGHT.background);
+        assert!(0 < background && background < 64 * 64, "the logo is drawn over it");
+    }
+
+    #[test]
+    fn the_log_scene_is_always_dark() {
+        let display = render_scene(&LogScene::new(), &Theme::LIGHT);
+        assert_eq!(display.get_pixel(Point::zero()), Some(consts::ColorType::BLACK));
+    }
+
+    #[test]
+    fn boots_into_the_menu_after_the_startup_melody() {
+        let (mut engine, buzzer) = engine();
+        let display = render_engine(&mut engine);
+        assert!(matches!(engine.active_scene(), SceneWrapper::SelfTestScene(_)));
+        assert_eq!(display.get_pixel(Point::zero()), Some(Theme::LIGHT.background));
+
+        for _ in 0..1000 {
+            engine.update(FRAME_MS);
+            if matches!(engine.active_scene(), SceneWrapper::MenuScene(_)) {
+                break;
+            }
+        }
+        assert!(matches!(engine.active_scene(), SceneWrapper::MenuScene(_)));
+        assert!(buzzer.tones().ends_with(melodies::STARTUP));
+    }
+
+    // what `FlappyScene` plays for a flap
+    const FLAP_TONE: (u32, u32) = (40, 20);
+
+    fn start_flappy() -> (crate::engine::Engine, crate::buzzer::RecordingBuzzer) {
+        let (mut engine, buzzer) = engine();
+        while !matches!(engine.active_scene(), SceneWrapper::MenuScene(_)) {
+            engine.update(FRAME_MS);
+        }
+        press(&mut engine, Button::A);
+        assert!(matches!(engine.active_scene(), SceneWrapper::FlappyScene(_)));
+        (engine, buzzer)
+    }
+
+    fn flaps(buzzer: &crate::buzzer::RecordingBuzzer) -> usize {
+        buzzer.tones().iter().filter(|&&tone| tone == FLAP_TONE).count()
+    }
+
+    #[test]
+    fn flappy_draws_every_frame_of_a_run() {
+        let (mut engine, buzzer) = start_flappy();
+
+        // flapping now and then keeps the player up, so the pipes scroll past and off the left edge
+        let mut taps = 0;
+        for frame in 0..2000 {
+            let flap = frame % 20 == 0;
+            taps += flap as usize;
+            engine.input_mut().update_button_level(Button::Up, flap);
+            engine.input_mut().tick(FRAME_MS);
+            engine.update(FRAME_MS);
+            render_engine(&mut engine);
+            if matches!(engine.active_scene(), SceneWrapper::GameOverScene(_)) {
+                break;
+            }
+        }
+        assert!(matches!(engine.active_scene(), SceneWrapper::GameOverScene(_)));
+        // the tap of the crashing frame may not have had a step left to flap in
+        assert!(flaps(&buzzer) + 1 >= taps, "{} flaps for {taps} taps", flaps(&buzzer));
+    }
+
+    #[test]
+    fn a_one_frame_tap_flaps_on_every_step_phase() {
+        let step_ms = FlappyScene::new().fixed_step_ms().unwrap();
+        for phase in 0..step_ms / FRAME_MS {
+            let (mut engine, buzzer) = start_flappy();
+            for _ in 0..phase {
+                engine.input_mut().update_button_level(Button::Up, false);
+                engine.input_mut().tick(FRAME_MS);
+                engine.update(FRAME_MS);
+            }
+            buzzer.clear();
+            for pressed in [true, false] {
+                engine.input_mut().update_button_level(Button::Up, pressed);
+                engine.input_mut().tick(FRAME_MS);
+                engine.update(FRAME_MS);
+            }
+            assert_eq!(flaps(&buzzer), 1, "tap {phase} frames in");
+        }
+    }
 }
@@ -0,0 +1,71 @@
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_10X20}},
+    prelude::{DrawTarget, Point, Primitive, Size},
+    primitives::{PrimitiveStyleBuilder, Rectangle},
+    text::{Alignment, Text},
+};
+
+use crate::{
+    consts, engine::Context, gfx::RenderError, input::Button, palette::Theme, scenes::{Scene, UpdateResult}
+};
+
+const BOX_SIZE: Size = Size::new(160, 70);
+
+/// Pause overlay, the paused scene stays visible underneath
+pub struct PauseScene;
+
+impl PauseScene {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Scene for PauseScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::A) {
+            return UpdateResult::PopScene;
+        }
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        let center = Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2);
+
+        let box_style = PrimitiveStyleBuilder::new()
+            .fill_color(theme.background)
+            .stroke_color(theme.foreground)
+            .stroke_width(2)
+            .build();
+        Rectangle::with_center(center, BOX_SIZE)
+            .into_styled(box_style)
+            .draw(target)?;
+
+        let title_style = MonoTextStyleBuilder::new()
+            .font(&FONT_10X20)
+            .text_color(theme.foreground)
+            .build();
+        Text::with_alignment("Paused", center, title_style, Alignment::Center).draw(target)?;
+
+        let hint_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        Text::with_alignment(
+            "A to resume",
+            center + Point::new(0, 20),
+            hint_style,
+            Alignment::Center,
+        )
+        .draw(target)?;
+
+        Ok(())
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,140 @@
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    pixelcolor::Rgb888,
+    mono_font::{MonoTextStyleBuilder, ascii::FONT_8X13},
+    prelude::{DrawTarget, OriginDimensions, Point, Primitive, Size},
+    primitives::{Ellipse, PrimitiveStyle},
+    text::{Alignment, Text},
+};
+
+use crate::{
+    assets::images::{self, ImageId}, consts,
+    clock::DayClock,
+    engine::Context,
+    gfx::{self, Gauge, RenderError, Sprite},
+    input::Button,
+    palette::Theme,
+    pet::{Condition, LifeStage, Pet, STAT_MAX},
+    scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene},
+};
+
+const SPRITE_CENTER: Point = Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2 - 40);
+
+const BAR_X: i32 = 80;
+const BAR_TOP: i32 = consts::HEIGHT as i32 - 70;
+const BAR_SIZE: Size = Size::new(120, 10);
+const BAR_SPACING: i32 = 18;
+// the background fades towards this at night, not all the way so dark text stays readable
+const NIGHT_SKY: Rgb888 = Rgb888::new(10, 14, 40);
+const NIGHT_SKY_MAX_ALPHA: f32 = 160.0;
+
+/// A = feed, Up = play, Down = sleep, B = back to the menu
+pub struct PetScene {
+    // copy of the engine's pet for drawing
+    pet: Pet,
+    day_clock: DayClock,
+}
+
+impl PetScene {
+    pub fn new() -> Self {
+        Self { pet: Pet::new(), day_clock: DayClock::default() }
+    }
+}
+
+impl Scene for PetScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+
+        if ctx.input.is_just_pressed(Button::A) {
+            ctx.pet.feed();
+        }
+        if ctx.input.is_just_pressed(Button::Up) {
+            ctx.pet.play();
+        }
+        if ctx.input.is_just_pressed(Button::Down) {
+            ctx.pet.sleep();
+        }
+
+        self.pet = ctx.pet.clone();
+        self.day_clock = *ctx.day_clock;
+
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        let sleeping = self.pet.condition() == Condition::Sleeping;
+        let (background, foreground) = if sleeping {
+            (theme.foreground, theme.background)
+        } else {
+            (theme.background, theme.foreground)
+        };
+        // dims with the daylight, a sleeping pet is already shown dark
+        let darkness = ((1.0 - self.day_clock.daylight_factor()) * NIGHT_SKY_MAX_ALPHA) as u8;
+        let background = if sleeping {
+            background
+        } else {
+            gfx::blend(NIGHT_SKY, Rgb888::from(background), darkness).into()
+        };
+        target.clear(background)?;
+
+        match self.pet.stage() {
+            LifeStage::Egg => {
+                Ellipse::with_center(SPRITE_CENTER, Size::new(40, 52))
+                    .into_styled(PrimitiveStyle::with_stroke(foreground, 3))
+                    .draw(target)?;
+            }
+            LifeStage::Baby => draw_centered(images::image(ImageId::PapajSmol), target)?,
+            LifeStage::Child => draw_centered(images::image(ImageId::PapajMid), target)?,
+            LifeStage::Adult => draw_centered(images::image(ImageId::Papaj), target)?,
+        }
+
+        let text_style = MonoTextStyleBuilder::new()
+            .font(&FONT_8X13)
+            .text_color(foreground)
+            .build();
+
+        let status = match self.pet.condition() {
+            Condition::Awake => "",
+            Condition::Sleeping => "Zzz...",
+            Condition::Sick => "sick!",
+        };
+        Text::with_alignment(status, Point::new(consts::WIDTH as i32 / 2, 30), text_style, Alignment::Center)
+            .draw(target)?;
+
+        let mut time = heapless::String::<8>::new();
+        let _ = write!(time, "{:02}:{:02}", self.day_clock.hours(), self.day_clock.minutes());
+        Text::new(&time, Point::new(10, 16), text_style).draw(target)?;
+
+        let stats = [
+            ("food", self.pet.hunger()),
+            ("fun", self.pet.happiness()),
+            ("energy", self.pet.energy()),
+        ];
+        for (i, (label, value)) in stats.iter().enumerate() {
+            let top = BAR_TOP + i as i32 * BAR_SPACING;
+            Text::new(label, Point::new(20, top + 9), text_style).draw(target)?;
+            Gauge::new(Point::new(BAR_X, top), BAR_SIZE, *value as f32 / STAT_MAX as f32)
+                .fill_color(foreground)
+                .border_color(foreground)
+                .draw(target)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn draw_centered<D>(bmp: &tinybmp::Bmp<'static, consts::ColorType>, target: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = consts::ColorType>,
+{
+    let size = bmp.size();
+    let top_left = SPRITE_CENTER - Point::new(size.width as i32 / 2, size.height as i32 / 2);
+    Sprite::new(bmp, top_left).draw(target)
+}
@@ -1,160 +1,200 @@
+use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+
 use embedded_graphics::{
     Drawable as _,
-    mono_font::{MonoTextStyleBuilder, ascii::{FONT_8X13, FONT_10X20}},
-    prelude::{DrawTarget, Point, RgbColor},
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_8X13, FONT_10X20}},
+    prelude::{DrawTarget, Point},
     text::{Alignment, Text},
 };
-use heapless::vec;
-
 use crate::{
-    consts, scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene}
+    buzzer::{Melody, melodies}, consts, engine::Context, gfx::{RenderError, draw_wrapped}, input::{SensorState, SensorType}, palette::Theme, scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene}
 };
 
-struct TestEntry {
+/// Battery voltages outside this can't come from a working LiPo and its ADC
+const BATTERY_PLAUSIBLE_V: (f32, f32) = (2.5, 4.3);
+
+/// Each check is shown running for this long before it runs, so the list doesn't flash by
+const CHECK_DELAY_MS: u32 = 250;
+const FINAL_DELAY_MS: u32 = 3000;
+/// The results stay up longer when something failed
+const FAILED_DELAY_MS: u32 = 8000;
+
+const LIST_TOP: i32 = 45;
+const LINE_HEIGHT: i32 = 12;
+const MARGIN: i32 = 20;
+
+/// What a check returns, the error says what's wrong in a few words
+pub type CheckResult = Result<(), String>;
+
+/// One line of the boot self-test
+pub struct SelfTestCheck {
     name: &'static str,
-    delay_ms: u32,
+    run: Box<dyn FnMut(&mut Context) -> CheckResult>,
+}
+
+impl SelfTestCheck {
+    pub fn new(name: &'static str, run: impl FnMut(&mut Context) -> CheckResult + 'static) -> Self {
+        Self { name, run: Box::new(run) }
+    }
 }
 
-const TEST_ENTRIES: &[TestEntry] = &[
-    TestEntry { name: "init display...", delay_ms: 300 },
-    TestEntry { name: "init thermometer...", delay_ms: 100 },
-    TestEntry { name: "calibrating light sensor...", delay_ms: 400 },
-    TestEntry { name: "rubbing the rat...", delay_ms: 1200 },
-];
+/// The checks every platform gets: each sensor reports plausible readings, the buzzer beeps.
+/// Platforms add their own hardware checks with `Engine::with_self_test_check`.
+pub fn default_checks() -> Vec<SelfTestCheck> {
+    let mut checks = vec![SelfTestCheck::new("battery", |ctx| {
+        sensor_reporting(ctx, SensorType::BatteryVoltage)?;
+        let voltage = ctx.input.get_sensor_value(SensorType::BatteryVoltage);
+        let (min, max) = BATTERY_PLAUSIBLE_V;
+        if !(min..=max).contains(&voltage) {
+            return Err(format!("{:.2} V is implausible", voltage));
+        }
+        Ok(())
+    })];
+    for sensor in SensorType::all().filter(|&sensor| sensor != SensorType::BatteryVoltage) {
+        checks.push(SelfTestCheck::new(sensor.name(), move |ctx| sensor_reporting(ctx, sensor)));
+    }
+    checks.push(SelfTestCheck::new("buzzer", |ctx| {
+        // nothing comes back from the buzzer, this one is checked by ear
+        ctx.output.play_tone(230, 32);
+        Ok(())
+    }));
+    checks
+}
 
-const FINAL_DELAY_MS: u32 = 3000;
+fn sensor_reporting(ctx: &Context, sensor: SensorType) -> CheckResult {
+    match ctx.input.get_sensor_state(sensor) {
+        SensorState::Uninitialized => Err(String::from("no readings")),
+        SensorState::SensorError => Err(String::from("sensor error")),
+        SensorState::Normal | SensorState::Event => Ok(()),
+    }
+}
 
+/// Runs the checks one after the other at boot, then shows the splash and goes to the menu
 pub struct SelfTestScene {
     elapsed_ms: u32,
-    current_test: usize,
+    checks: Vec<SelfTestCheck>,
+    /// result of each check run so far, by index in `checks`
+    results: Vec<CheckResult>,
     test_start_time: u32,
+    melody_started: bool,
 }
 
 impl SelfTestScene {
     pub fn new() -> Self {
+        Self::with_checks(default_checks())
+    }
+
+    pub fn with_checks(checks: Vec<SelfTestCheck>) -> Self {
         Self {
             elapsed_ms: 0,
-            current_test: 0,
+            checks,
+            results: Vec::new(),
             test_start_time: 0,
+            melody_started: false,
         }
     }
-}
 
-fn get_music_samples() -> heapless::Vec<(u32, u32), 10> {
-    let frequencies  = [293, 329, 349, 329, 293, 261, 261, 261, 261, 261];
+    /// Runs after the ones already there
+    pub fn add_check(&mut self, check: SelfTestCheck) {
+        self.checks.push(check);
+    }
 
-    // build a heapless Vec of (frequency, duration_ms) pairs; capacity 16 is enough for 15 entries
-    let mut samples: heapless::Vec<(u32, u32), 10> = heapless::Vec::new();
-    for &f in frequencies.iter() {
-        // use a fixed duration per note (adjust as needed)
-        let _ = samples.push((f, 50));
+    fn finished(&self) -> bool {
+        self.results.len() >= self.checks.len()
     }
 
-    samples
+    fn all_passed(&self) -> bool {
+        self.results.iter().all(Result::is_ok)
+    }
 }
 
-static mut notes_played: u32 = 0;
-
-
 impl Scene for SelfTestScene {
-    fn update(&mut self, _ctx: &mut crate::engine::Context) -> UpdateResult {
-        self.elapsed_ms += 32;
-        
-        // Check if current test is complete
-        if self.current_test < TEST_ENTRIES.len() {
-            let current_entry = &TEST_ENTRIES[self.current_test];
-            let test_elapsed = self.elapsed_ms - self.test_start_time;
-            
-            if test_elapsed >= current_entry.delay_ms {
-                // Move to next test
-                log::debug!("Self-test: {} completed", current_entry.name);
-                self.current_test += 1;
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        self.elapsed_ms += ctx.dt_ms;
+        let test_elapsed = self.elapsed_ms - self.test_start_time;
+
+        if !self.finished() {
+            if test_elapsed >= CHECK_DELAY_MS {
+                let check = &mut self.checks[self.results.len()];
+                let result = (check.run)(ctx);
+                match &result {
+                    Ok(()) => crate::notice!("Self-test {}: ok", check.name),
+                    Err(e) => crate::notice!("Self-test {}: FAILED, {}", check.name, e),
+                }
+                self.results.push(result);
                 self.test_start_time = self.elapsed_ms;
-
-                _ctx.output.play_tone(230, 32); // Play beep on test completion
             }
         } else {
             // All tests completed, wait for final delay then transition
-            let test_elapsed = self.elapsed_ms - self.test_start_time;
-
-            let samples = get_music_samples();
-
-            unsafe{
-                _ctx.output.play_tone(samples[(notes_played/3) as usize].0, samples[(notes_played/3) as usize].1);
-                notes_played += 1;
-
-                if notes_played >= (samples.len() as u32)*3 {
-                    return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
-                }
+            if !self.melody_started {
+                ctx.output.play_melody(Melody::from_notes(melodies::STARTUP));
+                self.melody_started = true;
+            } else if !ctx.output.is_melody_playing() && self.all_passed() {
+                return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
             }
 
-
-            if test_elapsed >= FINAL_DELAY_MS {
+            let delay_ms = if self.all_passed() { FINAL_DELAY_MS } else { FAILED_DELAY_MS };
+            if test_elapsed >= delay_ms {
                 return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
             }
         }
-        
+
         UpdateResult::None
     }
 
-    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
     where
         D: DrawTarget<Color = consts::ColorType>,
     {
-        target.clear(consts::ColorType::BLACK)?;
+        target.clear(theme.background)?;
 
-        let text_style = MonoTextStyleBuilder::new()
+        let title_style = MonoTextStyleBuilder::new()
             .font(&FONT_8X13)
-            .text_color(consts::ColorType::RED)
+            .text_color(theme.foreground)
             .build();
+        let style = |color| MonoTextStyleBuilder::new().font(&FONT_6X10).text_color(color).build();
 
         // Title
         Text::with_alignment(
             "Self-Test",
             Point::new(consts::WIDTH as i32 / 2, 20),
-            text_style,
+            title_style,
             Alignment::Center,
         )
         .draw(target)?;
 
-        let start_y = 50;
-        let line_height = 15;
-
-        // Draw test lines based on current progress
-        for (i, test_entry) in TEST_ENTRIES.iter().enumerate() {
-            if i < self.current_test {
-                // Test completed - show with [ok]
-                let mut text = heapless::String::<64>::new();
-                let _ = text.push_str(test_entry.name);
-                let _ = text.push_str(" [ok]");
-                
-                Text::new(
-                    text.as_str(),
-                    Point::new(20, start_y + (i as i32 * line_height)),
-                    text_style,
-                )
-                .draw(target)?;
-            } else if i == self.current_test {
-                // Current test in progress - show without [ok]
-                Text::new(
-                    test_entry.name,
-                    Point::new(20, start_y + (i as i32 * line_height)),
-                    text_style,
-                )
+        // the status goes at the right edge, failure reasons continue on the next lines
+        let status_x = consts::WIDTH as i32 - MARGIN;
+        let max_chars = (consts::WIDTH as usize - 2 * MARGIN as usize) / FONT_6X10.character_size.width as usize;
+        let mut position = Point::new(MARGIN, LIST_TOP);
+
+        for (i, check) in self.checks.iter().enumerate().take(self.results.len() + 1) {
+            Text::new(check.name, position, style(theme.foreground)).draw(target)?;
+            let (status, color) = match self.results.get(i) {
+                Some(Ok(())) => ("ok", theme.success),
+                Some(Err(_)) => ("FAIL", theme.warning),
+                None => ("...", theme.foreground),
+            };
+            Text::with_alignment(status, Point::new(status_x, position.y), style(color), Alignment::Right)
                 .draw(target)?;
+            position.y += LINE_HEIGHT;
+
+            if let Some(Err(reason)) = self.results.get(i) {
+                position = draw_wrapped(reason, max_chars - 2, position + Point::new(12, 0), style(theme.warning), LINE_HEIGHT, target)?;
+                position.x = MARGIN;
             }
         }
 
         // If all tests are complete, show "Rough Rat" in larger font
-        if self.current_test >= TEST_ENTRIES.len() {
+        if self.finished() {
             let large_text_style = MonoTextStyleBuilder::new()
                 .font(&FONT_10X20)
-                .text_color(consts::ColorType::RED)
+                .text_color(theme.accent)
                 .build();
 
             Text::with_alignment(
                 "Rough Rat",
-                Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 / 2 + 20),
+                Point::new(consts::WIDTH as i32 / 2, consts::HEIGHT as i32 - 30),
                 large_text_style,
                 Alignment::Center,
             )
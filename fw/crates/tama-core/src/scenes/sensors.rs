@@ -0,0 +1,136 @@
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_8X13}},
+    prelude::{DrawTarget, Point, Size},
+    text::Text,
+};
+use heapless::String;
+
+use crate::{
+    battery::voltage_to_percent,
+    consts,
+    engine::Context,
+    gfx::{Gauge, RenderError},
+    input::{Button, SensorState, SensorType},
+    palette::Theme,
+    scenes::{Scene, SceneWrapper, UpdateResult, lightcal::LightCalibrationScene, menu::MenuScene},
+};
+
+const ROWS_TOP: i32 = 40;
+const ROW_HEIGHT: i32 = 44;
+const GAUGE_SIZE: Size = Size::new(consts::WIDTH - 40, 10);
+
+/// Live readings of all sensors, A calibrates the light sensor, B goes back to the menu
+pub struct SensorScene {
+    // copied from the input every update, (smoothed value, state) by sensor index
+    readings: [(f32, SensorState); SensorType::COUNT],
+    charging: bool,
+}
+
+impl SensorScene {
+    pub fn new() -> Self {
+        Self {
+            readings: [(0.0, SensorState::Uninitialized); SensorType::COUNT],
+            charging: false,
+        }
+    }
+}
+
+impl Scene for SensorScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+        if ctx.input.is_just_pressed(Button::A) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(LightCalibrationScene::new()));
+        }
+
+        for sensor in SensorType::all() {
+            self.readings[sensor as usize] = (ctx.input.get_sensor_value(sensor), ctx.input.get_sensor_state(sensor));
+        }
+        self.charging = ctx.input.is_charging();
+
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        target.clear(theme.background)?;
+
+        let title_style = MonoTextStyleBuilder::new()
+            .font(&FONT_8X13)
+            .text_color(theme.foreground)
+            .build();
+        Text::new("Sensors", Point::new(20, 20), title_style).draw(target)?;
+
+        for sensor in SensorType::all() {
+            let (value, state) = self.readings[sensor as usize];
+            let top = ROWS_TOP + sensor as i32 * ROW_HEIGHT;
+
+            let mut label = String::<40>::new();
+            let precision = if sensor == SensorType::Thermometer { 1 } else { 2 };
+            let _ = write!(label, "{}: {:.*}", label_name(sensor), precision, value);
+            if !sensor.unit().is_empty() {
+                let _ = write!(label, " {}", sensor.unit());
+            }
+            let ratio = if sensor == SensorType::BatteryVoltage && self.charging {
+                // the voltage says more than the battery holds
+                let _ = write!(label, " (charging)");
+                voltage_to_percent(value) as f32 / 100.0
+            } else if sensor == SensorType::BatteryVoltage {
+                let percent = voltage_to_percent(value);
+                let _ = write!(label, " ({}%)", percent);
+                percent as f32 / 100.0
+            } else {
+                let (min, max) = sensor.range();
+                (value - min) / (max - min)
+            };
+
+            let (state_name, color) = match state {
+                SensorState::Uninitialized => ("no data", theme.foreground),
+                SensorState::Normal => ("ok", theme.success),
+                SensorState::Event => ("event", theme.caution),
+                SensorState::SensorError => ("error", theme.warning),
+            };
+            let label_style = MonoTextStyleBuilder::new()
+                .font(&FONT_6X10)
+                .text_color(theme.foreground)
+                .build();
+            let state_style = MonoTextStyleBuilder::new()
+                .font(&FONT_6X10)
+                .text_color(color)
+                .build();
+
+            let next = Text::new(&label, Point::new(20, top), label_style).draw(target)?;
+            Text::new(state_name, next + Point::new(6, 0), state_style).draw(target)?;
+
+            Gauge::new(Point::new(20, top + 6), GAUGE_SIZE, ratio)
+                .fill_color(color)
+                .border_color(theme.foreground)
+                .draw(target)?;
+        }
+
+        let hint_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        Text::new("A: calibrate light, B: back", Point::new(20, consts::HEIGHT as i32 - 14), hint_style)
+            .draw(target)?;
+
+        Ok(())
+    }
+}
+
+fn label_name(sensor: SensorType) -> &'static str {
+    match sensor {
+        SensorType::BatteryVoltage => "Battery",
+        SensorType::Thermometer => "Temperature",
+        SensorType::LightSensor => "Light",
+        SensorType::Accelerometer => "Motion",
+        SensorType::MicLoudness => "Mic",
+    }
+}
@@ -0,0 +1,217 @@
+use core::fmt::Write as _;
+
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyleBuilder, ascii::{FONT_6X10, FONT_8X13}},
+    prelude::{DrawTarget, Point, Size},
+    text::Text,
+};
+use heapless::String;
+
+use crate::{
+    buzzer::MAX_VOLUME,
+    clock::DayClock,
+    consts,
+    engine::Context,
+    gfx::{Gauge, RenderError},
+    input::Button,
+    palette::{Theme, ThemeId},
+    scenes::{Scene, SceneWrapper, UpdateResult, menu::MenuScene},
+    settings::Settings,
+};
+
+const ROWS_TOP: i32 = 50;
+const ROW_HEIGHT: i32 = 36;
+const GAUGE_SIZE: Size = Size::new(consts::WIDTH - 40, 10);
+const VOLUME_STEP: u8 = 10;
+const BRIGHTNESS_STEP: u8 = 10;
+// the screen would go dark with the backlight off, and there'd be no way to see the setting
+const MIN_BRIGHTNESS: u8 = 10;
+// Left/Right step through these, 0 is off
+const SCREENSAVER_TIMEOUTS_S: [u16; 6] = [0, 30, 60, 120, 300, 600];
+const CLOCK_STEP_S: i32 = 15 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Volume,
+    Brightness,
+    AutoBrightness,
+    Screensaver,
+    Theme,
+    Clock,
+}
+
+impl Row {
+    const ALL: [Row; 6] = [Row::Volume, Row::Brightness, Row::AutoBrightness, Row::Screensaver, Row::Theme, Row::Clock];
+}
+
+/// Up/Down pick a row, Left/Right change it (A flips the toggles and steps through the
+/// themes too), B goes back to the menu. Changes apply right away and are saved once
+/// the scene is left.
+pub struct SettingsScene {
+    cursor: usize,
+    // copied from the context every update
+    settings: Settings,
+    day_clock: DayClock,
+}
+
+impl SettingsScene {
+    /// `settings` are the current ones, they're on screen before the first update
+    pub fn new(settings: Settings) -> Self {
+        Self { cursor: 0, settings, day_clock: DayClock::new(settings.time_of_day_s) }
+    }
+}
+
+impl Scene for SettingsScene {
+    fn update(&mut self, ctx: &mut Context) -> UpdateResult {
+        if ctx.input.is_just_pressed(Button::B) {
+            return UpdateResult::ChangeScene(SceneWrapper::from(MenuScene::new()));
+        }
+
+        if ctx.input.is_just_pressed(Button::Up) {
+            self.cursor = (self.cursor + Row::ALL.len() - 1) % Row::ALL.len();
+        }
+        if ctx.input.is_just_pressed(Button::Down) {
+            self.cursor = (self.cursor + 1) % Row::ALL.len();
+        }
+
+        let direction = if ctx.input.is_just_pressed(Button::Right) {
+            1
+        } else if ctx.input.is_just_pressed(Button::Left) {
+            -1
+        } else {
+            0
+        };
+        let settings = &mut *ctx.settings;
+        match Row::ALL[self.cursor] {
+            Row::Volume if direction != 0 => {
+                settings.volume = step(settings.volume, direction, VOLUME_STEP, 0, MAX_VOLUME);
+            }
+            Row::Brightness if direction != 0 => {
+                settings.brightness = step(settings.brightness, direction, BRIGHTNESS_STEP, MIN_BRIGHTNESS, 100);
+            }
+            Row::AutoBrightness => {
+                if direction != 0 || ctx.input.is_just_pressed(Button::A) {
+                    settings.auto_brightness_enabled = !settings.auto_brightness_enabled;
+                }
+            }
+            Row::Screensaver if direction != 0 => {
+                // a timeout that isn't one of the steps continues from the next longer one
+                let current = SCREENSAVER_TIMEOUTS_S
+                    .iter()
+                    .position(|&timeout_s| timeout_s >= settings.screensaver_timeout_s)
+                    .unwrap_or(SCREENSAVER_TIMEOUTS_S.len() - 1);
+                let next = (current as i32 + direction).clamp(0, SCREENSAVER_TIMEOUTS_S.len() as i32 - 1);
+                settings.screensaver_timeout_s = SCREENSAVER_TIMEOUTS_S[next as usize];
+            }
+            Row::Theme => {
+                let step = if direction != 0 {
+                    direction
+                } else if ctx.input.is_just_pressed(Button::A) {
+                    1
+                } else {
+                    0
+                };
+                if step != 0 {
+                    let count = ThemeId::ALL.len() as i32;
+                    let current = ThemeId::ALL.iter().position(|&theme| theme == settings.theme).unwrap_or(0);
+                    settings.theme = ThemeId::ALL[(current as i32 + step).rem_euclid(count) as usize];
+                }
+            }
+            Row::Clock if direction != 0 => {
+                ctx.day_clock.shift(direction * CLOCK_STEP_S);
+                // so the new time counts as a change and gets saved
+                settings.time_of_day_s = ctx.day_clock.seconds();
+            }
+            Row::Volume | Row::Brightness | Row::Screensaver | Row::Clock => (),
+        }
+
+        self.settings = *ctx.settings;
+        self.day_clock = *ctx.day_clock;
+        UpdateResult::None
+    }
+
+    fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), RenderError<D::Error>>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        target.clear(theme.background)?;
+
+        let title_style = MonoTextStyleBuilder::new()
+            .font(&FONT_8X13)
+            .text_color(theme.foreground)
+            .build();
+        Text::new("Settings", Point::new(20, 20), title_style).draw(target)?;
+
+        for (i, &row) in Row::ALL.iter().enumerate() {
+            let top = ROWS_TOP + i as i32 * ROW_HEIGHT;
+            let color = if i == self.cursor {
+                theme.accent
+            } else {
+                theme.foreground
+            };
+            let style = MonoTextStyleBuilder::new()
+                .font(&FONT_6X10)
+                .text_color(color)
+                .build();
+
+            let mut label = String::<32>::new();
+            let ratio = match row {
+                Row::Volume => {
+                    let _ = write!(label, "Volume: {}%", self.settings.volume);
+                    Some(self.settings.volume as f32 / MAX_VOLUME as f32)
+                }
+                Row::Brightness => {
+                    let _ = write!(label, "Brightness: {}%", self.settings.brightness);
+                    Some(self.settings.brightness as f32 / 100.0)
+                }
+                Row::AutoBrightness => {
+                    let state = if self.settings.auto_brightness_enabled { "on" } else { "off" };
+                    let _ = write!(label, "Auto brightness: {}", state);
+                    None
+                }
+                Row::Screensaver => {
+                    match self.settings.screensaver_timeout_s {
+                        0 => {
+                            let _ = write!(label, "Screensaver: off");
+                        }
+                        timeout_s => {
+                            let _ = write!(label, "Screensaver: after {} s", timeout_s);
+                        }
+                    }
+                    None
+                }
+                Row::Theme => {
+                    let _ = write!(label, "Theme: {}", self.settings.theme.name());
+                    None
+                }
+                Row::Clock => {
+                    let _ = write!(label, "Clock: {:02}:{:02}", self.day_clock.hours(), self.day_clock.minutes());
+                    None
+                }
+            };
+
+            Text::new(&label, Point::new(20, top), style).draw(target)?;
+            if let Some(ratio) = ratio {
+                Gauge::new(Point::new(20, top + 6), GAUGE_SIZE, ratio)
+                    .fill_color(color)
+                    .border_color(theme.foreground)
+                    .draw(target)?;
+            }
+        }
+
+        let hint_style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(theme.foreground)
+            .build();
+        Text::new("Left/Right to change, B: back", Point::new(20, consts::HEIGHT as i32 - 14), hint_style)
+            .draw(target)?;
+
+        Ok(())
+    }
+}
+
+// `value` moved by `step` in `direction` (-1, 0, 1), kept within min..=max
+fn step(value: u8, direction: i32, step: u8, min: u8, max: u8) -> u8 {
+    (value as i32 + direction * step as i32).clamp(min as i32, max as i32) as u8
+}
@@ -0,0 +1,337 @@
+use crate::{
+    buzzer::MAX_VOLUME,
+    clock::{DayClock, SECONDS_PER_DAY},
+    input::LightCalibration,
+    palette::ThemeId,
+    pet::{PetStats, condition_from_byte, condition_to_byte},
+};
+
+// On-flash format, little endian:
+//   0      version
+//   1      volume (0-100)
+//   2      brightness (0-100)
+//   3      flags, bit 0 = auto brightness
+//   4      pet hunger
+//   5      pet happiness
+//   6      pet energy
+//   7      pet condition (0 awake, 1 sleeping, 2 sick)
+//   8..16  pet age in ms, u64
+// version 2:
+//   16..20 flappy high score, u32
+// version 3:
+//   20..22 screensaver timeout in seconds, u16, 0 = never
+// version 4:
+//   22..24 light sensor dark point, u16 ADC counts
+//   24..26 light sensor bright point, u16 ADC counts
+// version 5:
+//   26..34 wall-clock time of the save in unix seconds, u64, 0 = unknown
+// version 6:
+//   34     theme (0 light, 1 dark, 2 high contrast)
+// version 7:
+//   35..43 uptime summed over all sessions in ms, u64
+//   43..47 sessions (boots) so far, u32
+// version 8:
+//   47..51 time of day of the save in seconds past midnight, u32
+// version 9:
+//   51..55 pet neglect in ms, u32
+// Fields are only ever appended and the version bumped, so any blob of at least
+// `SETTINGS_V1_SIZE` bytes can be read. Fields newer than the blob keep their defaults.
+pub const SETTINGS_VERSION: u8 = 9;
+const SETTINGS_V1_SIZE: usize = 16;
+const SETTINGS_V2_SIZE: usize = 20;
+const SETTINGS_V3_SIZE: usize = 22;
+const SETTINGS_V4_SIZE: usize = 26;
+const SETTINGS_V5_SIZE: usize = 34;
+const SETTINGS_V6_SIZE: usize = 35;
+const SETTINGS_V7_SIZE: usize = 47;
+const SETTINGS_V8_SIZE: usize = 51;
+const SETTINGS_V9_SIZE: usize = 55;
+/// Size of the blob `Settings::to_bytes` writes
+pub const SETTINGS_SIZE: usize = SETTINGS_V9_SIZE;
+
+const FLAG_AUTO_BRIGHTNESS: u8 = 1 << 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub volume: u8,
+    pub brightness: u8,
+    pub auto_brightness_enabled: bool,
+    pub pet: PetStats,
+    pub flappy_high_score: u32,
+    /// idle time before the screensaver starts, 0 turns it off
+    pub screensaver_timeout_s: u16,
+    pub light_calibration: LightCalibration,
+    /// when these were saved, for the pet to catch up on the time powered off. Set by
+    /// the engine when the platform has a wall clock, 0 otherwise.
+    pub saved_at_s: u64,
+    pub theme: ThemeId,
+    /// as of the last save, see `Uptime::lifetime_ms`
+    pub lifetime_uptime_ms: u64,
+    /// counted up by the engine on every boot, this one included
+    pub sessions: u32,
+    /// the `DayClock` as of the last save, it continues from here on the next boot
+    pub time_of_day_s: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: MAX_VOLUME,
+            brightness: 100,
+            auto_brightness_enabled: true,
+            pet: PetStats::default(),
+            flappy_high_score: 0,
+            screensaver_timeout_s: 60,
+            light_calibration: LightCalibration::default(),
+            saved_at_s: 0,
+            theme: ThemeId::default(),
+            lifetime_uptime_ms: 0,
+            sessions: 0,
+            time_of_day_s: DayClock::default().seconds(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn to_bytes(&self) -> [u8; SETTINGS_SIZE] {
+        let mut bytes = [0; SETTINGS_SIZE];
+        bytes[0] = SETTINGS_VERSION;
+        bytes[1] = self.volume;
+        bytes[2] = self.brightness;
+        bytes[3] = if self.auto_brightness_enabled { FLAG_AUTO_BRIGHTNESS } else { 0 };
+        bytes[4] = self.pet.hunger;
+        bytes[5] = self.pet.happiness;
+        bytes[6] = self.pet.energy;
+        bytes[7] = condition_to_byte(self.pet.condition);
+        bytes[8..16].copy_from_slice(&self.pet.age_ms.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.flappy_high_score.to_le_bytes());
+        bytes[20..22].copy_from_slice(&self.screensaver_timeout_s.to_le_bytes());
+        bytes[22..24].copy_from_slice(&self.light_calibration.dark_raw.to_le_bytes());
+        bytes[24..26].copy_from_slice(&self.light_calibration.bright_raw.to_le_bytes());
+        bytes[26..34].copy_from_slice(&self.saved_at_s.to_le_bytes());
+        bytes[34] = self.theme.to_byte();
+        bytes[35..43].copy_from_slice(&self.lifetime_uptime_ms.to_le_bytes());
+        bytes[43..47].copy_from_slice(&self.sessions.to_le_bytes());
+        bytes[47..51].copy_from_slice(&self.time_of_day_s.to_le_bytes());
+        bytes[51..55].copy_from_slice(&self.pet.neglect_ms.to_le_bytes());
+        bytes
+    }
+
+    /// `None` if the blob is too short or wasn't written by any known version
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < SETTINGS_V1_SIZE || bytes[0] == 0 {
+            return None;
+        }
+
+        let condition = condition_from_byte(bytes[7]);
+        let mut age = [0; 8];
+        age.copy_from_slice(&bytes[8..16]);
+        let mut flappy_high_score = [0; 4];
+        if bytes[0] >= 2 && bytes.len() >= SETTINGS_V2_SIZE {
+            flappy_high_score.copy_from_slice(&bytes[16..20]);
+        }
+        let screensaver_timeout_s = if bytes[0] >= 3 && bytes.len() >= SETTINGS_V3_SIZE {
+            u16::from_le_bytes([bytes[20], bytes[21]])
+        } else {
+            Settings::default().screensaver_timeout_s
+        };
+        let light_calibration = if bytes[0] >= 4 && bytes.len() >= SETTINGS_V4_SIZE {
+            LightCalibration {
+                dark_raw: u16::from_le_bytes([bytes[22], bytes[23]]),
+                bright_raw: u16::from_le_bytes([bytes[24], bytes[25]]),
+            }
+        } else {
+            LightCalibration::default()
+        };
+        let mut saved_at_s = [0; 8];
+        if bytes[0] >= 5 && bytes.len() >= SETTINGS_V5_SIZE {
+            saved_at_s.copy_from_slice(&bytes[26..34]);
+        }
+        let theme = if bytes[0] >= 6 && bytes.len() >= SETTINGS_V6_SIZE {
+            ThemeId::from_byte(bytes[34])
+        } else {
+            ThemeId::default()
+        };
+        let mut lifetime_uptime_ms = [0; 8];
+        let mut sessions = [0; 4];
+        if bytes[0] >= 7 && bytes.len() >= SETTINGS_V7_SIZE {
+            lifetime_uptime_ms.copy_from_slice(&bytes[35..43]);
+            sessions.copy_from_slice(&bytes[43..47]);
+        }
+        let time_of_day_s = if bytes[0] >= 8 && bytes.len() >= SETTINGS_V8_SIZE {
+            u32::from_le_bytes([bytes[47], bytes[48], bytes[49], bytes[50]]) % SECONDS_PER_DAY
+        } else {
+            Settings::default().time_of_day_s
+        };
+        let mut neglect = [0; 4];
+        if bytes[0] >= 9 && bytes.len() >= SETTINGS_V9_SIZE {
+            neglect.copy_from_slice(&bytes[51..55]);
+        }
+
+        Some(Self {
+            volume: bytes[1].min(MAX_VOLUME),
+            brightness: bytes[2].min(100),
+            auto_brightness_enabled: bytes[3] & FLAG_AUTO_BRIGHTNESS != 0,
+            pet: PetStats {
+                hunger: bytes[4],
+                happiness: bytes[5],
+                energy: bytes[6],
+                condition,
+                age_ms: u64::from_le_bytes(age),
+                neglect_ms: u32::from_le_bytes(neglect),
+            },
+            flappy_high_score: u32::from_le_bytes(flappy_high_score),
+            screensaver_timeout_s,
+            light_calibration,
+            saved_at_s: u64::from_le_bytes(saved_at_s),
+            theme,
+            lifetime_uptime_ms: u64::from_le_bytes(lifetime_uptime_ms),
+            sessions: u32::from_le_bytes(sessions),
+            time_of_day_s,
+        })
+    }
+}
+
+/// Where settings are kept between runs
+pub trait SettingsStore {
+    /// `None` when nothing was saved yet or the stored data can't be read
+    fn load(&mut self) -> Option<Settings>;
+
+    fn save(&mut self, settings: &Settings);
+}
+
+/// Keeps settings only for as long as the program runs
+#[derive(Debug, Default)]
+pub struct MemorySettingsStore {
+    settings: Option<Settings>,
+}
+
+impl SettingsStore for MemorySettingsStore {
+    fn load(&mut self) -> Option<Settings> {
+        self.settings
+    }
+
+    fn save(&mut self, settings: &Settings) {
+        self.settings = Some(*settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pet::Condition;
+
+    // Every field away from its default
+    fn custom() -> Settings {
+        Settings {
+            volume: 30,
+            brightness: 40,
+            auto_brightness_enabled: false,
+            pet: PetStats {
+                hunger: 1,
+                happiness: 2,
+                energy: 3,
+                condition: Condition::Sleeping,
+                age_ms: 1 << 40,
+                neglect_ms: 4000,
+            },
+            flappy_high_score: 77,
+            screensaver_timeout_s: 0,
+            light_calibration: LightCalibration { dark_raw: 300, bright_raw: 3000 },
+            saved_at_s: 1_700_000_000,
+            theme: ThemeId::HighContrast,
+            lifetime_uptime_ms: 5 << 33,
+            sessions: 9,
+            time_of_day_s: 3600,
+        }
+    }
+
+    // What a blob written by `version` holds of `custom`, the rest are defaults
+    fn as_of_version(version: u8) -> Settings {
+        let (mut settings, defaults) = (custom(), Settings::default());
+        if version < 2 {
+            settings.flappy_high_score = defaults.flappy_high_score;
+        }
+        if version < 3 {
+            settings.screensaver_timeout_s = defaults.screensaver_timeout_s;
+        }
+        if version < 4 {
+            settings.light_calibration = defaults.light_calibration;
+        }
+        if version < 5 {
+            settings.saved_at_s = defaults.saved_at_s;
+        }
+        if version < 6 {
+            settings.theme = defaults.theme;
+        }
+        if version < 7 {
+            settings.lifetime_uptime_ms = defaults.lifetime_uptime_ms;
+            settings.sessions = defaults.sessions;
+        }
+        if version < 8 {
+            settings.time_of_day_s = defaults.time_of_day_s;
+        }
+        if version < 9 {
+            settings.pet.neglect_ms = defaults.pet.neglect_ms;
+        }
+        settings
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = custom().to_bytes();
+        assert_eq!(bytes[0], SETTINGS_VERSION);
+        assert_eq!(Settings::from_bytes(&bytes), Some(custom()));
+        assert_eq!(Settings::from_bytes(&Settings::default().to_bytes()), Some(Settings::default()));
+    }
+
+    #[test]
+    fn every_older_version_is_read() {
+        let sizes = [
+            SETTINGS_V1_SIZE,
+            SETTINGS_V2_SIZE,
+            SETTINGS_V3_SIZE,
+            SETTINGS_V4_SIZE,
+            SETTINGS_V5_SIZE,
+            SETTINGS_V6_SIZE,
+            SETTINGS_V7_SIZE,
+            SETTINGS_V8_SIZE,
+            SETTINGS_V9_SIZE,
+        ];
+        for (version, size) in (1..=SETTINGS_VERSION).zip(sizes) {
+            let mut blob = custom().to_bytes();
+            blob[0] = version;
+            assert_eq!(Settings::from_bytes(&blob[..size]), Some(as_of_version(version)), "version {version}");
+            // anything a newer version appended past it is ignored
+            assert_eq!(Settings::from_bytes(&blob), Some(as_of_version(version)), "padded version {version}");
+        }
+    }
+
+    #[test]
+    fn newer_blobs_keep_the_known_fields() {
+        let mut blob = [0xaa; SETTINGS_SIZE + 8];
+        blob[..SETTINGS_SIZE].copy_from_slice(&custom().to_bytes());
+        blob[0] = SETTINGS_VERSION + 1;
+        assert_eq!(Settings::from_bytes(&blob), Some(custom()));
+    }
+
+    #[test]
+    fn unreadable_blobs() {
+        let bytes = custom().to_bytes();
+        assert_eq!(Settings::from_bytes(&bytes[..SETTINGS_V1_SIZE - 1]), None);
+        assert_eq!(Settings::from_bytes(&[0; SETTINGS_SIZE]), None);
+        // a version whose fields were cut short falls back like the older version
+        assert_eq!(Settings::from_bytes(&bytes[..SETTINGS_V9_SIZE - 1]), Some(as_of_version(8)));
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let mut bytes = custom().to_bytes();
+        bytes[1] = 255;
+        bytes[2] = 255;
+        bytes[47..51].copy_from_slice(&(SECONDS_PER_DAY + 5).to_le_bytes());
+        let settings = Settings::from_bytes(&bytes).unwrap();
+        assert_eq!((settings.volume, settings.brightness), (MAX_VOLUME, 100));
+        assert_eq!(settings.time_of_day_s, 5);
+    }
+}
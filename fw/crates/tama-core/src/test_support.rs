@@ -0,0 +1,146 @@
+//! Headless rendering for the tests: scenes and the engine draw into a `MockDisplay`
+//! instead of the screen, so the tests can look at the pixels
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use embedded_graphics::{mock_display::MockDisplay, prelude::Point};
+use rand::{SeedableRng, rngs::SmallRng};
+
+use crate::{
+    buzzer::{MelodyPlayer, RecordingBuzzer},
+    clock::DayClock,
+    consts::ColorType,
+    engine::{Context, Engine},
+    input::Input,
+    log_buffer::LogBuffer,
+    output::Output,
+    palette::Theme,
+    pet::Pet,
+    scenes::Scene,
+    settings::{Settings, SettingsStore},
+    toast::ToastQueue,
+    uptime::Uptime,
+};
+
+/// Only the top left 64x64 pixels of the screen are kept, anything drawn past them is dropped.
+/// Scenes clear the screen and then draw over it, so drawing over a pixel twice is fine too.
+pub(crate) fn mock_display() -> MockDisplay<ColorType> {
+    let mut display = MockDisplay::new();
+    display.set_allow_out_of_bounds_drawing(true);
+    display.set_allow_overdraw(true);
+    display
+}
+
+pub(crate) fn render_scene(scene: &impl Scene, theme: &Theme) -> MockDisplay<ColorType> {
+    let mut display = mock_display();
+    scene
+        .draw(&mut display, theme)
+        .unwrap_or_else(|_| panic!("the scene couldn't be drawn"));
+    display
+}
+
+/// A fresh engine on the boot self-test, with a buzzer that records what it played
+pub(crate) fn engine() -> (Engine, RecordingBuzzer) {
+    engine_with_store(SharedSettingsStore::default())
+}
+
+/// Same as `engine`, with the settings loaded from and saved to `store`
+pub(crate) fn engine_with_store(store: SharedSettingsStore) -> (Engine, RecordingBuzzer) {
+    let buzzer = RecordingBuzzer::new();
+    let engine = Engine::with_settings_store(Box::new(buzzer.clone()), Box::new(store)).with_seed(1);
+    (engine, buzzer)
+}
+
+/// Remembers every save, clones share them: keep one and hand the other to the engine
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedSettingsStore {
+    saves: Rc<RefCell<Vec<Settings>>>,
+}
+
+impl SharedSettingsStore {
+    /// Loads `settings` as if a previous run saved them
+    pub(crate) fn with(settings: Settings) -> Self {
+        let store = Self::default();
+        store.saves.borrow_mut().push(settings);
+        store
+    }
+
+    /// Oldest first
+    pub(crate) fn saves(&self) -> Vec<Settings> {
+        self.saves.borrow().clone()
+    }
+}
+
+impl SettingsStore for SharedSettingsStore {
+    fn load(&mut self) -> Option<Settings> {
+        self.saves.borrow().last().copied()
+    }
+
+    fn save(&mut self, settings: &Settings) {
+        self.saves.borrow_mut().push(*settings);
+    }
+}
+
+pub(crate) fn render_engine(engine: &mut Engine) -> MockDisplay<ColorType> {
+    let mut display = mock_display();
+    engine
+        .render(&mut display)
+        .unwrap_or_else(|_| panic!("the engine couldn't render"));
+    display
+}
+
+/// Pixels drawn in `color`, out of the 64x64 kept
+pub(crate) fn count_pixels(display: &MockDisplay<ColorType>, color: ColorType) -> usize {
+    (0..64)
+        .flat_map(|y| (0..64).map(move |x| Point::new(x, y)))
+        .filter(|&point| display.get_pixel(point) == Some(color))
+        .count()
+}
+
+/// Everything a `Context` borrows, for updating a scene without an engine around it
+pub(crate) struct ContextParts {
+    pub rng: SmallRng,
+    pub input: Input,
+    pub buzzer: RecordingBuzzer,
+    pub melody_player: MelodyPlayer,
+    pub pet: Pet,
+    pub settings: Settings,
+    pub logs: LogBuffer,
+    pub uptime: Uptime,
+    pub toasts: ToastQueue,
+    pub day_clock: DayClock,
+}
+
+impl ContextParts {
+    pub(crate) fn new() -> Self {
+        Self {
+            rng: SmallRng::seed_from_u64(1),
+            input: Input::new(),
+            buzzer: RecordingBuzzer::new(),
+            melody_player: MelodyPlayer::new(),
+            pet: Pet::new(),
+            settings: Settings::default(),
+            logs: LogBuffer::new(),
+            uptime: Uptime::default(),
+            toasts: ToastQueue::new(),
+            day_clock: DayClock::default(),
+        }
+    }
+
+    pub(crate) fn context(&mut self, dt_ms: u32) -> Context<'_> {
+        Context {
+            rng: &mut self.rng,
+            input: &mut self.input,
+            output: Output::new(&self.buzzer, &mut self.melody_player),
+            pet: &mut self.pet,
+            settings: &mut self.settings,
+            logs: &self.logs,
+            uptime: &self.uptime,
+            toasts: &mut self.toasts,
+            day_clock: &mut self.day_clock,
+            date_seed: 0,
+            dt_ms,
+        }
+    }
+}
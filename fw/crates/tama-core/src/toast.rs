@@ -0,0 +1,197 @@
+use embedded_graphics::{
+    Drawable as _,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    prelude::{DrawTarget, Point, Primitive, Size},
+    primitives::{PrimitiveStyleBuilder, Rectangle},
+    text::Text,
+};
+use heapless::{Deque, String};
+
+use crate::{consts, gfx, palette::Theme};
+
+/// Longer messages are cut off
+pub const TOAST_MESSAGE_MAX_LEN: usize = 64;
+/// Toasts waiting behind the one shown, a push past that drops the oldest waiting one
+pub const TOAST_QUEUE_LEN: usize = 4;
+
+// FONT_6X10 glyphs, the banner fits this many lines before the rest is dropped
+const CHAR_WIDTH: u32 = 6;
+const LINE_HEIGHT: u32 = 11;
+const MAX_LINES: usize = 3;
+const MARGIN: u32 = 10;
+const PADDING: u32 = 4;
+const LINE_CHARS: usize = ((consts::WIDTH - 2 * MARGIN - 2 * PADDING) / CHAR_WIDTH) as usize;
+
+struct Toast {
+    message: String<TOAST_MESSAGE_MAX_LEN>,
+    /// counts down once the toast is shown
+    remaining_ms: u32,
+}
+
+/// Short messages ("Saved!") the engine shows one at a time in a banner at the bottom,
+/// over whatever the scenes drew. Each one stays up for its duration, then the next is shown.
+pub struct ToastQueue {
+    /// the shown one is first
+    toasts: Deque<Toast, { TOAST_QUEUE_LEN + 1 }>,
+    /// the shown toast changed since the last frame was drawn
+    changed: bool,
+}
+
+impl ToastQueue {
+    pub const fn new() -> Self {
+        Self { toasts: Deque::new(), changed: false }
+    }
+
+    pub fn push(&mut self, message: &str, duration_ms: u32) {
+        let mut text = String::new();
+        for c in message.chars() {
+            if text.push(c).is_err() {
+                break;
+            }
+        }
+        if self.toasts.is_full() {
+            // the shown one stays, the oldest waiting one behind it goes
+            if let Some(shown) = self.toasts.pop_front() {
+                self.toasts.pop_front();
+                let _ = self.toasts.push_front(shown);
+            }
+        }
+        self.changed |= self.toasts.is_empty();
+        let _ = self.toasts.push_back(Toast { message: text, remaining_ms: duration_ms });
+    }
+
+    /// Count the shown toast down by `dt_ms`, it's replaced by the next one once it ran out
+    pub fn update(&mut self, dt_ms: u32) {
+        let Some(shown) = self.toasts.front_mut() else {
+            return;
+        };
+        shown.remaining_ms = shown.remaining_ms.saturating_sub(dt_ms);
+        if shown.remaining_ms == 0 {
+            self.toasts.pop_front();
+            self.changed = true;
+        }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.toasts.front().map(|toast| toast.message.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.changed |= !self.toasts.is_empty();
+        self.toasts.clear();
+    }
+
+    /// A toast appeared or went away, the frame has to be drawn again
+    pub fn needs_redraw(&self) -> bool {
+        self.changed
+    }
+
+    pub(crate) fn mark_drawn(&mut self) {
+        self.changed = false;
+    }
+
+    pub fn draw<D>(&self, target: &mut D, theme: &Theme) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = consts::ColorType>,
+    {
+        let Some(message) = self.current() else {
+            return Ok(());
+        };
+        let lines = gfx::wrap_text(message, LINE_CHARS);
+        let line_count = lines.len().min(MAX_LINES) as u32;
+        let height = line_count * LINE_HEIGHT + 2 * PADDING;
+        let top_left = Point::new(MARGIN as i32, (consts::HEIGHT - MARGIN - height) as i32);
+        Rectangle::new(top_left, Size::new(consts::WIDTH - 2 * MARGIN, height))
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(theme.background)
+                    .stroke_color(theme.accent)
+                    .stroke_width(1)
+                    .build(),
+            )
+            .draw(target)?;
+
+        let style = MonoTextStyle::new(&FONT_6X10, theme.foreground);
+        let mut baseline = top_left + Point::new(PADDING as i32, PADDING as i32 + 8);
+        for line in lines.iter().take(MAX_LINES) {
+            Text::new(line, baseline, style).draw(target)?;
+            baseline.y += LINE_HEIGHT as i32;
+        }
+        Ok(())
+    }
+}
+
+impl Default for ToastQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    // Messages in the order they're shown, running each one out
+    fn shown_in_order(queue: &mut ToastQueue) -> Vec<String<TOAST_MESSAGE_MAX_LEN>> {
+        let mut shown = Vec::new();
+        while let Some(message) = queue.current() {
+            shown.push(String::try_from(message).unwrap());
+            queue.update(u32::MAX);
+        }
+        shown
+    }
+
+    #[test]
+    fn overflow_drops_the_oldest_waiting_toast() {
+        let mut queue = ToastQueue::new();
+        for message in ["shown", "a", "b", "c", "d", "e"] {
+            queue.push(message, 1000);
+        }
+        assert_eq!(shown_in_order(&mut queue), ["shown", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn toasts_expire_one_after_another() {
+        let mut queue = ToastQueue::new();
+        queue.push("first", 1000);
+        queue.push("second", 500);
+        queue.update(999);
+        assert_eq!(queue.current(), Some("first"));
+        // the next one only counts down once shown
+        queue.update(1);
+        assert_eq!(queue.current(), Some("second"));
+        queue.update(500);
+        assert_eq!(queue.current(), None);
+    }
+
+    #[test]
+    fn redraws_only_when_the_shown_toast_changes() {
+        let mut queue = ToastQueue::new();
+        assert!(!queue.needs_redraw());
+        queue.push("first", 1000);
+        assert!(queue.needs_redraw());
+        queue.mark_drawn();
+
+        queue.push("second", 1000);
+        queue.update(10);
+        assert!(!queue.needs_redraw());
+        queue.update(990);
+        assert!(queue.needs_redraw());
+        queue.mark_drawn();
+
+        queue.clear();
+        assert!(queue.needs_redraw());
+        queue.mark_drawn();
+        queue.clear();
+        assert!(!queue.needs_redraw());
+    }
+
+    #[test]
+    fn long_messages_are_cut_off() {
+        let mut queue = ToastQueue::new();
+        queue.push(&"ż".repeat(TOAST_MESSAGE_MAX_LEN), 1000);
+        assert_eq!(queue.current().unwrap().chars().count(), TOAST_MESSAGE_MAX_LEN / 2);
+    }
+}
@@ -0,0 +1,102 @@
+use core::fmt::Write as _;
+
+use heapless::String;
+
+/// Fits any `format_duration` result, even for `u64::MAX` ms
+pub const DURATION_STRING_LEN: usize = 24;
+
+const MS_PER_SECOND: u64 = 1000;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Time the engine has been updated for, this session and summed over all sessions.
+/// Counts the `dt_ms` of every update, so time spent waiting for the next frame is
+/// included and time powered off isn't.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uptime {
+    session_ms: u64,
+    /// lifetime total saved by the earlier sessions
+    earlier_sessions_ms: u64,
+}
+
+impl Uptime {
+    pub fn new(earlier_sessions_ms: u64) -> Self {
+        Self { session_ms: 0, earlier_sessions_ms }
+    }
+
+    pub fn advance(&mut self, dt_ms: u32) {
+        self.session_ms = self.session_ms.saturating_add(dt_ms as u64);
+    }
+
+    /// Since boot
+    pub fn session_ms(&self) -> u64 {
+        self.session_ms
+    }
+
+    /// Since the device was first turned on, what `Settings::lifetime_uptime_ms` is saved as
+    pub fn lifetime_ms(&self) -> u64 {
+        self.earlier_sessions_ms.saturating_add(self.session_ms)
+    }
+}
+
+/// "04:05:06", with the days in front once there are any: "3d 04:05:06"
+pub fn format_duration(ms: u64) -> String<DURATION_STRING_LEN> {
+    let seconds = ms / MS_PER_SECOND;
+    let (days, seconds) = (seconds / SECONDS_PER_DAY, seconds % SECONDS_PER_DAY);
+    let mut text = String::new();
+    if days > 0 {
+        let _ = write!(text, "{}d ", days);
+    }
+    let _ = write!(text, "{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{SharedSettingsStore, engine_with_store};
+
+    #[test]
+    fn durations_are_hours_minutes_seconds() {
+        assert_eq!(format_duration(0), "00:00:00");
+        assert_eq!(format_duration(999), "00:00:00");
+        assert_eq!(format_duration((4 * 3600 + 5 * 60 + 6) * 1000), "04:05:06");
+        assert_eq!(format_duration(SECONDS_PER_DAY * 1000 - 1), "23:59:59");
+    }
+
+    #[test]
+    fn days_go_in_front() {
+        let ms = (3 * SECONDS_PER_DAY + 4 * 3600 + 5 * 60 + 6) * 1000;
+        assert_eq!(format_duration(ms), "3d 04:05:06");
+        assert_eq!(format_duration(u64::MAX), "213503982334d 14:25:51");
+    }
+
+    #[test]
+    fn lifetime_adds_up_the_sessions() {
+        let mut uptime = Uptime::new(10_000);
+        uptime.advance(16);
+        uptime.advance(u32::MAX);
+        assert_eq!(uptime.session_ms(), 16 + u32::MAX as u64);
+        assert_eq!(uptime.lifetime_ms(), 10_000 + 16 + u32::MAX as u64);
+
+        let mut uptime = Uptime::new(u64::MAX - 1);
+        uptime.advance(16);
+        assert_eq!(uptime.lifetime_ms(), u64::MAX);
+    }
+
+    #[test]
+    fn lifetime_is_kept_across_boots() {
+        let store = SharedSettingsStore::default();
+        let (mut engine, _buzzer) = engine_with_store(store.clone());
+        engine.update(1500);
+        engine.shutdown();
+
+        let (mut engine, _buzzer) = engine_with_store(store);
+        assert_eq!(engine.settings().sessions, 2);
+        assert_eq!(engine.lifetime_uptime_ms(), 1500);
+        engine.update(61_000);
+        assert_eq!(engine.uptime_ms(), 61_000);
+        assert_eq!(engine.uptime_secs(), 61);
+        assert_eq!(engine.uptime_string(), "00:01:01");
+        assert_eq!(engine.lifetime_uptime_ms(), 62_500);
+    }
+}
@@ -1,21 +1,27 @@
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::thread;
 use std::time::Duration;
-use rodio::{OutputStream, OutputStreamHandle, Source};
-use tama_core::buzzer::BuzzerTrait;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use tama_core::buzzer::{BuzzerTrait, MAX_VOLUME};
+
+// Square wave amplitude at full volume
+const MAX_AMPLITUDE: f32 = 0.15;
 
 // Square wave generator
 struct SquareWave {
     frequency: f32,
+    amplitude: f32,
     sample_rate: u32,
     num_samples: usize,
     current_sample: usize,
 }
 
 impl SquareWave {
-    fn new(frequency: f32, sample_rate: u32) -> Self {
+    fn new(frequency: f32, amplitude: f32, sample_rate: u32) -> Self {
         Self {
             frequency,
+            amplitude,
             sample_rate,
             num_samples: 0,
             current_sample: 0,
@@ -43,9 +49,9 @@ impl Iterator for SquareWave {
         
         // Square wave: high for first half of cycle, low for second half
         if cycle_position < 0.5 {
-            Some(0.15)  // Amplitude
+            Some(self.amplitude)
         } else {
-            Some(-0.15)
+            Some(-self.amplitude)
         }
     }
 }
@@ -79,15 +85,22 @@ impl Source for SquareWave {
 pub struct BuzzerCommand {
     pub frequency_hz: u32,
     pub duration_ms: u32,
+    pub amplitude: f32,
+}
+
+enum BuzzerMessage {
+    Beep(BuzzerCommand),
+    Stop,
 }
 
 pub struct DesktopBuzzer {
-    command_tx: Sender<BuzzerCommand>,
+    command_tx: Sender<BuzzerMessage>,
+    volume: AtomicU8,
 }
 
 impl DesktopBuzzer {
     pub fn new() -> Self {
-        let (tx, rx) = channel::<BuzzerCommand>();
+        let (tx, rx) = channel::<BuzzerMessage>();
         
         // Spawn a thread to handle audio playback
         thread::spawn(move || {
@@ -96,44 +109,68 @@ impl DesktopBuzzer {
         
         Self {
             command_tx: tx,
+            volume: AtomicU8::new(MAX_VOLUME),
         }
     }
 }
 
 impl BuzzerTrait for DesktopBuzzer {
     fn beep(&self, frequency_hz: u32, duration_ms: u32) {
+        let amplitude = MAX_AMPLITUDE * self.volume.load(Ordering::Relaxed) as f32 / MAX_VOLUME as f32;
+
         // Send the beep command asynchronously, ignore errors if channel is closed
-        let _ = self.command_tx.send(BuzzerCommand {
+        let _ = self.command_tx.send(BuzzerMessage::Beep(BuzzerCommand {
             frequency_hz,
             duration_ms,
-        });
+            amplitude,
+        }));
+    }
+
+    fn stop(&self) {
+        let _ = self.command_tx.send(BuzzerMessage::Stop);
+    }
+
+    fn set_volume(&self, level: u8) {
+        self.volume.store(level.min(MAX_VOLUME), Ordering::Relaxed);
     }
 }
 
-fn buzzer_thread(rx: Receiver<BuzzerCommand>) {
+fn buzzer_thread(rx: Receiver<BuzzerMessage>) {
     // Initialize audio output once for the thread
     let Ok((_stream, stream_handle)) = OutputStream::try_default() else {
         eprintln!("Failed to initialize audio output for buzzer");
         return;
     };
-    
-    // Process beep commands from the channel
-    while let Ok(cmd) = rx.recv() {
-        play_beep(&stream_handle, cmd.frequency_hz, cmd.duration_ms);
+    let Some(mut sink) = new_sink(&stream_handle) else {
+        return;
+    };
+
+    // Beeps are queued on the sink and play back to back
+    while let Ok(message) = rx.recv() {
+        match message {
+            BuzzerMessage::Beep(cmd) => {
+                let sample_rate = 48000; // Standard audio sample rate
+                let source = SquareWave::new(cmd.frequency_hz as f32, cmd.amplitude, sample_rate)
+                    .take_duration(Duration::from_millis(cmd.duration_ms as u64));
+                sink.append(source);
+            }
+            BuzzerMessage::Stop => {
+                // dropping the old sink cuts off the tone it's playing and everything queued
+                let Some(new) = new_sink(&stream_handle) else {
+                    return;
+                };
+                sink = new;
+            }
+        }
     }
 }
 
-fn play_beep(stream_handle: &OutputStreamHandle, frequency_hz: u32, duration_ms: u32) {
-    let sample_rate = 48000; // Standard audio sample rate
-    let source = SquareWave::new(frequency_hz as f32, sample_rate)
-        .take_duration(Duration::from_millis(duration_ms as u64));
-    
-    // Play the sound (non-blocking)
-    if let Err(e) = stream_handle.play_raw(source.convert_samples()) {
-        eprintln!("Failed to play beep: {}", e);
-        return;
+fn new_sink(stream_handle: &OutputStreamHandle) -> Option<Sink> {
+    match Sink::try_new(stream_handle) {
+        Ok(sink) => Some(sink),
+        Err(e) => {
+            eprintln!("Failed to create buzzer sink: {}", e);
+            None
+        }
     }
-    
-    // Sleep to allow the sound to complete before processing next command
-    thread::sleep(Duration::from_millis(duration_ms as u64));
 }
@@ -0,0 +1,62 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::RgbColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use tama_core::consts::ColorType;
+use tama_core::engine::FrameStats;
+
+// weight of the newest frame in the average cost
+const COST_SMOOTHING: f32 = 0.1;
+const PANEL: Rectangle = Rectangle::new(Point::new(0, 0), Size::new(110, 24));
+
+/// FPS and the time `update` plus `render` take, in the top left corner. Toggled with F1.
+/// Drawn over the frame after the engine rendered, so it's redrawn every frame while shown.
+pub struct FpsOverlay {
+    visible: bool,
+    /// moving average of update+render in ms, None until the first frame
+    cost_ms: Option<f32>,
+}
+
+impl FpsOverlay {
+    pub fn new() -> Self {
+        Self { visible: false, cost_ms: None }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Once per frame, after `update` and `render`
+    pub fn record(&mut self, stats: &FrameStats) {
+        let cost_ms = (stats.update_us() + stats.render_us()) as f32 / 1000.0;
+        self.cost_ms = Some(match self.cost_ms {
+            Some(average) => average + (cost_ms - average) * COST_SMOOTHING,
+            None => cost_ms,
+        });
+    }
+
+    pub fn draw<D>(&self, target: &mut D, stats: &FrameStats) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = ColorType>,
+    {
+        if !self.visible {
+            return Ok(());
+        }
+        PANEL.into_styled(PrimitiveStyle::with_fill(ColorType::BLACK)).draw(target)?;
+
+        let style = MonoTextStyle::new(&FONT_6X10, ColorType::GREEN);
+        let fps = stats.fps_x100();
+        let fps_line = format!("{}.{:02} FPS", fps / 100, fps % 100);
+        let cost_line = format!("frame {:.2} ms", self.cost_ms.unwrap_or(0.0));
+        Text::with_baseline(&fps_line, Point::new(2, 2), style, Baseline::Top).draw(target)?;
+        Text::with_baseline(&cost_line, Point::new(2, 13), style, Baseline::Top).draw(target)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use embedded_graphics::prelude::Size;
+use embedded_graphics_simulator::SimulatorDisplay;
+use log::{LevelFilter, Metadata, Record};
+use tama_core::consts;
+use tama_core::engine::Engine;
+
+use crate::input_feed::InputFeed;
+use crate::mock_hw_tui::MockSensorState;
+use crate::sensor_script::SensorScript;
+use crate::{RECORD_EVERY_FRAMES, TARGET_FPS};
+
+/// Frames run without `--frames` or an input replay, 10 s of simulated time
+pub const DEFAULT_FRAMES: u32 = 10 * TARGET_FPS;
+
+static ERRORS_LOGGED: AtomicUsize = AtomicUsize::new(0);
+
+// Prints to stderr instead of the TUI and counts the errors for the exit status
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        crate::log_capture::capture(record);
+        if record.level() == log::Level::Error {
+            ERRORS_LOGGED.fetch_add(1, Ordering::Relaxed);
+        }
+        eprintln!("[{:<5}] {}: {}", record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// `--headless`: no window, TUI or audio. The engine renders into an in-memory display for
+/// `frames` frames, or until the input replay ends when there's one and no `frames`.
+/// Time is simulated at `TARGET_FPS`, so runs don't depend on how fast the machine is.
+/// Fails if anything logged an error, for smoke tests in CI.
+pub fn run(
+    frames: Option<u32>,
+    record_dir: Option<&Path>,
+    mut script: Option<SensorScript>,
+    mut input: InputFeed,
+) -> anyhow::Result<()> {
+    log::set_logger(&StderrLogger).map(|()| log::set_max_level(LevelFilter::Info))?;
+    log::info!("Tama Desktop Simulator started headless");
+
+    let mut display = SimulatorDisplay::<consts::ColorType>::new(Size::new(consts::WIDTH, consts::HEIGHT));
+    let mut engine = Engine::new();
+    let mut sensors = MockSensorState::default();
+    let until_replay_ends = frames.is_none() && input.is_replaying();
+    let frames = frames.unwrap_or(if until_replay_ends { u32::MAX } else { DEFAULT_FRAMES });
+    let dt_ms = 1000 / TARGET_FPS;
+
+    let mut frame_count = 0;
+    while frame_count < frames {
+        if until_replay_ends && !input.is_replaying() {
+            break;
+        }
+        let now_ms = frame_count * dt_ms;
+        input.start_frame(now_ms);
+
+        if let Some(active_script) = &mut script {
+            active_script.advance(now_ms, |sensor, value| sensors.set(sensor, value));
+            if active_script.is_finished() {
+                log::info!("Sensor script finished");
+                script = None;
+            }
+        }
+        crate::generate_mock_hw_data(&mut engine, &mut input, &sensors);
+        let update_ms = input.tick(&mut engine, dt_ms);
+        crate::log_capture::drain_into(engine.logs_mut());
+        if let Some(update_ms) = update_ms {
+            engine.update(update_ms);
+        }
+        if engine.power_off_requested() {
+            log::info!("Battery empty, simulator powering off");
+            break;
+        }
+        if engine.needs_redraw() {
+            // scene errors are logged by the engine, the display can't fail
+            let _ = engine.render(&mut display);
+        }
+
+        if let Some(dir) = record_dir
+            && frame_count.is_multiple_of(RECORD_EVERY_FRAMES)
+        {
+            let path = dir.join(format!("frame-{:06}.png", frame_count / RECORD_EVERY_FRAMES));
+            if let Err(e) = crate::screenshot::save_png(&display, &path) {
+                log::error!("{:#}", e);
+            }
+        }
+        frame_count += 1;
+    }
+
+    engine.shutdown();
+    log::info!("Ran {} frames", frame_count);
+
+    let errors = ERRORS_LOGGED.load(Ordering::Relaxed);
+    if errors > 0 {
+        anyhow::bail!("{} errors logged", errors);
+    }
+    Ok(())
+}
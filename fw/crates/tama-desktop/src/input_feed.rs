@@ -0,0 +1,108 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write as _};
+use std::path::Path;
+
+use anyhow::Context as _;
+use tama_core::engine::Engine;
+use tama_core::input::{Button, InputPlayer, InputRecorder, SensorType, TimedInputEvent};
+
+type Sink = Box<dyn FnMut(TimedInputEvent)>;
+
+/// Where the engine's input comes from: the window and the TUI, optionally
+/// recorded to a file (`--record-input`), or a recording played back (`--replay-input`)
+pub struct InputFeed {
+    recorder: Option<InputRecorder<Sink>>,
+    player: Option<InputPlayer>,
+    /// time of the current frame, stamped on recorded events
+    time_ms: u32,
+}
+
+impl InputFeed {
+    pub fn live() -> Self {
+        Self {
+            recorder: None,
+            player: None,
+            time_ms: 0,
+        }
+    }
+
+    /// Every event is written to `path` as a line, see `TimedInputEvent` for the format
+    pub fn recording(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create input recording {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let sink: Sink = Box::new(move |event| {
+            let _ = writeln!(writer, "{}", event);
+        });
+        Ok(Self {
+            recorder: Some(InputRecorder::new(sink)),
+            ..Self::live()
+        })
+    }
+
+    /// Live input is ignored while the recording plays
+    pub fn replaying(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read input recording {}", path.display()))?;
+        let mut events = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let event = TimedInputEvent::parse(line)
+                .with_context(|| format!("invalid input event on line {} of {}", i + 1, path.display()))?;
+            events.push(event);
+        }
+        Ok(Self {
+            player: Some(InputPlayer::new(events)),
+            ..Self::live()
+        })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.player.is_some()
+    }
+
+    pub fn start_frame(&mut self, time_ms: u32) {
+        self.time_ms = time_ms;
+    }
+
+    /// See `Input::update_button_level`
+    pub fn update_button_level(&mut self, engine: &mut Engine, button: Button, pressed: bool) {
+        if self.is_replaying() {
+            return;
+        }
+        match &mut self.recorder {
+            Some(recorder) => recorder.update_button_level(engine.input_mut(), button, pressed, self.time_ms),
+            None => engine.input_mut().update_button_level(button, pressed),
+        }
+    }
+
+    pub fn update_sensor(&mut self, engine: &mut Engine, sensor: SensorType, value: f32) {
+        if self.is_replaying() {
+            return;
+        }
+        match &mut self.recorder {
+            Some(recorder) => recorder.update_sensor(engine.input_mut(), sensor, value, self.time_ms),
+            None => engine.input_mut().update_sensor(sensor, value, self.time_ms),
+        }
+    }
+
+    /// Ends the frame's input. Returns how far the engine should be updated, `None` when
+    /// a replay has no frame due yet.
+    pub fn tick(&mut self, engine: &mut Engine, dt_ms: u32) -> Option<u32> {
+        if let Some(player) = &mut self.player {
+            let ticked_ms = player.advance(engine.input_mut(), self.time_ms);
+            if player.is_finished() {
+                log::info!("Input replay finished, back to live input");
+                self.player = None;
+            }
+            return ticked_ms;
+        }
+        match &mut self.recorder {
+            Some(recorder) => recorder.tick(engine.input_mut(), dt_ms, self.time_ms),
+            None => engine.input_mut().tick(dt_ms),
+        }
+        Some(dt_ms)
+    }
+}
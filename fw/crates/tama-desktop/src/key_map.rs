@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context as _};
+use embedded_graphics_simulator::sdl2::Keycode;
+use tama_core::input::Button;
+
+// handled by the window itself, can't be bound to buttons
+const RESERVED_KEYS: [Keycode; 3] = [Keycode::Escape, Keycode::F1, Keycode::F12];
+
+/// Which keys stand in for the device buttons. The default is WASD for the d-pad and J/K
+/// for A/B, `--keymap <path>` loads one from a file of `key = button` lines.
+/// Keys are SDL key names (`W`, `Space`, `Left`...), buttons are named as in `Button::name`.
+/// Empty lines and lines starting with `#` are skipped, a key can be bound once but a
+/// button to several keys.
+pub struct KeyMap {
+    buttons: HashMap<Keycode, Button>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let buttons = [
+            (Keycode::W, Button::Up),
+            (Keycode::A, Button::Left),
+            (Keycode::S, Button::Down),
+            (Keycode::D, Button::Right),
+            (Keycode::J, Button::A),
+            (Keycode::K, Button::B),
+        ];
+        Self { buttons: buttons.into_iter().collect() }
+    }
+}
+
+impl KeyMap {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read key map {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid key map {}", path.display()))
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut buttons = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, button) = parse_binding(line).with_context(|| format!("line {}", i + 1))?;
+            if buttons.insert(key, button).is_some() {
+                bail!("line {}: `{}` is bound twice", i + 1, key.name());
+            }
+        }
+        Ok(Self { buttons })
+    }
+
+    pub fn button(&self, key: Keycode) -> Option<Button> {
+        self.buttons.get(&key).copied()
+    }
+
+    /// Any of the keys bound to `button` is held
+    pub fn is_held(&self, button: Button, keys_held: &HashSet<Keycode>) -> bool {
+        keys_held.iter().any(|&key| self.button(key) == Some(button))
+    }
+}
+
+fn parse_binding(line: &str) -> anyhow::Result<(Keycode, Button)> {
+    let Some((key, button)) = line.split_once('=') else {
+        bail!("expected `key = button`");
+    };
+    let (key, button) = (key.trim(), button.trim());
+
+    let key = Keycode::from_name(key).ok_or_else(|| anyhow!("unknown key `{}`", key))?;
+    if RESERVED_KEYS.contains(&key) {
+        bail!("`{}` is used by the simulator", key.name());
+    }
+    let button = Button::from_name(button).ok_or_else(|| anyhow!("unknown button `{}`", button))?;
+    Ok((key, button))
+}
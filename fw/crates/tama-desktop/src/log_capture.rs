@@ -0,0 +1,18 @@
+use std::sync::Mutex;
+
+use tama_core::log_buffer::LogBuffer;
+
+// Records logged since the last frame, moved over to the engine by `drain_into`
+static CAPTURED: Mutex<LogBuffer> = Mutex::new(LogBuffer::new());
+
+pub fn capture(record: &log::Record) {
+    if let Ok(mut captured) = CAPTURED.lock() {
+        captured.capture(record);
+    }
+}
+
+pub fn drain_into(logs: &mut LogBuffer) {
+    if let Ok(mut captured) = CAPTURED.lock() {
+        captured.drain_into(logs);
+    }
+}
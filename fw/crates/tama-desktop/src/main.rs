@@ -1,105 +1,214 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use embedded_graphics::prelude::Size;
 use embedded_graphics_simulator::sdl2::Keycode;
 use embedded_graphics_simulator::{
     OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
 };
+use tama_core::clock::Clock;
 use tama_core::consts;
 use tama_core::engine::Engine;
-use tama_core::input::{Button, ButtonState};
+use tama_core::input::Button;
 
 use tama_core::input::SensorType;
 
 mod buzzer;
+mod fps_overlay;
+mod headless;
+mod input_feed;
+mod key_map;
+mod log_capture;
 mod mock_hw_tui;
+mod screenshot;
+mod sensor_script;
+
+const TARGET_FPS: u32 = 30;
+// with `--record`, one frame in this many is saved
+const RECORD_EVERY_FRAMES: u32 = 3;
 
 fn handle_simulator_events(
     engine: &mut Engine, 
+    input: &mut input_feed::InputFeed,
     window: &mut Window, 
-    button_pressed: &mut HashMap<Button, bool>
+    key_map: &key_map::KeyMap,
+    keys_held: &mut HashSet<Keycode>,
+    fps_overlay: &mut fps_overlay::FpsOverlay,
+    screenshot_requested: &mut bool,
 ) -> bool {
-    // there's a 100% a better way to handle input but idk, this is just for testing
-        for (button, pressed) in button_pressed.iter() {
-            engine.input_mut().set_button(
-                *button,
-                if *pressed {
-                    ButtonState::Pressed
-                } else {
-                    ButtonState::Released
-                },
-            );
-        }
-
-        for event in window.events() {
-            match event {
-                SimulatorEvent::Quit => {
+    for event in window.events() {
+        match event {
+            SimulatorEvent::Quit => {
+                return false;
+            }
+            SimulatorEvent::KeyDown { keycode, repeat: false, .. } => match keycode {
+                Keycode::Escape => {
+                    log::info!("Escape pressed, exiting simulator.");
                     return false;
                 }
-                SimulatorEvent::KeyDown { keycode, repeat: false, .. } => {
-                    let button = match keycode {
-                        Keycode::W => Some(Button::Up),
-                        Keycode::A => Some(Button::Left),
-                        Keycode::S => Some(Button::Down),
-                        Keycode::D => Some(Button::Right),
-                        Keycode::J => Some(Button::A),
-                        Keycode::K => Some(Button::B),
-                        Keycode::Escape => {
-                            log::info!("Escape pressed, exiting simulator.");
-                            return false;
-                        }
-                        _ => None,
-                    };
-
-                    if let Some(button) = button {
-                        log::debug!("Button pressed: {:?}", button);
-                        engine
-                            .input_mut()
-                            .set_button(button, ButtonState::JustPressed);
-                        button_pressed.insert(button, true);
+                Keycode::F1 => {
+                    fps_overlay.toggle();
+                    // the frame under the overlay is only drawn again when something changes
+                    if !fps_overlay.is_visible() {
+                        engine.request_redraw();
                     }
                 }
-                SimulatorEvent::KeyUp { keycode, .. } => {
-                    let button = match keycode {
-                        Keycode::W => Some(Button::Up),
-                        Keycode::A => Some(Button::Left),
-                        Keycode::S => Some(Button::Down),
-                        Keycode::D => Some(Button::Right),
-                        Keycode::J => Some(Button::A),
-                        Keycode::K => Some(Button::B),
-                        _ => None,
-                    };
-
-                    if let Some(button) = button {
-                        engine
-                            .input_mut()
-                            .set_button(button, ButtonState::JustReleased);
-                        button_pressed.insert(button, false);
+                Keycode::F12 => *screenshot_requested = true,
+                _ => {
+                    if let Some(button) = key_map.button(keycode) {
+                        log::debug!("Button pressed: {:?}", button);
+                        keys_held.insert(keycode);
                     }
                 }
-                _ => (),
+            },
+            SimulatorEvent::KeyUp { keycode, .. } => {
+                keys_held.remove(&keycode);
             }
+            _ => (),
         }
+    }
+
+    // keys only give the levels, the input works out the edges like on the device.
+    // Keys are tracked rather than buttons, so letting go of one of two keys bound to
+    // the same button doesn't release it.
+    for button in Button::ALL {
+        input.update_button_level(engine, button, key_map.is_held(button, keys_held));
+    }
     true
+}
 
+// time since the simulator started, monotonic unlike SystemTime (NTP, DST)
+#[derive(Clone, Copy)]
+struct InstantClock {
+    start: Instant,
 }
 
-fn generate_mock_hw_data(engine: &mut Engine, tui: &mock_hw_tui::MockHwTui) {
-    // Get sensor values from TUI
-    let sensors = tui.get_sensor_state();
-    let time_ms = 0; // TODO: get actual time
-    
-    engine.input_mut().update_sensor(SensorType::BatteryVoltage, sensors.battery_voltage, time_ms);
-    engine.input_mut().update_sensor(SensorType::Thermometer, sensors.temperature, time_ms);
-    engine.input_mut().update_sensor(SensorType::LightSensor, sensors.light_level, time_ms);
-    engine.input_mut().update_sensor(SensorType::Accelerometer, sensors.accelerometer, time_ms);
-    engine.input_mut().update_sensor(SensorType::MicLoudness, sensors.mic_loudness, time_ms);
+impl InstantClock {
+    fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Clock for InstantClock {
+    fn now_us(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+}
+
+fn generate_mock_hw_data(engine: &mut Engine, input: &mut input_feed::InputFeed, sensors: &mock_hw_tui::MockSensorState) {
+    input.update_sensor(engine, SensorType::BatteryVoltage, sensors.battery_voltage);
+    input.update_sensor(engine, SensorType::Thermometer, sensors.temperature);
+    input.update_sensor(engine, SensorType::LightSensor, sensors.light_level);
+    input.update_sensor(engine, SensorType::Accelerometer, sensors.accelerometer);
+    input.update_sensor(engine, SensorType::MicLoudness, sensors.mic_loudness);
+}
+
+// stable for the whole (UTC) day, used to seed the daily challenge
+fn days_since_epoch() -> u32 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_epoch.as_secs() / (24 * 60 * 60)) as u32
+}
+
+struct Args {
+    /// `--script <path>`, replays a recorded sensor trace, see `SensorScript`
+    script: Option<PathBuf>,
+    /// `--record <dir>`, saves every `RECORD_EVERY_FRAMES`th frame there as a PNG
+    record_dir: Option<PathBuf>,
+    /// `--record-input <path>`, writes all input to a file, see `TimedInputEvent`
+    record_input: Option<PathBuf>,
+    /// `--replay-input <path>`, plays back a `--record-input` file instead of live input
+    replay_input: Option<PathBuf>,
+    /// `--keymap <path>`, binds other keys to the buttons, see `KeyMap`
+    keymap: Option<PathBuf>,
+    /// `--headless`, runs without window and TUI, see `headless::run`
+    headless: bool,
+    /// `--frames <n>`, how many frames a headless run lasts
+    frames: Option<u32>,
+}
+
+impl Args {
+    fn parse() -> anyhow::Result<Self> {
+        let mut args = std::env::args().skip(1);
+        let mut parsed = Self {
+            script: None,
+            record_dir: None,
+            record_input: None,
+            replay_input: None,
+            keymap: None,
+            headless: false,
+            frames: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--script" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--script needs a path"))?;
+                    parsed.script = Some(PathBuf::from(path));
+                }
+                "--record" => {
+                    let dir = args.next().ok_or_else(|| anyhow::anyhow!("--record needs a directory"))?;
+                    parsed.record_dir = Some(PathBuf::from(dir));
+                }
+                "--record-input" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--record-input needs a path"))?;
+                    parsed.record_input = Some(PathBuf::from(path));
+                }
+                "--replay-input" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--replay-input needs a path"))?;
+                    parsed.replay_input = Some(PathBuf::from(path));
+                }
+                "--keymap" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--keymap needs a path"))?;
+                    parsed.keymap = Some(PathBuf::from(path));
+                }
+                "--headless" => parsed.headless = true,
+                "--frames" => {
+                    let frames = args.next().ok_or_else(|| anyhow::anyhow!("--frames needs a count"))?;
+                    parsed.frames = Some(frames.parse().map_err(|_| anyhow::anyhow!("bad frame count `{}`", frames))?);
+                }
+                _ => anyhow::bail!("unknown argument `{}`", arg),
+            }
+        }
+        Ok(parsed)
+    }
 }
 
 fn main() -> anyhow::Result<()> {
+    // loaded before the TUI takes over the terminal, so errors are readable
+    let args = Args::parse()?;
+    let mut script = match &args.script {
+        Some(path) => Some(sensor_script::SensorScript::load(path)?),
+        None => None,
+    };
+    if let Some(dir) = &args.record_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let key_map = match &args.keymap {
+        Some(path) => key_map::KeyMap::load(path)?,
+        None => key_map::KeyMap::default(),
+    };
+    let mut input = match (&args.record_input, &args.replay_input) {
+        (Some(_), Some(_)) => anyhow::bail!("--record-input and --replay-input can't be used together"),
+        (Some(path), None) => input_feed::InputFeed::recording(path)?,
+        (None, Some(path)) => input_feed::InputFeed::replaying(path)?,
+        (None, None) => input_feed::InputFeed::live(),
+    };
+    if args.frames.is_some() && !args.headless {
+        anyhow::bail!("--frames only works with --headless");
+    }
+    if args.headless {
+        return headless::run(args.frames, args.record_dir.as_deref(), script, input);
+    }
+
     // Initialize the Mock Hardware TUI (also sets up the logger)
     let tui = mock_hw_tui::MockHwTui::new()?;
     log::info!("Tama Desktop Simulator started");
+    if script.is_some() {
+        tui.set_read_only(true);
+        log::info!("Sensor script loaded, sensor values are read-only");
+    }
     
     // Create the desktop buzzer (handles audio asynchronously)
     let buzzer = Box::new(buzzer::DesktopBuzzer::new());
@@ -110,24 +219,80 @@ fn main() -> anyhow::Result<()> {
     let settings = OutputSettingsBuilder::new().scale(2).pixel_spacing(0).build();
 
     let mut window = Window::new("tama-desktop", &settings);
-    window.set_max_fps(30);
+    window.set_max_fps(TARGET_FPS);
     let mut engine = Engine::with_buzzer(buzzer);
-    let mut button_pressed: HashMap<Button, bool> = HashMap::new();
+    engine.set_date_seed(days_since_epoch());
+    engine.set_wall_clock(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs()));
+    // update and render are timed for the FPS overlay
+    let clock = InstantClock::new();
+    engine.set_clock(Box::new(clock));
+    let mut keys_held: HashSet<Keycode> = HashSet::new();
+    let mut fps_overlay = fps_overlay::FpsOverlay::new();
     
     log::info!("Engine and display initialized");
 
+    let mut last_frame_ms = clock.now_ms();
+    let mut frame_count = 0u32;
     'running: loop {
         window.update(&display);
 
-        if !handle_simulator_events(&mut engine, &mut window, &mut button_pressed) {
+        // the window caps the frame rate at TARGET_FPS
+        let now_ms = clock.now_ms();
+        let dt_ms = now_ms.wrapping_sub(last_frame_ms);
+        last_frame_ms = now_ms;
+        input.start_frame(now_ms);
+
+        let mut screenshot_requested = false;
+        if !handle_simulator_events(&mut engine, &mut input, &mut window, &key_map, &mut keys_held, &mut fps_overlay, &mut screenshot_requested) {
             log::info!("Simulator window closed");
             break 'running;
         } //TODO verbose exit handling        
 
-        generate_mock_hw_data(&mut engine, &tui);
-        engine.update();
-        engine.render(&mut display)?;
+        if let Some(active_script) = &mut script {
+            active_script.advance(now_ms, |sensor, value| tui.set_sensor_value(sensor, value));
+            if active_script.is_finished() {
+                log::info!("Sensor script finished");
+                tui.set_read_only(false);
+                script = None;
+            }
+        }
+        generate_mock_hw_data(&mut engine, &mut input, &tui.get_sensor_state());
+        let update_ms = input.tick(&mut engine, dt_ms);
+        log_capture::drain_into(engine.logs_mut());
+        if let Some(update_ms) = update_ms {
+            engine.update(update_ms);
+        }
+        tui.set_smoothed_values(SensorType::ALL.map(|sensor| engine.input().get_sensor_value(sensor)));
+        if engine.power_off_requested() {
+            log::info!("Battery empty, simulator powering off");
+            break 'running;
+        }
+        // the display keeps the last frame otherwise
+        if engine.needs_redraw() {
+            engine.render(&mut display)?;
+        }
+        fps_overlay.record(engine.stats());
+        fps_overlay.draw(&mut display, engine.stats())?;
+
+        if screenshot_requested {
+            let path = screenshot::timestamped_path(Path::new("."));
+            match screenshot::save_png(&display, &path) {
+                Ok(()) => log::info!("Screenshot saved to {}", path.display()),
+                Err(e) => log::error!("{:#}", e),
+            }
+        }
+        if let Some(dir) = &args.record_dir
+            && frame_count.is_multiple_of(RECORD_EVERY_FRAMES)
+        {
+            let path = dir.join(format!("frame-{:06}.png", frame_count / RECORD_EVERY_FRAMES));
+            if let Err(e) = screenshot::save_png(&display, &path) {
+                log::error!("{:#}", e);
+            }
+        }
+        frame_count = frame_count.wrapping_add(1);
     }
 
+    engine.shutdown();
+
     Ok(())
 }
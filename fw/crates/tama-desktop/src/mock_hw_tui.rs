@@ -1,4 +1,5 @@
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::thread;
 use std::io;
@@ -16,17 +17,41 @@ use ratatui::{
     widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
     Frame, Terminal,
 };
+use tama_core::battery::voltage_to_percent;
+use tama_core::input::SensorType;
 
 // Shared sensor state - matches tama_core::input::SensorType enum
 #[derive(Clone, Debug)]
 pub struct MockSensorState {
-    pub battery_voltage: f32,  // Volts (2.5 - 4.2)
-    pub temperature: f32,       // Celsius (-40 - 80)
+    pub battery_voltage: f32,  // Volts
+    pub temperature: f32,       // Celsius
     pub light_level: f32,       // 0.0 - 1.0
     pub accelerometer: f32,     // Movement intensity (0.0 - 1.0)
     pub mic_loudness: f32,      // Audio level (0.0 - 1.0)
 }
 
+impl MockSensorState {
+    pub fn get(&self, sensor: SensorType) -> f32 {
+        match sensor {
+            SensorType::BatteryVoltage => self.battery_voltage,
+            SensorType::Thermometer => self.temperature,
+            SensorType::LightSensor => self.light_level,
+            SensorType::Accelerometer => self.accelerometer,
+            SensorType::MicLoudness => self.mic_loudness,
+        }
+    }
+
+    pub fn set(&mut self, sensor: SensorType, value: f32) {
+        match sensor {
+            SensorType::BatteryVoltage => self.battery_voltage = value,
+            SensorType::Thermometer => self.temperature = value,
+            SensorType::LightSensor => self.light_level = value,
+            SensorType::Accelerometer => self.accelerometer = value,
+            SensorType::MicLoudness => self.mic_loudness = value,
+        }
+    }
+}
+
 impl Default for MockSensorState {
     fn default() -> Self {
         Self {
@@ -77,6 +102,10 @@ impl LevelExt for Level {
 // TUI state
 struct TuiState {
     sensor_state: Arc<Mutex<MockSensorState>>,
+    // what the engine sees after smoothing, by sensor index
+    smoothed: Arc<Mutex<[f32; SensorType::COUNT]>>,
+    // set while a sensor script drives the values
+    read_only: Arc<AtomicBool>,
     logs: Vec<LogEntry>,
     rx: Receiver<TuiMessage>,
     selected_sensor: usize,
@@ -85,9 +114,16 @@ struct TuiState {
 }
 
 impl TuiState {
-    fn new(sensor_state: Arc<Mutex<MockSensorState>>, rx: Receiver<TuiMessage>) -> Self {
+    fn new(
+        sensor_state: Arc<Mutex<MockSensorState>>,
+        smoothed: Arc<Mutex<[f32; SensorType::COUNT]>>,
+        read_only: Arc<AtomicBool>,
+        rx: Receiver<TuiMessage>,
+    ) -> Self {
         Self {
             sensor_state,
+            smoothed,
+            read_only,
             logs: Vec::new(),
             rx,
             selected_sensor: 0,
@@ -114,17 +150,18 @@ impl TuiState {
     }
 
     fn adjust_sensor(&mut self, increase: bool) {
+        if self.read_only.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(&sensor) = SensorType::ALL.get(self.selected_sensor) else {
+            return;
+        };
         let mut state = self.sensor_state.lock().unwrap();
         let delta = if increase { 1.0 } else { -1.0 };
-
-        match self.selected_sensor {
-            0 => state.battery_voltage = (state.battery_voltage + delta * 0.1).clamp(2.5, 4.2),
-            1 => state.temperature = (state.temperature + delta).clamp(-40.0, 80.0),
-            2 => state.light_level = (state.light_level + delta * 0.1).clamp(0.0, 1.0),
-            3 => state.accelerometer = (state.accelerometer + delta * 0.1).clamp(0.0, 1.0),
-            4 => state.mic_loudness = (state.mic_loudness + delta * 0.1).clamp(0.0, 1.0),
-            _ => {}
-        }
+        let step = if sensor == SensorType::Thermometer { 1.0 } else { 0.1 };
+        let (min, max) = sensor.range();
+        let value = (state.get(sensor) + delta * step).clamp(min, max);
+        state.set(sensor, value);
     }
 }
 
@@ -152,6 +189,7 @@ impl log::Log for TuiLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
+            crate::log_capture::capture(record);
             let entry = LogEntry {
                 level: record.level(),
                 message: format!("{}", record.args()),
@@ -167,19 +205,26 @@ impl log::Log for TuiLogger {
 // Public handle for the TUI
 pub struct MockHwTui {
     sensor_state: Arc<Mutex<MockSensorState>>,
+    smoothed: Arc<Mutex<[f32; SensorType::COUNT]>>,
+    read_only: Arc<AtomicBool>,
     tx: Sender<TuiMessage>,
 }
 
 impl MockHwTui {
     pub fn new() -> Result<Self, log::SetLoggerError> {
         let sensor_state = Arc::new(Mutex::new(MockSensorState::default()));
+        let smoothed = Arc::new(Mutex::new([0.0; SensorType::COUNT]));
         let (tx, rx) = channel();
 
+        let read_only = Arc::new(AtomicBool::new(false));
+
         let sensor_state_clone = Arc::clone(&sensor_state);
+        let smoothed_clone = Arc::clone(&smoothed);
+        let read_only_clone = Arc::clone(&read_only);
 
         // Spawn TUI thread
         thread::spawn(move || {
-            if let Err(e) = run_tui(sensor_state_clone, rx) {
+            if let Err(e) = run_tui(sensor_state_clone, smoothed_clone, read_only_clone, rx) {
                 eprintln!("TUI error: {}", e);
             }
         });
@@ -191,6 +236,8 @@ impl MockHwTui {
 
         Ok(Self {
             sensor_state,
+            smoothed,
+            read_only,
             tx,
         })
     }
@@ -204,6 +251,21 @@ impl MockHwTui {
     pub fn get_sensor_state(&self) -> MockSensorState {
         self.sensor_state.lock().unwrap().clone()
     }
+
+    pub fn set_sensor_value(&self, sensor: SensorType, value: f32) {
+        self.sensor_state.lock().unwrap().set(sensor, value);
+    }
+
+    /// The engine's smoothed values (`Input::get_sensor_value`) by sensor index, shown
+    /// under the sliders
+    pub fn set_smoothed_values(&self, values: [f32; SensorType::COUNT]) {
+        *self.smoothed.lock().unwrap() = values;
+    }
+
+    /// Lock the sliders, the values are still shown
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
 }
 
 impl Drop for MockHwTui {
@@ -214,6 +276,8 @@ impl Drop for MockHwTui {
 
 fn run_tui(
     sensor_state: Arc<Mutex<MockSensorState>>,
+    smoothed: Arc<Mutex<[f32; SensorType::COUNT]>>,
+    read_only: Arc<AtomicBool>,
     rx: Receiver<TuiMessage>,
 ) -> Result<(), io::Error> {
     // Setup terminal
@@ -223,7 +287,7 @@ fn run_tui(
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut tui_state = TuiState::new(sensor_state, rx);
+    let mut tui_state = TuiState::new(sensor_state, smoothed, read_only, rx);
 
     // Initial log
     tui_state.logs.push(LogEntry {
@@ -256,7 +320,7 @@ fn run_tui(
                         }
                     }
                     KeyCode::Down => {
-                        if tui_state.selected_sensor < 4 {  // 5 sensors (0-4)
+                        if tui_state.selected_sensor < SensorType::COUNT - 1 {
                             tui_state.selected_sensor += 1;
                         }
                     }
@@ -315,30 +379,30 @@ fn ui(f: &mut Frame, state: &TuiState) {
 }
 
 fn render_sensors(f: &mut Frame, area: Rect, state: &TuiState) {
+    let title = if state.read_only.load(Ordering::Relaxed) {
+        "Sensor Values (scripted, read-only)"
+    } else {
+        "Sensor Values (adjust with ←/→ or +/-)"
+    };
     let block = Block::default()
         .borders(Borders::ALL)
-        .title("Sensor Values (adjust with ←/→ or +/-)");
+        .title(title);
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
     let sensor_state = state.sensor_state.lock().unwrap();
-
-    // Matches tama_core::input::SensorType enum order
-    let sensors = vec![
-        ("Battery Voltage", sensor_state.battery_voltage, "V", 2.5, 4.2),
-        ("Temperature", sensor_state.temperature, "°C", -40.0, 80.0),
-        ("Light Level", sensor_state.light_level, "", 0.0, 1.0),
-        ("Accelerometer", sensor_state.accelerometer, "", 0.0, 1.0),
-        ("Mic Loudness", sensor_state.mic_loudness, "", 0.0, 1.0),
-    ];
+    let smoothed = *state.smoothed.lock().unwrap();
 
     let sensor_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(vec![Constraint::Length(2); 5])
+        .constraints(vec![Constraint::Length(2); SensorType::COUNT])
         .split(inner);
 
-    for (i, (name, value, unit, min, max)) in sensors.iter().enumerate() {
+    for (i, sensor) in SensorType::all().enumerate() {
+        let value = sensor_state.get(sensor);
+        let (min, max) = sensor.range();
+        let unit = sensor.unit();
         let is_selected = i == state.selected_sensor;
         let style = if is_selected {
             Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
@@ -347,7 +411,10 @@ fn render_sensors(f: &mut Frame, area: Rect, state: &TuiState) {
         };
 
         let ratio = ((value - min) / (max - min)).clamp(0.0, 1.0);
-        let label = format!("{}: {:.2}{}", name, value, unit);
+        let mut label = format!("{}: {:.2}{}", sensor_label(sensor), value, unit);
+        if sensor == SensorType::BatteryVoltage {
+            label.push_str(&format!(" ({}%)", voltage_to_percent(value)));
+        }
 
         let gauge = Gauge::default()
             .block(Block::default())
@@ -355,7 +422,31 @@ fn render_sensors(f: &mut Frame, area: Rect, state: &TuiState) {
             .ratio(ratio as f64)
             .label(label);
 
-        f.render_widget(gauge, sensor_layout[i]);
+        // the slider on the first line, what the engine made of it on the second,
+        // so the lag of the moving average shows
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1)])
+            .split(sensor_layout[i]);
+        f.render_widget(gauge, rows[0]);
+
+        let smoothed_value = smoothed[i];
+        let smoothed_ratio = ((smoothed_value - min) / (max - min)).clamp(0.0, 1.0);
+        let smoothed_gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::DarkGray))
+            .ratio(smoothed_ratio as f64)
+            .label(format!("  smoothed: {:.2}{}", smoothed_value, unit));
+        f.render_widget(smoothed_gauge, rows[1]);
+    }
+}
+
+fn sensor_label(sensor: SensorType) -> &'static str {
+    match sensor {
+        SensorType::BatteryVoltage => "Battery Voltage",
+        SensorType::Thermometer => "Temperature",
+        SensorType::LightSensor => "Light Level",
+        SensorType::Accelerometer => "Accelerometer",
+        SensorType::MicLoudness => "Mic Loudness",
     }
 }
 
@@ -0,0 +1,23 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context as _;
+use embedded_graphics_simulator::{OutputSettingsBuilder, SimulatorDisplay};
+use tama_core::consts;
+
+/// Save the display at its real resolution, one image pixel per display pixel
+pub fn save_png(display: &SimulatorDisplay<consts::ColorType>, path: &Path) -> anyhow::Result<()> {
+    let settings = OutputSettingsBuilder::new().scale(1).pixel_spacing(0).build();
+    display
+        .to_rgb_output_image(&settings)
+        .save_png(path)
+        .with_context(|| format!("failed to save screenshot {}", path.display()))
+}
+
+/// `tama-<unix ms>.png` in `dir`, so screenshots sort by when they were taken
+pub fn timestamped_path(dir: &Path) -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    dir.join(format!("tama-{}.png", since_epoch.as_millis()))
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context as _};
+use tama_core::input::SensorType;
+
+// One scripted reading, `time_ms` counts from the script start
+#[derive(Clone, Debug)]
+struct ScriptRow {
+    time_ms: u32,
+    sensor: SensorType,
+    value: f32,
+}
+
+/// Recorded sensor trace, a CSV file of `time_ms,sensor,value` rows.
+/// Sensors are named as in `SensorType::name`: `battery`, `temperature`, `light`, `accelerometer` and `mic`.
+/// Empty lines and lines starting with `#` are skipped, rows don't have to be sorted.
+pub struct SensorScript {
+    rows: Vec<ScriptRow>,
+    next: usize,
+}
+
+impl SensorScript {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read sensor script {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid sensor script {}", path.display()))
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut rows = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rows.push(parse_row(line).with_context(|| format!("line {}", i + 1))?);
+        }
+        // stable, rows with the same time keep their file order
+        rows.sort_by_key(|row| row.time_ms);
+
+        Ok(Self { rows, next: 0 })
+    }
+
+    /// Call `apply` for every row due at `now_ms` that wasn't applied yet
+    pub fn advance(&mut self, now_ms: u32, mut apply: impl FnMut(SensorType, f32)) {
+        while let Some(row) = self.rows.get(self.next) {
+            if row.time_ms > now_ms {
+                break;
+            }
+            apply(row.sensor, row.value);
+            self.next += 1;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.rows.len()
+    }
+}
+
+fn parse_row(line: &str) -> anyhow::Result<ScriptRow> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let [time_ms, sensor, value] = fields[..] else {
+        bail!("expected `time_ms,sensor,value`, got {} fields", fields.len());
+    };
+
+    Ok(ScriptRow {
+        time_ms: time_ms.parse().with_context(|| format!("bad time `{}`", time_ms))?,
+        sensor: parse_sensor(sensor)?,
+        value: value.parse().with_context(|| format!("bad value `{}`", value))?,
+    })
+}
+
+fn parse_sensor(name: &str) -> anyhow::Result<SensorType> {
+    SensorType::from_name(name).ok_or_else(|| anyhow!("unknown sensor `{}`", name))
+}
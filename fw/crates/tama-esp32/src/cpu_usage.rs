@@ -0,0 +1,62 @@
+use esp_idf_hal::cpu::Core;
+use esp_idf_hal::task::get_idle_task;
+use esp_idf_svc::sys::{uxTaskGetNumberOfTasks, uxTaskGetSystemState, TaskStatus_t};
+
+const CORES: [Core; 2] = [Core::Core0, Core::Core1];
+// uxTaskGetSystemState fails when tasks were created since counting them
+const SPARE_TASK_SLOTS: usize = 4;
+
+// Busy share of each core, from the FreeRTOS run time stats: the part of the time since
+// the last sample that core's idle task didn't run. The stats need
+// CONFIG_FREERTOS_USE_TRACE_FACILITY and CONFIG_FREERTOS_GENERATE_RUN_TIME_STATS (see
+// sdkconfig.defaults), which cost a bit on every context switch, so this is only built
+// with the `cpu-stats` feature.
+pub struct CpuUsage {
+    // run time counters wrap, they're compared as u32 whatever their configured width
+    last_total: u32,
+    last_idle: [u32; 2],
+}
+
+impl CpuUsage {
+    pub fn new() -> Self {
+        let mut usage = Self { last_total: 0, last_idle: [0; 2] };
+        usage.sample();
+        usage
+    }
+
+    // Busy percent of core 0 and core 1 since the last call, None when the stats
+    // can't be read
+    pub fn sample(&mut self) -> Option<[u8; 2]> {
+        let capacity = unsafe { uxTaskGetNumberOfTasks() } as usize + SPARE_TASK_SLOTS;
+        let mut tasks: Vec<TaskStatus_t> = Vec::with_capacity(capacity);
+        let mut total = 0;
+        let filled = unsafe { uxTaskGetSystemState(tasks.as_mut_ptr(), capacity as _, &mut total) } as usize;
+        if filled == 0 {
+            return None;
+        }
+        // the first `filled` entries were written by uxTaskGetSystemState
+        unsafe { tasks.set_len(filled) };
+
+        let mut idle = [0; 2];
+        for (i, &core) in CORES.iter().enumerate() {
+            let handle = get_idle_task(core);
+            idle[i] = tasks.iter().find(|task| task.xHandle == handle)?.ulRunTimeCounter as u32;
+        }
+
+        let total = total as u32;
+        let elapsed = total.wrapping_sub(self.last_total);
+        let mut busy = [0; 2];
+        for i in 0..CORES.len() {
+            let idle_time = idle[i].wrapping_sub(self.last_idle[i]);
+            busy[i] = if elapsed == 0 {
+                0
+            } else {
+                100 - (idle_time as u64 * 100 / elapsed as u64).min(100) as u8
+            };
+        }
+
+        self.last_total = total;
+        self.last_idle = idle;
+        Some(busy)
+    }
+}
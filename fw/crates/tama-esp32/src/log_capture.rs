@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+
+use esp_idf_svc::log::EspLogger;
+use log::Log as _;
+use tama_core::log_buffer::LogBuffer;
+#[cfg(feature = "panic-screen")]
+use tama_core::log_buffer::{LogEntry, LogLevel, SizedLogBuffer, LOG_LINE_MAX_LEN};
+
+// Records logged since the last frame, moved over to the engine by `drain_into`
+static CAPTURED: Mutex<LogBuffer> = Mutex::new(LogBuffer::new());
+// The last important records for the panic screen, the engine's copy can't be reached
+// from the panic hook
+#[cfg(feature = "panic-screen")]
+static RECENT: Mutex<SizedLogBuffer<{ crate::panic_screen::PANIC_LOG_LINES }, LOG_LINE_MAX_LEN>> =
+    Mutex::new(SizedLogBuffer::new());
+static LOGGER: CaptureLogger = CaptureLogger { esp: EspLogger::new() };
+
+// Prints through EspLogger as before and keeps a copy for the on-device log view
+struct CaptureLogger {
+    esp: EspLogger,
+}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.esp.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.esp.log(record);
+        if let Ok(mut captured) = CAPTURED.lock() {
+            captured.capture(record);
+        }
+        #[cfg(feature = "panic-screen")]
+        if record.level() <= log::Level::Info {
+            let entry = LogEntry::from_record(record);
+            if entry.level <= LogLevel::Notice {
+                if let Ok(mut recent) = RECENT.lock() {
+                    recent.push_entry(entry);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.esp.flush();
+    }
+}
+
+// Replaces EspLogger::initialize_default
+pub fn init() {
+    log::set_logger(&LOGGER)
+        .map(|()| LOGGER.esp.initialize())
+        .unwrap();
+}
+
+pub fn drain_into(logs: &mut LogBuffer) {
+    if let Ok(mut captured) = CAPTURED.lock() {
+        captured.drain_into(logs);
+    }
+}
+
+// Oldest first. Skipped if the panic came from inside the logger while it held the lock.
+#[cfg(feature = "panic-screen")]
+pub fn recent_for_panic() -> Vec<LogEntry> {
+    match RECENT.try_lock() {
+        Ok(recent) => recent.iter().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
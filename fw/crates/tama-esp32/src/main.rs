@@ -1,97 +1,376 @@
 use esp_idf_hal::{
     delay::FreeRtos,
-    gpio::PinDriver,
+    gpio::{AnyInputPin, AnyOutputPin, Gpio0, Input, InputPin as _, InterruptType, Output, OutputPin as _, PinDriver},
+    ledc::{config::TimerConfig, LedcDriver, LedcTimerDriver, CHANNEL0, TIMER0},
     prelude::*,
-    spi::{SpiDeviceDriver, SpiDriver, SpiDriverConfig, Dma},
+    spi::{SpiDeviceDriver, SpiDriver, SpiDriverConfig, Dma, SPI2},
     units::FromValueType,
 };
 use mipidsi::{
-    interface::SpiInterface,
+    interface::{Interface as _, SpiInterface},
     models::ST7789,
     options::{ColorInversion, Orientation, Rotation},
     Builder,
 };
-use tama_core::engine::Engine;
-use tama_core::input::{Button, ButtonState};
+use peripherals::{gpio_error, PeripheralError};
+use power::{ChargerPins, PowerControl};
+use stack_monitor::{StackMonitor, STACK_WARN_BELOW_BYTES};
+use tama_core::brightness::AutoBrightness;
+use tama_core::clock::Clock;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use tama_core::engine::{Engine, StubBuzzer};
+use tama_core::frame_exchange::SharedFramebuffer;
+use tama_core::frame_scheduler::FrameScheduler;
+use tama_core::framebuffer::PixelByteOrder;
+use tama_core::input::{Button, Debouncer, SensorState, SensorType};
+use tama_core::settings::{Settings, SettingsStore};
 use embedded_graphics::{
     prelude::*,
     pixelcolor::Rgb565,
     primitives::Rectangle,
 };
-use std::sync::{Arc, Mutex, Condvar};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "cpu-stats")]
+mod cpu_usage;
+mod log_capture;
+#[cfg(feature = "panic-screen")]
+mod panic_screen;
+mod peripherals;
+mod power;
+mod stack_monitor;
+
+// The ST7789 takes the pixels big endian, see `PixelByteOrder`
+const PANEL_BYTE_ORDER: PixelByteOrder = PixelByteOrder::BigEndian;
+// Bytes per array handed to the display interface for full-row transfers. It copies them
+// into its buffer with a memcpy each, so large arrays make it a bulk copy.
+const TRANSFER_CHUNK_BYTES: usize = 512;
+
+// How the main loop is paced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FramePacing {
+    // Fixed delay per frame, rendering doesn't wait for the panel
+    FreeRunning,
+    // Before rendering, wait until the previous frame reached the panel. A frame then
+    // counts as taking until its transfer ended, which is what the FrameScheduler sees.
+    Vsync,
+}
 
-// Simple framebuffer that implements DrawTarget
-struct Framebuffer {
-    data: Box<[Rgb565]>,
-    width: u32,
-    height: u32,
+const FRAME_PACING: FramePacing = FramePacing::FreeRunning;
+
+// One ST7789 module: the part of the controller's 240x320 memory its glass shows,
+// in the controller's native (portrait) orientation, and how it's mounted. mipidsi
+// moves the offset along with the rotation, so it's always given for Deg0.
+struct PanelConfig {
+    width: u16,
+    height: u16,
+    offset_x: u16,
+    offset_y: u16,
+    inverted: bool,
+    rotation: Rotation,
 }
 
-impl Framebuffer {
-    fn new(width: u32, height: u32) -> Self {
-        let size = (width * height) as usize;
-        let data = vec![Rgb565::BLACK; size].into_boxed_slice();
-        Self { data, width, height }
-    }
-    
-    fn iter(&self) -> impl Iterator<Item = Rgb565> + '_ {
-        self.data.iter().copied()
+impl PanelConfig {
+    // 1.69" module with rounded corners, the memory's middle 280 rows
+    const ST7789_240X280: PanelConfig = PanelConfig {
+        width: 240,
+        height: 280,
+        offset_x: 0,
+        offset_y: 20,
+        inverted: true,
+        rotation: Rotation::Deg0,
+    };
+
+    // 1.3"/1.54" square modules, the first 240 rows (mipidsi moves them to the
+    // last ones at 180 and 270 degrees)
+    #[allow(dead_code)]
+    const ST7789_240X240: PanelConfig = PanelConfig {
+        width: 240,
+        height: 240,
+        offset_x: 0,
+        offset_y: 0,
+        inverted: true,
+        rotation: Rotation::Deg0,
+    };
+
+    // 2" modules using all of the memory, mounted landscape
+    #[allow(dead_code)]
+    const ST7789_320X240: PanelConfig = PanelConfig {
+        width: 240,
+        height: 320,
+        offset_x: 0,
+        offset_y: 0,
+        inverted: true,
+        rotation: Rotation::Deg90,
+    };
+
+    // Size of the picture at `rotation`, what the framebuffer has to be
+    const fn size_at(&self, rotation: Rotation) -> (u32, u32) {
+        match rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (self.width as u32, self.height as u32),
+            Rotation::Deg90 | Rotation::Deg270 => (self.height as u32, self.width as u32),
+        }
     }
 }
 
-// Thread-safe framebuffer wrapper for IPC between cores
-struct SharedFramebuffer {
-    framebuffer: Arc<Mutex<Framebuffer>>,
-    frame_ready: Arc<(Mutex<bool>, Condvar)>,
+// The panel this build drives
+const PANEL: PanelConfig = PanelConfig::ST7789_240X280;
+
+// Scenes lay out for tama_core::consts, a panel of another size needs those changed too
+const _: () = {
+    let (width, height) = PANEL.size_at(PANEL.rotation);
+    assert!(
+        width == tama_core::consts::WIDTH && height == tama_core::consts::HEIGHT,
+        "PANEL doesn't match tama_core::consts::WIDTH/HEIGHT"
+    );
+};
+
+// Display settings Core 0 can change while the transfer thread owns the display
+struct DisplayControl {
+    rotation: AtomicU8,
+    // cleared when the display couldn't be set up, nothing is rendered then
+    running: AtomicBool,
 }
 
-impl SharedFramebuffer {
-    fn new(width: u32, height: u32) -> Self {
+impl DisplayControl {
+    fn new(rotation: Rotation) -> Self {
         Self {
-            framebuffer: Arc::new(Mutex::new(Framebuffer::new(width, height))),
-            frame_ready: Arc::new((Mutex::new(false), Condvar::new())),
+            rotation: AtomicU8::new(rotation_to_u8(rotation)),
+            running: AtomicBool::new(true),
         }
     }
-    
-    fn clone_for_transfer(&self) -> (Arc<Mutex<Framebuffer>>, Arc<(Mutex<bool>, Condvar)>) {
-        (Arc::clone(&self.framebuffer), Arc::clone(&self.frame_ready))
+
+    fn stop(&self, error: &PeripheralError) {
+        tama_core::notice!("Running without display, {}", error);
+        self.running.store(false, Ordering::Relaxed);
     }
-    
-    fn lock(&self) -> std::sync::MutexGuard<Framebuffer> {
-        self.framebuffer.lock().unwrap()
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
     }
-    
-    fn signal_frame_ready(&self) {
-        let (lock, cvar) = &*self.frame_ready;
-        let mut ready = lock.lock().unwrap();
-        *ready = true;
-        cvar.notify_one();
+
+    // Applied by the transfer thread before the next transfer. The framebuffer keeps the
+    // size of `PANEL.rotation` and only the panel's scan direction changes, so a quarter
+    // turn from it crops the frame to the panel.
+    #[allow(dead_code)] // nothing picks the orientation yet
+    fn set_rotation(&self, rotation: Rotation) {
+        self.rotation.store(rotation_to_u8(rotation), Ordering::Relaxed);
+    }
+
+    fn rotation(&self) -> Rotation {
+        match self.rotation.load(Ordering::Relaxed) {
+            1 => Rotation::Deg90,
+            2 => Rotation::Deg180,
+            3 => Rotation::Deg270,
+            _ => Rotation::Deg0,
+        }
     }
 }
 
-impl OriginDimensions for Framebuffer {
-    fn size(&self) -> Size {
-        Size::new(self.width, self.height)
+fn rotation_to_u8(rotation: Rotation) -> u8 {
+    match rotation {
+        Rotation::Deg0 => 0,
+        Rotation::Deg90 => 1,
+        Rotation::Deg180 => 2,
+        Rotation::Deg270 => 3,
     }
 }
 
-impl DrawTarget for Framebuffer {
-    type Color = Rgb565;
-    type Error = core::convert::Infallible;
-
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for Pixel(point, color) in pixels {
-            if point.x >= 0 && point.x < self.width as i32 
-                && point.y >= 0 && point.y < self.height as i32 {
-                let index = (point.y as u32 * self.width + point.x as u32) as usize;
-                self.data[index] = color;
+// Settings blob in the default NVS partition
+struct NvsSettingsStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl NvsSettingsStore {
+    const NAMESPACE: &'static str = "tama";
+    const KEY: &'static str = "settings";
+
+    fn new(partition: EspDefaultNvsPartition) -> Result<Self, esp_idf_svc::sys::EspError> {
+        Ok(Self { nvs: EspNvs::new(partition, Self::NAMESPACE, true)? })
+    }
+}
+
+impl SettingsStore for NvsSettingsStore {
+    fn load(&mut self) -> Option<Settings> {
+        // room for blobs written by newer firmware with more fields
+        let mut buffer = [0u8; 64];
+        match self.nvs.get_blob(Self::KEY, &mut buffer) {
+            Ok(Some(bytes)) => {
+                let settings = Settings::from_bytes(bytes);
+                if settings.is_none() {
+                    log::warn!("Saved settings ({} bytes) can't be read, using defaults", bytes.len());
+                }
+                settings
             }
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("Failed to read settings from NVS: {:?}", e);
+                None
+            }
+        }
+    }
+
+    fn save(&mut self, settings: &Settings) {
+        match self.nvs.set_blob(Self::KEY, &settings.to_bytes()) {
+            Ok(()) => log::info!("Settings saved"),
+            Err(e) => log::error!("Failed to save settings to NVS: {:?}", e),
+        }
+    }
+}
+
+// esp_timer counts microseconds since boot
+#[derive(Clone, Copy)]
+struct EspClock;
+
+impl Clock for EspClock {
+    fn now_us(&self) -> u64 {
+        unsafe { esp_idf_svc::sys::esp_timer_get_time() as u64 }
+    }
+}
+
+// Frames a button has to read the same before it counts as pressed/released, 3 frames
+// is ~100 ms at 30 FPS. 1 turns debouncing off.
+const BUTTON_DEBOUNCE_FRAMES: u8 = 3;
+// Latch presses with a GPIO interrupt, so a tap shorter than a frame isn't missed. The pin
+// is still polled every frame for the release, and alone when the interrupt can't be set up.
+const BUTTON_INTERRUPTS: bool = true;
+
+// Set by the ISR on a falling edge (press) of the button pin, taken by the main loop
+static BUTTON_EDGE_SEEN: AtomicBool = AtomicBool::new(false);
+
+fn subscribe_button_edges(button: &mut PinDriver<'static, Gpio0, Input>) -> Result<(), esp_idf_svc::sys::EspError> {
+    button.set_interrupt_type(InterruptType::NegEdge)?;
+    // runs in the ISR, so it only sets the flag
+    unsafe { button.subscribe(|| BUTTON_EDGE_SEEN.store(true, Ordering::Relaxed))? };
+    button.enable_interrupt()
+}
+
+// ST7789 positive/negative voltage gamma control, 14 bytes each
+const ST7789_PVGAMCTRL: u8 = 0xE0;
+const ST7789_NVGAMCTRL: u8 = 0xE1;
+
+struct GammaCurve {
+    positive: [u8; 14],
+    negative: [u8; 14],
+}
+
+// From the vendor init sequence for the 1.69" 240x280 module. The panel's power-on
+// curve looks washed out next to the simulator, this one has deeper darks.
+const PANEL_GAMMA: GammaCurve = GammaCurve {
+    positive: [0xD0, 0x04, 0x0D, 0x11, 0x13, 0x2B, 0x3F, 0x54, 0x4C, 0x18, 0x0D, 0x0B, 0x1F, 0x23],
+    negative: [0xD0, 0x04, 0x0C, 0x11, 0x13, 0x2C, 0x3F, 0x44, 0x51, 0x2F, 0x1F, 0x1F, 0x20, 0x23],
+};
+// None keeps the panel's power-on curve
+const DISPLAY_GAMMA: Option<&GammaCurve> = Some(&PANEL_GAMMA);
+
+// Task watchdog resets the chip when a subscribed task isn't fed within the timeout
+// (CONFIG_ESP_TASK_WDT_TIMEOUT_S), the `no-watchdog` feature turns it off for debugging
+const WATCHDOG_ENABLED: bool = !cfg!(feature = "no-watchdog");
+const WATCHDOG_TIMEOUT_MS: u64 = esp_idf_svc::sys::CONFIG_ESP_TASK_WDT_TIMEOUT_S as u64 * 1000;
+// Frames taking longer than this are logged, they're getting close to a reset
+const WATCHDOG_WARN_MS: u64 = WATCHDOG_TIMEOUT_MS / 2;
+// The transfer thread wakes up this often to feed the watchdog while no frames come in
+const TRANSFER_IDLE_WAKE: Duration = Duration::from_millis(WATCHDOG_TIMEOUT_MS / 4);
+
+// Subscription of the task that created it, feed it once per loop iteration
+struct TaskWatchdog {
+    subscribed: bool,
+}
+
+impl TaskWatchdog {
+    fn subscribe_current_task(task_name: &str) -> Self {
+        if !WATCHDOG_ENABLED {
+            return Self { subscribed: false };
+        }
+        let result = esp_idf_svc::sys::esp!(unsafe { esp_idf_svc::sys::esp_task_wdt_add(core::ptr::null_mut()) });
+        if let Err(e) = result {
+            log::error!("{} not watched by the task watchdog: {:?}", task_name, e);
+        }
+        Self { subscribed: result.is_ok() }
+    }
+
+    fn feed(&self) {
+        if self.subscribed {
+            unsafe { esp_idf_svc::sys::esp_task_wdt_reset() };
+        }
+    }
+}
+
+// A task that ends (the transfer thread without a display) would trip the watchdog otherwise
+impl Drop for TaskWatchdog {
+    fn drop(&mut self) {
+        if self.subscribed {
+            unsafe { esp_idf_svc::sys::esp_task_wdt_delete(core::ptr::null_mut()) };
         }
-        Ok(())
+    }
+}
+
+// The display's SPI device and control pins, the display needs all of them
+struct DisplayBus {
+    spi: SpiDeviceDriver<'static, SpiDriver<'static>>,
+    dc: PinDriver<'static, AnyOutputPin, Output>,
+    rst: PinDriver<'static, AnyOutputPin, Output>,
+}
+
+fn try_init_display_bus(
+    spi: SPI2,
+    sclk: AnyOutputPin,
+    sdo: AnyOutputPin,
+    sdi: AnyInputPin,
+    cs: AnyOutputPin,
+    dc: AnyOutputPin,
+    rst: AnyOutputPin,
+) -> Result<DisplayBus, PeripheralError> {
+    log::info!("Configuring SPI with DMA...");
+
+    // Configure SPI driver with DMA enabled for better performance
+    // DMA allows large transfers without CPU intervention
+    let spi_driver = SpiDriver::new(
+        spi,
+        sclk,
+        sdo,
+        Some(sdi),
+        &SpiDriverConfig::new().dma(Dma::Auto(32768)), // Enable DMA
+    )
+    .map_err(PeripheralError::Spi)?;
+
+    // Create SPI device with CS pin
+    // ST7789 can handle up to 80 MHz, using 80 MHz for maximum performance
+    let config = esp_idf_hal::spi::config::Config::new()
+        .baudrate(80.MHz().into());
+    
+    let spi_device = SpiDeviceDriver::new(
+        spi_driver,
+        Some(cs),
+        &config,
+    )
+    .map_err(PeripheralError::Spi)?;
+
+    log::info!("SPI with DMA configured successfully");
+
+    // Configure display control pins
+    Ok(DisplayBus {
+        spi: spi_device,
+        dc: PinDriver::output(dc).map_err(gpio_error("display DC pin"))?,
+        rst: PinDriver::output(rst).map_err(gpio_error("display reset pin"))?,
+    })
+}
+
+// Backlight is dimmed with PWM
+fn try_init_backlight(timer: TIMER0, channel: CHANNEL0, pin: AnyOutputPin) -> Result<LedcDriver<'static>, PeripheralError> {
+    let backlight_timer = LedcTimerDriver::new(timer, &TimerConfig::new().frequency(5.kHz().into()))
+        .map_err(PeripheralError::Ledc)?;
+    LedcDriver::new(channel, backlight_timer, pin).map_err(PeripheralError::Ledc)
+}
+
+// Backlight brightness in percent
+fn set_backlight(backlight: &mut LedcDriver, percent: u8) {
+    let duty = backlight.get_max_duty() * percent.min(100) as u32 / 100;
+    if let Err(e) = backlight.set_duty(duty) {
+        log::error!("Failed to set backlight to {}%: {:?}", percent, e);
     }
 }
 
@@ -100,29 +379,82 @@ fn main() {
     // implemented by esp-idf-sys might not link properly. See https://github.com/esp-rs/esp-idf-template/issues/71
     esp_idf_svc::sys::link_patches();
 
-    // Bind the log crate to the ESP Logging facilities
-    esp_idf_svc::log::EspLogger::initialize_default();
+    // Bind the log crate to the ESP Logging facilities, recent lines are also kept for the log scene
+    log_capture::init();
+    #[cfg(feature = "panic-screen")]
+    panic_screen::install();
 
-    // Disable the task watchdog timer temporarily while debugging slow rendering
-    // This prevents system resets during long-running operations
-    unsafe {
-        esp_idf_svc::sys::esp_task_wdt_deinit();
+    // The task watchdog stays on so a hang resets the chip, debug builds that stop
+    // in long operations (slow rendering...) can turn it off with `no-watchdog`
+    if WATCHDOG_ENABLED {
+        log::info!("Task watchdog enabled, {} ms timeout", WATCHDOG_TIMEOUT_MS);
+    } else {
+        unsafe {
+            esp_idf_svc::sys::esp_task_wdt_deinit();
+        }
+        log::info!("Task watchdog timer disabled");
     }
-    log::info!("Task watchdog timer disabled");
 
     log::info!("Tama ESP32 starting...");
 
     let peripherals = Peripherals::take().unwrap();
 
-    // Configure button input (simple test - will be refactored later)
+    // Configure button input (simple test - will be refactored later).
+    // Hardware that doesn't come up is left out, the rest keeps working.
     let button_pin = peripherals.pins.gpio0;
-    let button = PinDriver::input(button_pin).unwrap();
-    log::info!("Button configured on GPIO0");
+    let mut button = match PinDriver::input(button_pin).map_err(gpio_error("button GPIO0")) {
+        Ok(button) => {
+            log::info!("Button configured on GPIO0");
+            Some(button)
+        }
+        Err(e) => {
+            tama_core::notice!("Running without button, {}", e);
+            None
+        }
+    };
+    let button_interrupts = match button.as_mut().filter(|_| BUTTON_INTERRUPTS).map(subscribe_button_edges) {
+        Some(Ok(())) => {
+            log::info!("Button interrupt enabled");
+            true
+        }
+        Some(Err(e)) => {
+            tama_core::notice!("Button interrupt unavailable, polling only: {}", e);
+            false
+        }
+        None => false,
+    };
 
     // Set GPIO5 high before configuring SPI
-    let mut gpio5 = PinDriver::output(peripherals.pins.gpio5).unwrap();
-    gpio5.set_high().unwrap();
-    log::info!("GPIO5 set high");
+    let gpio5 = PinDriver::output(peripherals.pins.gpio5).and_then(|mut pin| pin.set_high().map(|()| pin));
+    let gpio5 = match gpio5.map_err(gpio_error("GPIO5")) {
+        Ok(pin) => {
+            log::info!("GPIO5 set high");
+            Some(pin)
+        }
+        Err(e) => {
+            tama_core::notice!("{}, the display may stay dark", e);
+            None
+        }
+    };
+    // BQ24073 status, R4 and R5 pull both lines up
+    let charger = PinDriver::input(peripherals.pins.gpio12.downgrade_input())
+        .map_err(gpio_error("charger /CHG GPIO12"))
+        .and_then(|charge_status| {
+            let power_good = PinDriver::input(peripherals.pins.gpio13.downgrade_input())
+                .map_err(gpio_error("charger /PGOOD GPIO13"))?;
+            Ok(ChargerPins { charge_status, power_good })
+        });
+    let charger = match charger {
+        Ok(charger) => {
+            log::info!("Charger status on GPIO12 and GPIO13");
+            Some(charger)
+        }
+        Err(e) => {
+            tama_core::notice!("Running without charger status, {}", e);
+            None
+        }
+    };
+    let mut power = PowerControl::new(gpio5, charger);
 
     // Configure SPI pins
     let sclk = peripherals.pins.gpio37; // SPI Clock
@@ -147,51 +479,41 @@ fn main() {
     // let rst = peripherals.pins.gpio3;      // Reset
     // let backlight = peripherals.pins.gpio7; // Backlight control
 
-    log::info!("Configuring SPI with DMA...");
-
-    // Configure SPI driver with DMA enabled for better performance
-    // DMA allows large transfers without CPU intervention
-    let spi_driver = SpiDriver::new(
+    let display_bus = try_init_display_bus(
         peripherals.spi2,
-        sclk,
-        sdo,
-        Some(sdi),
-        &SpiDriverConfig::new().dma(Dma::Auto(32768)), // Enable DMA
-    )
-    .unwrap();
-
-    // Create SPI device with CS pin
-    // ST7789 can handle up to 80 MHz, using 80 MHz for maximum performance
-    let config = esp_idf_hal::spi::config::Config::new()
-        .baudrate(80.MHz().into());
-    
-    let spi_device = SpiDeviceDriver::new(
-        spi_driver,
-        Some(cs),
-        &config,
-    )
-    .unwrap();
-
-    log::info!("SPI with DMA configured successfully");
-
-    // Configure display control pins
-    let dc_pin = PinDriver::output(dc).unwrap();
-    let rst_pin = PinDriver::output(rst).unwrap();
-    let mut backlight_pin = PinDriver::output(backlight).unwrap();
+        sclk.downgrade_output(),
+        sdo.downgrade_output(),
+        sdi.downgrade_input(),
+        cs.downgrade_output(),
+        dc.downgrade_output(),
+        rst.downgrade_output(),
+    );
+    let mut backlight_pwm = match try_init_backlight(peripherals.ledc.timer0, peripherals.ledc.channel0, backlight.downgrade_output()) {
+        Ok(pwm) => Some(pwm),
+        Err(e) => {
+            tama_core::notice!("Running without backlight control, {}", e);
+            None
+        }
+    };
 
     log::info!("Preparing display hardware...");
 
     // Turn on backlight
-    backlight_pin.set_high().unwrap();
+    if let Some(pwm) = &mut backlight_pwm {
+        set_backlight(pwm, 100);
+    }
 
-    // Allocate framebuffer on heap for double buffering
-    // 280x240 pixels * 2 bytes per pixel (RGB565) = 134,400 bytes
-    log::info!("Allocating shared framebuffer (134,400 bytes)...");
-    let shared_fb = SharedFramebuffer::new(240, 280);
+    // Allocate both framebuffers on heap for double buffering, 2 bytes per pixel (RGB565)
+    let (fb_width, fb_height) = PANEL.size_at(PANEL.rotation);
+    log::info!("Allocating shared framebuffers (2 x {} bytes)...", fb_width * fb_height * 2);
+    let shared_fb = SharedFramebuffer::new(fb_width, fb_height, PANEL_BYTE_ORDER);
     log::info!("Shared framebuffer allocated successfully");
 
     // Clone Arc references for the display transfer thread (Core 1)
-    let (fb_arc, frame_ready_arc) = shared_fb.clone_for_transfer();
+    let exchange = shared_fb.clone_for_transfer();
+    // Core 0 keeps a handle to change the orientation at runtime
+    let display_control = Arc::new(DisplayControl::new(PANEL.rotation));
+    let transfer_display_control = Arc::clone(&display_control);
     
     // Spawn display transfer thread on Core 1
     log::info!("Spawning display transfer thread...");
@@ -200,71 +522,139 @@ fn main() {
         .name("display_transfer".to_string())
         .stack_size(3092) // 16KB stack for display thread (needs space for display buffer)
         .spawn(move || {
+            let bus = match display_bus {
+                Ok(bus) => bus,
+                Err(e) => return transfer_display_control.stop(&e),
+            };
             log::info!("Display transfer thread started - initializing display...");
+            let watchdog = TaskWatchdog::subscribe_current_task("Display transfer thread");
             
             // Create display interface with heap-allocated buffer
             let mut buffer = vec![0u8; 65535].into_boxed_slice(); // 64 KB buffer on heap
-            let di = SpiInterface::new(spi_device, dc_pin, &mut *buffer);
+            let di = SpiInterface::new(bus.spi, bus.dc, &mut *buffer);
 
             // Initialize the display in this thread
-            let mut display = Builder::new(ST7789, di)
-                .display_size(240, 280)
-                .display_offset(0, 20)
-                .orientation(Orientation::new().rotate(Rotation::Deg0))
-                .invert_colors(ColorInversion::Inverted)
-                .reset_pin(rst_pin)
-                .init(&mut FreeRtos)
-                .unwrap();
+            let init_display = |di, rst, rotation| -> Result<_, PeripheralError> {
+                let inversion = if PANEL.inverted { ColorInversion::Inverted } else { ColorInversion::Normal };
+                let mut display = Builder::new(ST7789, di)
+                    .display_size(PANEL.width, PANEL.height)
+                    .display_offset(PANEL.offset_x, PANEL.offset_y)
+                    .orientation(Orientation::new().rotate(rotation))
+                    .invert_colors(inversion)
+                    .reset_pin(rst)
+                    .init(&mut FreeRtos)
+                    .map_err(|e| PeripheralError::Display(format!("{:?}", e)))?;
+                if let Some(gamma) = DISPLAY_GAMMA {
+                    // mipidsi has no gamma option, the commands go straight to the panel.
+                    // Set once per init, so it costs nothing per frame.
+                    let dcs = unsafe { display.dcs() };
+                    dcs.send_command(ST7789_PVGAMCTRL, &gamma.positive)
+                        .and_then(|()| dcs.send_command(ST7789_NVGAMCTRL, &gamma.negative))
+                        .map_err(|e| PeripheralError::Display(format!("gamma: {:?}", e)))?;
+                }
+                Ok(display)
+            };
+            let mut rotation = transfer_display_control.rotation();
+            let mut display = match init_display(di, bus.rst, rotation) {
+                Ok(display) => display,
+                Err(e) => return transfer_display_control.stop(&e),
+            };
+            // only used under `lock_panel` from here on, unregistered while it's rebuilt
+            #[cfg(feature = "panic-screen")]
+            unsafe { panic_screen::register_panel(&mut display) };
 
             log::info!("Display initialized successfully in transfer thread!");
             
-            let (ready_lock, cvar) = &*frame_ready_arc;
             let mut frame_count = 0u32;
+            let mut stack_monitor = StackMonitor::for_current_task("Display thread", STACK_WARN_BELOW_BYTES, 120);
             
             loop {
-                // Wait for frame ready signal from Core 0
-                let mut ready = ready_lock.lock().unwrap();
-                while !*ready {
-                    ready = cvar.wait(ready).unwrap();
+                watchdog.feed();
+
+                // Wait for a frame from Core 0
+                let lock_start = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
+                let Some(mut fb) = exchange.take_ready(TRANSFER_IDLE_WAKE) else {
+                    continue;
+                };
+                let lock_acquired = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
+                // a panic elsewhere parks this thread here, so the panic screen gets the panel
+                #[cfg(feature = "panic-screen")]
+                let _panel = panic_screen::lock_panel(|| watchdog.feed());
+
+                let wanted_rotation = transfer_display_control.rotation();
+                if wanted_rotation != rotation {
+                    // mipidsi 0.9's set_orientation only rewrites MADCTL and keeps the
+                    // offset and size of the old orientation, so the display is rebuilt.
+                    // 90/270 swap the offset to the other axis.
+                    log::info!("Transfer thread: Rotating display {:?} -> {:?}", rotation, wanted_rotation);
+                    #[cfg(feature = "panic-screen")]
+                    panic_screen::unregister_panel();
+                    let (di, _model, rst) = display.release();
+                    display = match init_display(di, rst.unwrap(), wanted_rotation) {
+                        Ok(display) => display,
+                        Err(e) => return transfer_display_control.stop(&e),
+                    };
+                    #[cfg(feature = "panic-screen")]
+                    unsafe { panic_screen::register_panel(&mut display) };
+                    rotation = wanted_rotation;
+                    // the reset lost the panel contents, send the whole frame
+                    let full_frame = Rectangle::new(Point::zero(), fb.size());
+                    fb.mark_dirty(full_frame);
                 }
-                *ready = false;
-                drop(ready); // Release lock before doing transfer
                 
                 if frame_count % 120 == 0 {
                     log::info!("Transfer thread: Transferring frame {}...", frame_count);
-                    
-                    // Check stack usage for display thread
-                    unsafe {
-                        let current_task = esp_idf_svc::sys::xTaskGetCurrentTaskHandle();
-                        let stack_high_water_mark = esp_idf_svc::sys::uxTaskGetStackHighWaterMark(current_task);
-                        log::info!("Display thread stack high water mark: {} bytes remaining", stack_high_water_mark * 4);
-                    }
                 }
+                stack_monitor.check();
                 
-                // Measure time to acquire lock and transfer
-                let lock_start = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
-                
-                // Lock framebuffer and transfer to display
-                let fb = fb_arc.lock().unwrap();
-                let lock_acquired = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
-                let bounding_box = Rectangle::new(Point::zero(), fb.size());
+                // This thread owns the front buffer now, no lock held during the transfer.
+                // Only the area drawn to since the last transfer is sent.
+                let dirty = fb.take_dirty();
                 
-                log::trace!("Transfer thread: Transfer start");
+                log::trace!("Transfer thread: Transfer start {:?}", dirty);
                 let transfer_start = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
                 
-                if let Err(e) = display.fill_contiguous(&bounding_box, fb.iter()) {
-                    log::error!("Transfer thread: Display transfer error: {:?}", e);
+                if let Some(area) = dirty {
+                    // mipidsi sets the window (it knows the offsets of the orientation) and
+                    // starts the memory write, the pixels then go to the interface as bytes
+                    // in the panel's order instead of through its per-pixel color conversion
+                    let end = area.bottom_right().unwrap_or(area.top_left);
+                    let mut result = display.set_pixels(
+                        area.top_left.x as u16,
+                        area.top_left.y as u16,
+                        end.x as u16,
+                        end.y as u16,
+                        core::iter::empty::<Rgb565>(),
+                    );
+                    if result.is_ok() {
+                        let dcs = unsafe { display.dcs() };
+                        result = match fb.rows_bytes(area) {
+                            // full rows (whole frames, most scene changes) are one block of memory
+                            Some(bytes) => {
+                                let chunks = bytes.chunks_exact(TRANSFER_CHUNK_BYTES);
+                                let rest = chunks.remainder();
+                                dcs.send_pixels(chunks.map(|chunk| <[u8; TRANSFER_CHUNK_BYTES]>::try_from(chunk).unwrap()))
+                                    .and_then(|()| dcs.send_pixels(rest.chunks_exact(2).map(|pixel| [pixel[0], pixel[1]])))
+                            }
+                            // partial rows go pixel by pixel
+                            None => dcs.send_pixels(fb.region_bytes(area)),
+                        };
+                    }
+                    if let Err(e) = result {
+                        log::error!("Transfer thread: Display transfer error: {:?}", e);
+                    }
                 }
                 
                 let transfer_end = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
                 log::trace!("Transfer thread: Transfer complete");
+                exchange.give_back(fb, transfer_end as u64);
                 
                 // Log timing every 30 frames
                 if frame_count % 30 == 0 {
                     let lock_wait_us = lock_acquired - lock_start;
                     let transfer_us = transfer_end - transfer_start;
                     let total_us = transfer_end - lock_start;
-                    log::info!("Frame timing - Lock wait: {} us, Transfer: {} us ({} ms), Total: {} us ({} ms)", 
+                    log::info!("Frame timing - Frame wait: {} us, Transfer: {} us ({} ms), Total: {} us ({} ms)", 
                         lock_wait_us, transfer_us, transfer_us / 1000, total_us, total_us / 1000);
                 }
                 
@@ -273,107 +663,180 @@ fn main() {
         })
         .expect("Failed to spawn display transfer thread");
 
-    // Initialize the game engine
-    let mut engine = Engine::new();
+    // Initialize the game engine, settings and the pet come from NVS
+    let engine = match EspDefaultNvsPartition::take().and_then(NvsSettingsStore::new) {
+        Ok(store) => Engine::with_settings_store(Box::new(StubBuzzer), Box::new(store)),
+        Err(e) => {
+            log::error!("NVS unavailable, settings won't be kept: {:?}", e);
+            Engine::new()
+        }
+    };
+    // esp_random is a true RNG once the radio is up, before that it's at least seeded from boot noise
+    let seed = unsafe { ((esp_idf_svc::sys::esp_random() as u64) << 32) | esp_idf_svc::sys::esp_random() as u64 };
+    let mut engine = engine.with_seed(seed);
+    engine.set_clock(Box::new(EspClock));
     log::info!("Engine initialized on Core 0");
 
     let mut frame_count = 0u32;
-    let mut button_pressed = false; // Track button state for edge detection
+    let mut stack_monitor = StackMonitor::for_current_task("Main thread", STACK_WARN_BELOW_BYTES, 30);
+    let mut button_debouncer = Debouncer::new(BUTTON_DEBOUNCE_FRAMES);
+    let mut auto_brightness = AutoBrightness::default();
+    let mut backlight_level = 100;
     
-    // Setup for constant FPS timing using vTaskDelayUntil
+    // Setup for constant FPS timing using vTaskDelayUntil, the scheduler lowers the
+    // rate while frames take longer than that
     const TARGET_FPS: u32 = 30;
-    const FRAME_TIME_MS: u32 = 1000 / TARGET_FPS; // 33ms for 30 FPS
+    let mut scheduler = FrameScheduler::new(TARGET_FPS);
     let mut last_wake_time = unsafe { esp_idf_svc::sys::xTaskGetTickCount() };
+    let clock = EspClock;
+    let mut last_frame_ms = clock.now_ms();
+    // with vsync, start of the frame whose transfer is still running
+    let mut transferring_frame_start_us = None;
+    let mut was_charger_connected = false;
+    let watchdog = TaskWatchdog::subscribe_current_task("Main loop");
+    #[cfg(feature = "cpu-stats")]
+    let mut cpu_usage = cpu_usage::CpuUsage::new();
     
     // Main game loop on Core 0 - Rendering only
     log::info!("Starting main game loop on Core 0 with target {} FPS...", TARGET_FPS);
     loop {
+        // fed before the frame's work, so each frame has the whole timeout
+        watchdog.feed();
+        let frame_start_us = unsafe { esp_idf_svc::sys::esp_timer_get_time() } as u64;
+
         // Simple button handling (will be refactored later)
-        // GPIO0 is pulled high, button press pulls it low
-        let button_is_low = button.is_low();
-        
-        if button_is_low && !button_pressed {
-            // Button just pressed
-            log::info!("Button A pressed");
-            engine.input_mut().set_button(Button::A, ButtonState::JustPressed);
-            engine.input_mut().set_button(Button::Up, ButtonState::JustPressed);
-
-            button_pressed = true;
-        } else if button_is_low && button_pressed {
-            // Button held
-            engine.input_mut().set_button(Button::A, ButtonState::Pressed);
-            engine.input_mut().set_button(Button::Up, ButtonState::Pressed);
-        } else if !button_is_low && button_pressed {
-            // Button just released
-            log::info!("Button A released");
-            engine.input_mut().set_button(Button::A, ButtonState::JustReleased);
-            engine.input_mut().set_button(Button::Up, ButtonState::JustReleased);
-
-            button_pressed = false;
-        } else {
-            // Button not pressed
-            engine.input_mut().set_button(Button::A, ButtonState::Released);
-            engine.input_mut().set_button(Button::Up, ButtonState::Released);
+        // GPIO0 is pulled high, button press pulls it low. It stands in for both A and Up.
+        let raw_pressed = button.as_ref().is_some_and(|button| button.is_low());
+        if button_interrupts && BUTTON_EDGE_SEEN.swap(false, Ordering::Relaxed) {
+            button_debouncer.press_edge();
+            // the driver disables the interrupt every time it fires
+            if let Some(Err(e)) = button.as_mut().map(|button| button.enable_interrupt()) {
+                log::warn!("Failed to re-enable the button interrupt: {:?}", e);
+            }
         }
+        let button_is_low = button_debouncer.update(raw_pressed);
+        if button_is_low != engine.input().is_pressed(Button::A) {
+            log::info!("Button A {}", if button_is_low { "pressed" } else { "released" });
+        }
+        engine.input_mut().update_button_level(Button::A, button_is_low);
+        engine.input_mut().update_button_level(Button::Up, button_is_low);
+        let charger_connected = power.is_charger_connected();
+        if charger_connected != was_charger_connected {
+            log::info!("Charger {}", if charger_connected { "connected" } else { "disconnected" });
+            was_charger_connected = charger_connected;
+        }
+        engine.input_mut().set_charging(power.is_charging());
+        let now_ms = clock.now_ms();
+        let dt_ms = now_ms.wrapping_sub(last_frame_ms);
+        last_frame_ms = now_ms;
+        engine.input_mut().tick(dt_ms);
         
         // Update game state
         log::trace!("Core 0: Engine update");
-        let update_start = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
-        engine.update();
-        let update_end = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
+        log_capture::drain_into(engine.logs_mut());
+        engine.update(dt_ms);
+        if engine.power_off_requested() {
+            log::info!("Battery empty, powering off");
+            engine.shutdown();
+            if let Some(pwm) = &mut backlight_pwm {
+                set_backlight(pwm, 0);
+            }
+            // a press boots it again, it powers off once more if the battery wasn't charged
+            power.enter_deep_sleep(true);
+        }
+
+        // Follow the ambient light once the light sensor is reporting, full brightness until then.
+        // The settings are checked every frame so changes on the settings screen show right away.
+        let settings = engine.settings();
+        auto_brightness.set_manual((!settings.auto_brightness_enabled).then_some(settings.brightness));
+        if auto_brightness.is_manual()
+            || engine.input().get_sensor_state(SensorType::LightSensor) == SensorState::Normal
+        {
+            let level = auto_brightness.update(engine.input().get_sensor_value(SensorType::LightSensor));
+            if level != backlight_level {
+                if let Some(pwm) = &mut backlight_pwm {
+                    set_backlight(pwm, level);
+                }
+                backlight_level = level;
+            }
+        }
+
+        // Nothing changed on screen, the panel keeps showing the last frame and
+        // neither rendering nor the SPI transfer is needed. Without a display there's
+        // nothing to render for.
+        let redraw = engine.needs_redraw() && display_control.is_running();
+
+        if FRAME_PACING == FramePacing::Vsync && redraw {
+            let done_us = shared_fb.wait_for_transfer();
+            if let Some(start_us) = transferring_frame_start_us.take() {
+                if let Some(fps) = scheduler.end_frame(start_us, done_us) {
+                    log::info!("Core 0: frame rate now {} FPS", fps);
+                }
+            }
+        }
 
         // Render to shared framebuffer (fast - all in RAM)
         log::trace!("Core 0: Render start");
-        let render_start = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
-        let lock_wait_start = render_start;
+        let lock_wait_start = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
         
         {
             let mut fb = shared_fb.lock();
             let lock_acquired = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
             
-            if let Err(e) = engine.render(&mut *fb) {
-                log::error!("Core 0: Render error: {:?}", e);
+            if redraw {
+                // the framebuffer can't fail, only scenes can and the engine logs those
+                let _ = engine.render(&mut *fb);
             }
             
-            let render_end = unsafe { esp_idf_svc::sys::esp_timer_get_time() };
-            
             // Log timing every 30 frames
             if frame_count % 30 == 0 {
-                let update_us = update_end - update_start;
+                let stats = engine.stats();
                 let lock_wait_us = lock_acquired - lock_wait_start;
-                let render_us = render_end - lock_acquired;
-                let total_frame_us = render_end - update_start;
                 
                 log::info!("Core 0: Rendering frame {}...", frame_count);
-                log::info!("Core 0 timing - Update: {} us, Lock wait: {} us, Render: {} us, Total: {} us ({} ms)", 
-                    update_us, lock_wait_us, render_us, total_frame_us, total_frame_us / 1000);
-                
-                // Check stack usage for main thread
-                unsafe {
-                    let current_task = esp_idf_svc::sys::xTaskGetCurrentTaskHandle();
-                    let stack_high_water_mark = esp_idf_svc::sys::uxTaskGetStackHighWaterMark(current_task);
-                    log::info!("Main thread stack high water mark: {} bytes remaining", stack_high_water_mark * 4);
+                log::info!("Core 0 timing - Update: {} us, Lock wait: {} us, Render: {} us, {}.{:02} FPS", 
+                    stats.update_us(), lock_wait_us, stats.render_us(), stats.fps_x100() / 100, stats.fps_x100() % 100);
+                log::info!("Core 0: {} frames dropped ({:?} policy), running at {} FPS", shared_fb.frames_dropped(), shared_fb.policy(), scheduler.fps());
+                #[cfg(feature = "cpu-stats")]
+                match cpu_usage.sample() {
+                    Some([core0, core1]) => log::info!("CPU busy - Core 0: {}%, Core 1: {}%", core0, core1),
+                    None => log::warn!("CPU usage unavailable, are the FreeRTOS run time stats enabled?"),
                 }
             }
         } // Lock released here
+        stack_monitor.check();
         
-        // Signal Core 1 that frame is ready for transfer
-        log::trace!("Core 0: Signaling frame ready");
-        shared_fb.signal_frame_ready();
+        // Signal Core 1 that frame is ready for transfer, or that a skipped one still is
+        // once the scene went static
+        if redraw || shared_fb.has_unsent_changes() {
+            log::trace!("Core 0: Signaling frame ready");
+            shared_fb.signal_frame_ready();
+        }
         
         frame_count = frame_count.wrapping_add(1);
         
         // Constant FPS timing using vTaskDelayUntil
         // This ensures consistent frame timing regardless of execution time
-        // NOTE: If display transfer on Core 1 is slower than rendering, the framebuffer
-        // lock above will block Core 0 until transfer completes. This prevents tearing
-        // but may cause frame drops if transfer takes longer than FRAME_TIME_MS.
-        // To avoid this, consider double buffering or making the lock non-blocking.
+        // NOTE: Rendering goes to the back buffer, so a slow transfer on Core 1 doesn't
+        // block Core 0. If the transfer takes longer than a frame, frames get dropped
+        // (or Core 0 waits with FrameDropPolicy::Block), see frames_dropped(). With
+        // FramePacing::Vsync, Core 0 waits for the transfer before rendering instead.
+        let frame_end_us = unsafe { esp_idf_svc::sys::esp_timer_get_time() } as u64;
+        let frame_ms = (frame_end_us - frame_start_us) / 1000;
+        if WATCHDOG_ENABLED && frame_ms > WATCHDOG_WARN_MS {
+            tama_core::notice!("Frame took {} ms, the watchdog resets after {} ms", frame_ms, WATCHDOG_TIMEOUT_MS);
+        }
+        if FRAME_PACING == FramePacing::Vsync && redraw {
+            // counted once its transfer ended, before the next render
+            transferring_frame_start_us = Some(frame_start_us);
+        } else if let Some(fps) = scheduler.end_frame(frame_start_us, frame_end_us) {
+            log::info!("Core 0: frame rate now {} FPS", fps);
+        }
         
         // Convert milliseconds to FreeRTOS ticks
         // FreeRTOS tick rate is typically 100 Hz (10ms per tick) or 1000 Hz (1ms per tick)
         // We use pdMS_TO_TICKS macro equivalent: (ms * configTICK_RATE_HZ) / 1000
-        let ticks_to_wait = (FRAME_TIME_MS * esp_idf_svc::sys::configTICK_RATE_HZ) / 1000;
+        let ticks_to_wait = (scheduler.frame_time_ms() * esp_idf_svc::sys::configTICK_RATE_HZ) / 1000;
         
         unsafe {
             esp_idf_svc::sys::xTaskDelayUntil(
@@ -0,0 +1,208 @@
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    mono_font::{ascii::{FONT_6X10, FONT_8X13}, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use esp_idf_hal::delay::FreeRtos;
+use tama_core::log_buffer::LogEntry;
+
+// Entries kept for the panic screen, only errors, warnings and notices, see log_capture
+pub const PANIC_LOG_LINES: usize = 8;
+// How long the report stays up before the panic carries on and resets the chip
+const PANIC_SCREEN_HOLD_MS: u32 = 10_000;
+// A whole frame takes a few tens of ms to send, the display thread lets go of the panel
+// after that. If it doesn't (it's stuck in the SPI driver...), nothing is drawn.
+const PANEL_RELEASE_TIMEOUT: Duration = Duration::from_millis(500);
+const CHAR_WIDTH: i32 = 6;
+const LINE_HEIGHT: i32 = 11;
+// the panic message gets at most this many lines, the logs get the rest
+const MESSAGE_MAX_LINES: usize = 8;
+
+static PANICKING: AtomicBool = AtomicBool::new(false);
+// Held by the display thread while it uses the panel, the panic hook takes it to draw
+static PANEL_LOCK: Mutex<()> = Mutex::new(());
+static PANEL: Mutex<Option<RegisteredPanel>> = Mutex::new(None);
+
+// The display thread's display, type-erased so this doesn't need to name mipidsi's types
+struct RegisteredPanel {
+    display: *mut (),
+    draw: unsafe fn(*mut (), &Report),
+    owner: ThreadId,
+}
+
+// Only dereferenced by the panic hook, while the owner holds off (see `lock_panel`)
+// or is the thread panicking
+unsafe impl Send for RegisteredPanel {}
+
+struct Report {
+    message: String,
+    logs: Vec<LogEntry>,
+}
+
+// Best-effort crash screen: when anything panics, the panic message and the last
+// errors/warnings/notices are drawn straight to the panel, bypassing the engine and the
+// frame exchange, which may be what broke. Call once at startup, the previous hook
+// (printing to the console) still runs first.
+pub fn install() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        // a panic while drawing the report, leave it to the default handling
+        if PANICKING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let report = Report {
+            message: info.to_string(),
+            logs: crate::log_capture::recent_for_panic(),
+        };
+        if draw(&report) {
+            for _ in 0..PANIC_SCREEN_HOLD_MS / 100 {
+                // the task may not be watched, then this only complains
+                if !cfg!(feature = "no-watchdog") {
+                    unsafe { esp_idf_svc::sys::esp_task_wdt_reset() };
+                }
+                FreeRtos::delay_ms(100);
+            }
+        }
+    }));
+}
+
+// Makes `display` what the panic screen is drawn on, called by the thread owning it.
+//
+// Safety: `display` has to stay where it is until `unregister_panel`, and the owner
+// may only use it while it holds the guard from `lock_panel`.
+pub unsafe fn register_panel<D>(display: &mut D)
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: core::fmt::Debug,
+{
+    let panel = RegisteredPanel {
+        display: display as *mut D as *mut (),
+        draw: draw_erased::<D>,
+        owner: thread::current().id(),
+    };
+    *PANEL.lock().unwrap_or_else(PoisonError::into_inner) = Some(panel);
+}
+
+// Before the registered display is moved or dropped (rebuilt for a rotation...)
+pub fn unregister_panel() {
+    *PANEL.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+// Hold while using the registered display. Once some other thread panicked this never
+// returns, the caller's task only keeps feeding its watchdog so the report stays up.
+pub fn lock_panel(feed_watchdog: impl Fn()) -> MutexGuard<'static, ()> {
+    loop {
+        if PANICKING.load(Ordering::SeqCst) {
+            loop {
+                feed_watchdog();
+                FreeRtos::delay_ms(100);
+            }
+        }
+        match PANEL_LOCK.try_lock() {
+            Ok(guard) => return guard,
+            Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner(),
+            // only the panic hook takes it from another thread, next round parks
+            Err(TryLockError::WouldBlock) => FreeRtos::delay_ms(1),
+        }
+    }
+}
+
+// false when there's no panel to draw on
+fn draw(report: &Report) -> bool {
+    let panel = match PANEL.try_lock() {
+        Ok(panel) => panel,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return false,
+    };
+    let Some(panel) = panel.as_ref() else {
+        return false;
+    };
+
+    // the owner panicking may be in the middle of using the panel, it's drawn over anyway.
+    // Any other thread waits for the owner to finish its transfer and park.
+    let _guard = if panel.owner == thread::current().id() {
+        None
+    } else {
+        let deadline = Instant::now() + PANEL_RELEASE_TIMEOUT;
+        loop {
+            match PANEL_LOCK.try_lock() {
+                Ok(guard) => break Some(guard),
+                Err(TryLockError::Poisoned(poisoned)) => break Some(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => FreeRtos::delay_ms(5),
+                Err(TryLockError::WouldBlock) => {
+                    log::error!("Display thread didn't release the panel, no panic screen");
+                    return false;
+                }
+            }
+        }
+    };
+
+    unsafe { (panel.draw)(panel.display, report) };
+    true
+}
+
+unsafe fn draw_erased<D>(display: *mut (), report: &Report)
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: core::fmt::Debug,
+{
+    let display = &mut *(display as *mut D);
+    if let Err(e) = draw_report(display, report) {
+        log::error!("Panic screen not drawn: {:?}", e);
+    }
+}
+
+fn draw_report<D>(target: &mut D, report: &Report) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    target.clear(Rgb565::BLACK)?;
+    let size = target.bounding_box().size;
+    let line_chars = (size.width as i32 / CHAR_WIDTH - 1).max(1) as usize;
+    let max_lines = ((size.height as i32 - 20) / LINE_HEIGHT).max(0) as usize;
+
+    let title_style = MonoTextStyle::new(&FONT_8X13, Rgb565::RED);
+    Text::with_baseline("PANIC", Point::new(4, 4), title_style, Baseline::Top).draw(target)?;
+
+    // the message wrapped at the screen edge, "panicked at <file:line:column>:" comes first
+    let mut lines: Vec<(String, Rgb565)> = Vec::new();
+    for message_line in report.message.lines() {
+        let chars: Vec<char> = message_line.chars().collect();
+        for chunk in chars.chunks(line_chars) {
+            if lines.len() < MESSAGE_MAX_LINES {
+                lines.push((chunk.iter().collect(), Rgb565::WHITE));
+            }
+        }
+    }
+
+    // newest entries last, above them as many older ones as still fit
+    let log_room = max_lines.saturating_sub(lines.len());
+    let skip = report.logs.len().saturating_sub(log_room);
+    for entry in &report.logs[skip..] {
+        let mut line = format!("{} ", entry.level.prefix());
+        if !entry.tag.is_empty() {
+            line.push_str(&entry.tag);
+            line.push(' ');
+        }
+        line.extend(entry.message.chars());
+        let line: String = line.chars().take(line_chars).collect();
+        lines.push((line, entry.level.color()));
+    }
+
+    for (i, (line, color)) in lines.iter().enumerate() {
+        let position = Point::new(4, 20 + i as i32 * LINE_HEIGHT);
+        let style = MonoTextStyle::new(&FONT_6X10, *color);
+        Text::with_baseline(line, position, style, Baseline::Top).draw(target)?;
+    }
+
+    Ok(())
+}
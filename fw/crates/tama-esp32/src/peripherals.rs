@@ -0,0 +1,33 @@
+use std::fmt;
+
+use esp_idf_svc::sys::EspError;
+
+// A part of the hardware that didn't come up at boot. main logs it as a NOTICE and runs
+// without that part instead of panicking, so the rest (and the pet's saves) keep working.
+#[derive(Debug)]
+pub enum PeripheralError {
+    Gpio { pin: &'static str, error: EspError },
+    Spi(EspError),
+    // the backlight PWM
+    Ledc(EspError),
+    // mipidsi's init error, it only has a Debug representation
+    Display(String),
+}
+
+impl fmt::Display for PeripheralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeripheralError::Gpio { pin, error } => write!(f, "{} setup failed: {}", pin, error),
+            PeripheralError::Spi(error) => write!(f, "SPI setup failed: {}", error),
+            PeripheralError::Ledc(error) => write!(f, "LEDC setup failed: {}", error),
+            PeripheralError::Display(error) => write!(f, "display init failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PeripheralError {}
+
+// For `.map_err(gpio_error("GPIO0"))`
+pub fn gpio_error(pin: &'static str) -> impl FnOnce(EspError) -> PeripheralError {
+    move |error| PeripheralError::Gpio { pin, error }
+}
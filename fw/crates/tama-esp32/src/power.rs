@@ -0,0 +1,95 @@
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_hal::gpio::{AnyInputPin, Gpio5, Input, Output, PinDriver};
+use esp_idf_svc::sys::{esp, EspError};
+
+// GPIO0, low while the button is pressed
+const WAKE_BUTTON_GPIO: i32 = 0;
+const WAKE_BUTTON_LEVEL: i32 = 0;
+const POWER_HOLD_GPIO: i32 = 5;
+// the notice goes out over the UART before the CPU stops
+const LOG_FLUSH_MS: u32 = 20;
+
+// The BQ24073's open-drain status outputs, both pulled low while active
+pub struct ChargerPins {
+    // /CHG, low while the battery is charging, released when it's full or there's no input
+    pub charge_status: PinDriver<'static, AnyInputPin, Input>,
+    // /PGOOD, low while USB power is in range
+    pub power_good: PinDriver<'static, AnyInputPin, Input>,
+}
+
+// Sleep modes, the GPIO5 load switch that powers the display and sensors, and the charger
+pub struct PowerControl {
+    // None when the pin couldn't be set up, the peripherals then stay powered in deep sleep
+    power_hold: Option<PinDriver<'static, Gpio5, Output>>,
+    // None when the pins couldn't be set up, then it never looks like it's charging
+    charger: Option<ChargerPins>,
+}
+
+impl PowerControl {
+    pub fn new(power_hold: Option<PinDriver<'static, Gpio5, Output>>, charger: Option<ChargerPins>) -> Self {
+        Self { power_hold, charger }
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.charger.as_ref().is_some_and(|charger| charger.charge_status.is_low())
+    }
+
+    pub fn is_charger_connected(&self) -> bool {
+        self.charger.as_ref().is_some_and(|charger| charger.power_good.is_low())
+    }
+
+    // While charging the battery voltage reads higher than the charge it holds. Once it's
+    // full the charger only tops it up, and the voltage can be trusted again.
+    #[allow(dead_code)] // the engine works it out from `is_charging`
+    pub fn is_voltage_reliable(&self) -> bool {
+        !self.is_charging()
+    }
+
+    // Cuts the peripheral power and sleeps until the button is pressed, or until a reset
+    // without `wake_on_button`. Waking up from deep sleep boots from scratch.
+    pub fn enter_deep_sleep(&mut self, wake_on_button: bool) -> ! {
+        tama_core::notice!("Entering deep sleep, {}", if wake_on_button { "the button wakes it" } else { "only a reset wakes it" });
+
+        if let Some(power_hold) = &mut self.power_hold {
+            // pins aren't driven in deep sleep, the hold keeps the switch off
+            let result = power_hold.set_low().and_then(|()| unsafe {
+                esp!(esp_idf_svc::sys::gpio_hold_en(POWER_HOLD_GPIO))?;
+                esp_idf_svc::sys::gpio_deep_sleep_hold_en();
+                Ok(())
+            });
+            if let Err(e) = result {
+                log::warn!("Failed to switch the peripherals off: {:?}", e);
+            }
+        }
+        if wake_on_button {
+            if let Err(e) = enable_button_wakeup() {
+                log::error!("Failed to set the button as wakeup source: {:?}", e);
+            }
+        }
+
+        FreeRtos::delay_ms(LOG_FLUSH_MS);
+        unsafe { esp_idf_svc::sys::esp_deep_sleep_start() }
+    }
+
+    // Stops both cores for `duration_ms` and carries on where it left off, RAM and
+    // peripherals are kept
+    #[allow(dead_code)] // nothing idles in light sleep yet
+    pub fn enter_light_sleep(&mut self, duration_ms: u32) -> Result<(), EspError> {
+        tama_core::notice!("Entering light sleep for {} ms", duration_ms);
+        FreeRtos::delay_ms(LOG_FLUSH_MS);
+        unsafe {
+            esp!(esp_idf_svc::sys::esp_sleep_enable_timer_wakeup(duration_ms as u64 * 1000))?;
+            esp!(esp_idf_svc::sys::esp_light_sleep_start())
+        }
+    }
+}
+
+// EXT0 on the button pin, which is an RTC GPIO. Its pull-up is kept on in sleep, the
+// external one may be behind the load switch.
+fn enable_button_wakeup() -> Result<(), EspError> {
+    unsafe {
+        esp!(esp_idf_svc::sys::rtc_gpio_pullup_en(WAKE_BUTTON_GPIO))?;
+        esp!(esp_idf_svc::sys::rtc_gpio_pulldown_dis(WAKE_BUTTON_GPIO))?;
+        esp!(esp_idf_svc::sys::esp_sleep_enable_ext0_wakeup(WAKE_BUTTON_GPIO, WAKE_BUTTON_LEVEL))
+    }
+}
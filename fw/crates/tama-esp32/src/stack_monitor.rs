@@ -0,0 +1,50 @@
+use esp_idf_svc::sys::{uxTaskGetStackHighWaterMark, xTaskGetCurrentTaskHandle, TaskHandle_t};
+
+// Stack never touched by a task, below this a deeper call chain may overflow it
+pub const STACK_WARN_BELOW_BYTES: u32 = 512;
+
+// Watches the stack of one task. `check` is cheap enough to call every loop iteration,
+// it only reads the high water mark every `interval` calls. The NOTICE comes each time the
+// free stack reaches a new low under the threshold, not on every check.
+pub struct StackMonitor {
+    task: TaskHandle_t,
+    name: &'static str,
+    warn_below_bytes: u32,
+    interval: u32,
+    calls: u32,
+    lowest_bytes: u32,
+}
+
+impl StackMonitor {
+    // Watches the task calling this
+    pub fn for_current_task(name: &'static str, warn_below_bytes: u32, interval: u32) -> Self {
+        Self {
+            task: unsafe { xTaskGetCurrentTaskHandle() },
+            name,
+            warn_below_bytes,
+            interval: interval.max(1),
+            calls: 0,
+            lowest_bytes: u32::MAX,
+        }
+    }
+
+    // Free stack in bytes that was never used, ESP-IDF counts stack in bytes
+    pub fn remaining_bytes(&self) -> u32 {
+        unsafe { uxTaskGetStackHighWaterMark(self.task) }
+    }
+
+    pub fn check(&mut self) {
+        let due = self.calls % self.interval == 0;
+        self.calls = self.calls.wrapping_add(1);
+        if !due {
+            return;
+        }
+
+        let remaining = self.remaining_bytes();
+        log::info!("{} stack high water mark: {} bytes remaining", self.name, remaining);
+        if remaining < self.warn_below_bytes && remaining < self.lowest_bytes {
+            tama_core::notice!("{} stack nearly full, {} bytes left", self.name, remaining);
+        }
+        self.lowest_bytes = self.lowest_bytes.min(remaining);
+    }
+}